@@ -0,0 +1,136 @@
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+/// The address of a Unix socket.
+///
+/// This is similar to [`std::os::unix::net::SocketAddr`], but it can be obtained from a received message.
+/// Note that connected seqpacket sockets usually receive messages without an address:
+/// in that case the address is unnamed.
+#[derive(Clone)]
+pub struct SocketAddr {
+	pub(crate) address: libc::sockaddr_un,
+	pub(crate) len: libc::socklen_t,
+}
+
+impl SocketAddr {
+	/// Create an unnamed address.
+	pub(crate) fn new_unnamed() -> Self {
+		Self {
+			address: unsafe { core::mem::zeroed() },
+			len: 0,
+		}
+	}
+
+	/// Create an abstract address with the given name.
+	///
+	/// The name should not include the leading null byte: it is added by this function.
+	/// Abstract addresses do not exist in the file system,
+	/// so they do not leave stale socket files behind when the process exits.
+	///
+	/// If the name does not fit in a `sockaddr_un`, an error of kind [`std::io::ErrorKind::InvalidInput`] is returned.
+	/// This is only available on Linux and Android.
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	pub fn from_abstract_name(name: &[u8]) -> std::io::Result<Self> {
+		let mut address = Self::new_unnamed();
+		let max_len = address.address.sun_path.len() - 1;
+		if name.len() > max_len {
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidInput,
+				format!(
+					"abstract socket name of {} bytes exceeds the maximum of {} bytes",
+					name.len(),
+					max_len
+				),
+			));
+		}
+		address.address.sun_family = libc::AF_UNIX as _;
+		for (dest, &byte) in address.address.sun_path[1..].iter_mut().zip(name) {
+			*dest = byte as _;
+		}
+		address.len = (sun_path_offset(&address.address) + 1 + name.len()) as libc::socklen_t;
+		Ok(address)
+	}
+
+	/// Create an address that makes `bind()` pick a unique abstract address automatically.
+	///
+	/// This address only holds the address family, which triggers the autobind feature of Linux.
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	pub(crate) fn autobind() -> Self {
+		let mut address = Self::new_unnamed();
+		address.address.sun_family = libc::AF_UNIX as _;
+		address.len = sun_path_offset(&address.address) as libc::socklen_t;
+		address
+	}
+
+	/// Get the raw bytes of the `sun_path` field, limited to the length reported by the kernel.
+	fn path_bytes(&self) -> &[u8] {
+		let offset = sun_path_offset(&self.address);
+		let len = (self.len as usize)
+			.saturating_sub(offset)
+			.min(self.address.sun_path.len());
+		unsafe { core::slice::from_raw_parts(self.address.sun_path.as_ptr().cast(), len) }
+	}
+
+	/// Check if the address is unnamed.
+	pub fn is_unnamed(&self) -> bool {
+		matches!(self.address_kind(), AddressKind::Unnamed)
+	}
+
+	/// Get the path of the address, if it is a pathname address.
+	pub fn as_pathname(&self) -> Option<&Path> {
+		match self.address_kind() {
+			AddressKind::Pathname(path) => Some(path),
+			_ => None,
+		}
+	}
+
+	/// Get the name of the address, if it is an abstract address.
+	///
+	/// The returned name does not include the leading null byte.
+	/// This is only available on Linux and Android.
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	pub fn as_abstract_name(&self) -> Option<&[u8]> {
+		match self.address_kind() {
+			AddressKind::Abstract(name) => Some(name),
+			_ => None,
+		}
+	}
+
+	fn address_kind(&self) -> AddressKind<'_> {
+		let path = self.path_bytes();
+		if path.is_empty() {
+			AddressKind::Unnamed
+		} else if path[0] == 0 {
+			if cfg!(any(target_os = "android", target_os = "linux")) {
+				AddressKind::Abstract(&path[1..])
+			} else {
+				AddressKind::Unnamed
+			}
+		} else {
+			// Trim the trailing null byte and anything after it.
+			let len = path.iter().position(|&byte| byte == 0).unwrap_or(path.len());
+			AddressKind::Pathname(Path::new(OsStr::from_bytes(&path[..len])))
+		}
+	}
+}
+
+impl std::fmt::Debug for SocketAddr {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self.address_kind() {
+			AddressKind::Unnamed => write!(f, "(unnamed)"),
+			AddressKind::Pathname(path) => write!(f, "{:?} (pathname)", path),
+			AddressKind::Abstract(name) => write!(f, "\"{}\" (abstract)", name.escape_ascii()),
+		}
+	}
+}
+
+enum AddressKind<'a> {
+	Unnamed,
+	Pathname(&'a Path),
+	Abstract(&'a [u8]),
+}
+
+fn sun_path_offset(address: &libc::sockaddr_un) -> usize {
+	address.sun_path.as_ptr() as usize - (address as *const libc::sockaddr_un as usize)
+}
@@ -9,62 +9,192 @@ use core::marker::PhantomData;
 use core::mem::{size_of, zeroed};
 use core::ptr::read_unaligned;
 use core::slice::from_raw_parts;
-use std::os::unix::io::RawFd;
+use std::any::Any;
+use std::os::unix::io::{FromRawFd, OwnedFd, RawFd};
+use std::sync::RwLock;
 
-#[cfg(any(target_os = "android", target_os = "linux",))]
+#[cfg(any(
+	target_os = "android",
+	target_os = "linux",
+	target_os = "freebsd",
+	target_os = "dragonfly"
+))]
 use libc::{gid_t, pid_t, uid_t};
 
+/// The native credential struct of a credentials control message.
+#[cfg(any(target_os = "android", target_os = "linux"))]
+type RawCred = libc::ucred;
+
+/// The native credential struct of a credentials control message.
+#[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
+type RawCred = libc::cmsgcred;
+
+/// The control message type for credentials.
+#[cfg(any(target_os = "android", target_os = "linux"))]
+const SCM_CREDS: libc::c_int = libc::SCM_CREDENTIALS;
+
+/// The control message type for credentials.
+#[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
+const SCM_CREDS: libc::c_int = libc::SCM_CREDS;
+
+/// The maximum number of file descriptors that Linux accepts in a single message (`SCM_MAX_FD`).
+///
+/// Other platforms may allow more, but this is used as upper limit for internally allocated buffers.
+pub(crate) const MAX_FDS_PER_MESSAGE: usize = 253;
+
+/// Get the buffer space needed for a control message holding `count` file descriptors.
+pub(crate) fn fds_space(count: usize) -> usize {
+	unsafe { libc::CMSG_SPACE((count * size_of::<RawFd>()) as u32) as usize }
+}
+
+/// Get the buffer space needed for a control message of `count` elements of type `T`.
+///
+/// Returns `None` if the size would overflow.
+fn checked_cmsg_space<T>(count: usize) -> Option<usize> {
+	let data_len = count.checked_mul(size_of::<T>())?;
+	let data_len = u32::try_from(data_len).ok()?;
+	// CMSG_SPACE() adds the header and padding, which is less than twice the header size.
+	data_len.checked_add(2 * size_of::<libc::cmsghdr>() as u32)?;
+	Some(unsafe { libc::CMSG_SPACE(data_len) as usize })
+}
+
+/// Get the buffer space needed to add `count` file descriptors with [`SocketAncillary::add_fds()`].
+///
+/// A buffer of this size is guaranteed to fit exactly one `SCM_RIGHTS` control message with `count` file descriptors:
+///
+/// ```
+/// use tokio_seqpacket::ancillary::{cmsg_space_for_fds, SocketAncillary};
+/// let mut buffer = vec![0u8; cmsg_space_for_fds(4)];
+/// let mut ancillary = SocketAncillary::new(&mut buffer);
+/// assert!(ancillary.add_fds(&[0, 1, 2, 3]));
+/// ```
+///
+/// # Panics
+/// This function panics if the size overflows.
+pub fn cmsg_space_for_fds(count: usize) -> usize {
+	checked_cmsg_space::<RawFd>(count).expect("control message size overflows")
+}
+
+/// Get the buffer space needed to add `count` credentials.
+///
+/// Since the kernel only accepts a single credential per `SCM_CREDENTIALS` (or `SCM_CREDS`) control message,
+/// this is the space for `count` separate control messages of one credential each.
+/// Note that [`SocketAncillary::add_creds()`] can only add one credential at a time.
+///
+/// # Panics
+/// This function panics if the size overflows.
+#[cfg(any(
+	target_os = "android",
+	target_os = "linux",
+	target_os = "freebsd",
+	target_os = "dragonfly"
+))]
+pub fn cmsg_space_for_creds(count: usize) -> usize {
+	checked_cmsg_space::<RawCred>(1)
+		.and_then(|space| space.checked_mul(count))
+		.expect("control message size overflows")
+}
+
+/// A kind of control message, used to compute buffer sizes with [`cmsg_space()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CmsgKind {
+	/// A `SCM_RIGHTS` control message with the given number of file descriptors.
+	Fds(usize),
+
+	/// The given number of `SCM_CREDENTIALS` control messages.
+	///
+	/// See [`cmsg_space_for_creds()`].
+	#[cfg(any(
+		target_os = "android",
+		target_os = "linux",
+		target_os = "freebsd",
+		target_os = "dragonfly"
+	))]
+	Creds(usize),
+}
+
+/// Get the buffer space needed for a combination of control messages.
+///
+/// This is the sum of [`cmsg_space_for_fds()`] and [`cmsg_space_for_creds()`] for all elements of `kinds`.
+///
+/// # Panics
+/// This function panics if the size overflows.
+pub fn cmsg_space(kinds: &[CmsgKind]) -> usize {
+	kinds
+		.iter()
+		.try_fold(0usize, |total, kind| {
+			let space = match *kind {
+				CmsgKind::Fds(count) => checked_cmsg_space::<RawFd>(count)?,
+				#[cfg(any(
+					target_os = "android",
+					target_os = "linux",
+					target_os = "freebsd",
+					target_os = "dragonfly"
+				))]
+				CmsgKind::Creds(count) => checked_cmsg_space::<RawCred>(1)?.checked_mul(count)?,
+			};
+			total.checked_add(space)
+		})
+		.expect("control message size overflows")
+}
+
 fn add_to_ancillary_data<T>(
 	buffer: &mut [u8],
 	length: &mut usize,
 	source: &[T],
 	cmsg_level: libc::c_int,
 	cmsg_type: libc::c_int,
-) -> bool {
-	let source_len = if let Some(source_len) = source.len().checked_mul(size_of::<T>()) {
-		if let Ok(source_len) = u32::try_from(source_len) {
-			source_len
-		} else {
-			return false;
-		}
-	} else {
-		return false;
-	};
+) -> Result<(), AncillaryAddError> {
+	let source_len = source
+		.len()
+		.checked_mul(size_of::<T>())
+		.and_then(|source_len| u32::try_from(source_len).ok())
+		.ok_or(AncillaryAddError::LengthOverflow)?;
 
 	unsafe {
 		let additional_space = libc::CMSG_SPACE(source_len) as usize;
 
-		let new_length = if let Some(new_length) = additional_space.checked_add(*length) {
-			new_length
-		} else {
-			return false;
-		};
+		let new_length = additional_space
+			.checked_add(*length)
+			.ok_or(AncillaryAddError::LengthOverflow)?;
 
 		if new_length > buffer.len() {
-			return false;
+			return Err(AncillaryAddError::BufferFull {
+				needed: additional_space,
+				available: buffer.len().saturating_sub(*length),
+			});
 		}
 
 		for byte in &mut buffer[*length..new_length] {
 			*byte = 0;
 		}
 
-		*length = new_length;
-
 		let mut msg: libc::msghdr = zeroed();
 		msg.msg_control = buffer.as_mut_ptr().cast();
-		msg.msg_controllen = *length as _;
+		msg.msg_controllen = new_length as _;
 
 		let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
 		let mut previous_cmsg = cmsg;
-		while !cmsg.is_null() && (*cmsg).cmsg_len > 0 {
+		while !cmsg.is_null() {
 			previous_cmsg = cmsg;
 			cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+
+			// Most operating systems, but not Linux, return the previous pointer when its length is zero.
+			// The new (zeroed) header is the last one in the buffer, so stop there.
+			if cmsg == previous_cmsg {
+				break;
+			}
 		}
 
-		if previous_cmsg.is_null() {
-			return false;
+		// The walk must end at the new (zeroed) header right after the existing messages.
+		// Anything else means the existing messages in the buffer have invalid lengths.
+		if previous_cmsg.is_null() || previous_cmsg as usize - buffer.as_ptr() as usize != *length {
+			return Err(AncillaryAddError::CmsgChainCorrupt);
 		}
 
+		*length = new_length;
+
 		(*previous_cmsg).cmsg_level = cmsg_level;
 		(*previous_cmsg).cmsg_type = cmsg_type;
 		(*previous_cmsg).cmsg_len = libc::CMSG_LEN(source_len) as _;
@@ -73,7 +203,7 @@ fn add_to_ancillary_data<T>(
 
 		libc::memcpy(data, source.as_ptr().cast(), source_len as usize);
 	}
-	true
+	Ok(())
 }
 
 struct AncillaryDataIter<'a, T> {
@@ -81,6 +211,15 @@ struct AncillaryDataIter<'a, T> {
 	phantom: PhantomData<T>,
 }
 
+impl<T> Clone for AncillaryDataIter<'_, T> {
+	fn clone(&self) -> Self {
+		Self {
+			data: self.data,
+			phantom: PhantomData,
+		}
+	}
+}
+
 impl<'a, T> AncillaryDataIter<'a, T> {
 	/// Create `AncillaryDataIter` struct to iterate through the data unit in the control message.
 	///
@@ -112,49 +251,292 @@ impl<'a, T> Iterator for AncillaryDataIter<'a, T> {
 }
 
 /// Unix credential.
-#[cfg(any(target_os = "android", target_os = "linux",))]
+///
+/// On Linux and Android, this holds a `struct ucred` as used in `SCM_CREDENTIALS` messages.
+///
+/// On FreeBSD and DragonFly, this holds a `struct cmsgcred` as used in `SCM_CREDS` messages.
+/// The UID and GID are the effective user and group ID of the sender: `cmcred_euid` and the first entry of `cmcred_groups`.
+/// The real IDs and supplementary groups are not exposed.
+/// When sending, the kernel fills in all fields itself, so the values set on a credential have no effect there.
+///
+/// Other platforms, including macOS, do not support passing credentials as ancillary data.
+/// Use [`UnixSeqpacket::peer_cred()`](crate::UnixSeqpacket::peer_cred) there instead.
+#[cfg(any(
+	target_os = "android",
+	target_os = "linux",
+	target_os = "freebsd",
+	target_os = "dragonfly"
+))]
 #[derive(Clone)]
-pub struct SocketCred(libc::ucred);
+pub struct SocketCred(RawCred);
 
-#[cfg(any(target_os = "android", target_os = "linux",))]
+#[cfg(any(
+	target_os = "android",
+	target_os = "linux",
+	target_os = "freebsd",
+	target_os = "dragonfly"
+))]
 impl SocketCred {
 	/// Create a Unix credential struct.
 	///
 	/// PID, UID and GID is set to 0.
 	#[allow(clippy::new_without_default)]
 	pub fn new() -> SocketCred {
-		SocketCred(libc::ucred { pid: 0, uid: 0, gid: 0 })
+		// SAFETY: All fields of the native credential struct are plain integers.
+		SocketCred(unsafe { zeroed() })
+	}
+
+	/// Create a Unix credential struct with the given PID, UID and GID.
+	pub fn from_parts(pid: pid_t, uid: uid_t, gid: gid_t) -> SocketCred {
+		let mut cred = SocketCred::new();
+		cred.set_pid(pid);
+		cred.set_uid(uid);
+		cred.set_gid(gid);
+		cred
 	}
 
 	/// Set the PID.
+	#[cfg(any(target_os = "android", target_os = "linux"))]
 	pub fn set_pid(&mut self, pid: pid_t) {
 		self.0.pid = pid;
 	}
 
+	/// Set the PID.
+	#[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
+	pub fn set_pid(&mut self, pid: pid_t) {
+		self.0.cmcred_pid = pid;
+	}
+
 	/// Get the current PID.
+	#[cfg(any(target_os = "android", target_os = "linux"))]
 	pub fn get_pid(&self) -> pid_t {
 		self.0.pid
 	}
 
+	/// Get the current PID.
+	#[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
+	pub fn get_pid(&self) -> pid_t {
+		self.0.cmcred_pid
+	}
+
 	/// Set the UID.
+	#[cfg(any(target_os = "android", target_os = "linux"))]
 	pub fn set_uid(&mut self, uid: uid_t) {
 		self.0.uid = uid;
 	}
 
+	/// Set the UID.
+	///
+	/// This sets both the real and the effective UID.
+	#[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
+	pub fn set_uid(&mut self, uid: uid_t) {
+		self.0.cmcred_uid = uid;
+		self.0.cmcred_euid = uid;
+	}
+
 	/// Get the current UID.
+	#[cfg(any(target_os = "android", target_os = "linux"))]
 	pub fn get_uid(&self) -> uid_t {
 		self.0.uid
 	}
 
+	/// Get the current effective UID.
+	#[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
+	pub fn get_uid(&self) -> uid_t {
+		self.0.cmcred_euid
+	}
+
 	/// Set the GID.
+	#[cfg(any(target_os = "android", target_os = "linux"))]
 	pub fn set_gid(&mut self, gid: gid_t) {
 		self.0.gid = gid;
 	}
 
+	/// Set the GID.
+	///
+	/// This sets both the real and the effective GID.
+	#[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
+	pub fn set_gid(&mut self, gid: gid_t) {
+		self.0.cmcred_gid = gid;
+		self.0.cmcred_groups[0] = gid;
+		self.0.cmcred_ngroups = self.0.cmcred_ngroups.max(1);
+	}
+
 	/// Get the current GID.
+	#[cfg(any(target_os = "android", target_os = "linux"))]
 	pub fn get_gid(&self) -> gid_t {
 		self.0.gid
 	}
+
+	/// Get the current effective GID.
+	///
+	/// If the credential holds no groups, this falls back to the real GID.
+	#[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
+	pub fn get_gid(&self) -> gid_t {
+		if self.0.cmcred_ngroups > 0 {
+			self.0.cmcred_groups[0]
+		} else {
+			self.0.cmcred_gid
+		}
+	}
+
+	/// Compare the selected fields of two credentials.
+	///
+	/// For example, use `CredFields::UID | CredFields::GID` to ignore the PID.
+	pub fn matches(&self, other: &SocketCred, fields: CredFields) -> bool {
+		(!fields.contains(CredFields::PID) || self.get_pid() == other.get_pid())
+			&& (!fields.contains(CredFields::UID) || self.get_uid() == other.get_uid())
+			&& (!fields.contains(CredFields::GID) || self.get_gid() == other.get_gid())
+	}
+}
+
+#[cfg(any(target_os = "android", target_os = "linux",))]
+impl From<libc::ucred> for SocketCred {
+	fn from(ucred: libc::ucred) -> Self {
+		SocketCred(ucred)
+	}
+}
+
+#[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
+impl From<libc::cmsgcred> for SocketCred {
+	fn from(cmsgcred: libc::cmsgcred) -> Self {
+		SocketCred(cmsgcred)
+	}
+}
+
+#[cfg(any(
+	target_os = "android",
+	target_os = "linux",
+	target_os = "freebsd",
+	target_os = "dragonfly"
+))]
+impl std::fmt::Debug for SocketCred {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		f.debug_struct("SocketCred")
+			.field("pid", &self.get_pid())
+			.field("uid", &self.get_uid())
+			.field("gid", &self.get_gid())
+			.finish()
+	}
+}
+
+#[cfg(any(
+	target_os = "android",
+	target_os = "linux",
+	target_os = "freebsd",
+	target_os = "dragonfly"
+))]
+impl PartialEq for SocketCred {
+	fn eq(&self, other: &Self) -> bool {
+		self.matches(other, CredFields::ALL)
+	}
+}
+
+#[cfg(any(
+	target_os = "android",
+	target_os = "linux",
+	target_os = "freebsd",
+	target_os = "dragonfly"
+))]
+impl Eq for SocketCred {}
+
+#[cfg(any(
+	target_os = "android",
+	target_os = "linux",
+	target_os = "freebsd",
+	target_os = "dragonfly"
+))]
+impl std::hash::Hash for SocketCred {
+	fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+		(self.get_pid(), self.get_uid(), self.get_gid()).hash(state);
+	}
+}
+
+/// Convert the credentials of a connected peer, as returned by [`UnixSeqpacket::peer_cred()`](crate::UnixSeqpacket::peer_cred).
+///
+/// Unlike credentials received in a `SCM_CREDENTIALS` message, these are recorded by the kernel at connect time,
+/// and they do not require `SO_PASSCRED`.
+/// If the PID of the peer is not known, it is set to 0.
+#[cfg(any(
+	target_os = "android",
+	target_os = "linux",
+	target_os = "freebsd",
+	target_os = "dragonfly"
+))]
+impl From<crate::UCred> for SocketCred {
+	fn from(ucred: crate::UCred) -> Self {
+		let mut cred = SocketCred::new();
+		cred.set_pid(ucred.pid().unwrap_or(0));
+		cred.set_uid(ucred.uid());
+		cred.set_gid(ucred.gid());
+		cred
+	}
+}
+
+/// A set of credential fields to compare with [`SocketCred::matches()`].
+///
+/// Fields can be combined with the `|` operator.
+#[cfg(any(
+	target_os = "android",
+	target_os = "linux",
+	target_os = "freebsd",
+	target_os = "dragonfly"
+))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct CredFields(u8);
+
+#[cfg(any(
+	target_os = "android",
+	target_os = "linux",
+	target_os = "freebsd",
+	target_os = "dragonfly"
+))]
+impl CredFields {
+	/// No fields.
+	pub const NONE: Self = Self(0);
+
+	/// The process ID.
+	pub const PID: Self = Self(1 << 0);
+
+	/// The user ID.
+	pub const UID: Self = Self(1 << 1);
+
+	/// The group ID.
+	pub const GID: Self = Self(1 << 2);
+
+	/// All fields.
+	pub const ALL: Self = Self(Self::PID.0 | Self::UID.0 | Self::GID.0);
+
+	/// Check if all fields in `other` are also in `self`.
+	pub fn contains(self, other: Self) -> bool {
+		self.0 & other.0 == other.0
+	}
+}
+
+#[cfg(any(
+	target_os = "android",
+	target_os = "linux",
+	target_os = "freebsd",
+	target_os = "dragonfly"
+))]
+impl core::ops::BitOr for CredFields {
+	type Output = Self;
+
+	fn bitor(self, other: Self) -> Self {
+		Self(self.0 | other.0)
+	}
+}
+
+#[cfg(any(
+	target_os = "android",
+	target_os = "linux",
+	target_os = "freebsd",
+	target_os = "dragonfly"
+))]
+impl core::ops::BitOrAssign for CredFields {
+	fn bitor_assign(&mut self, other: Self) {
+		self.0 |= other.0;
+	}
 }
 
 /// This control message contains file descriptors.
@@ -170,13 +552,161 @@ impl<'a> Iterator for ScmRights<'a> {
 	}
 }
 
+impl ScmRights<'_> {
+	/// Check that all remaining file descriptors in the message are of the expected type.
+	///
+	/// This does not advance the iterator, and it does not take ownership of the file descriptors.
+	/// An error of kind [`std::io::ErrorKind::InvalidData`] is returned if a file descriptor has a different type.
+	///
+	/// See [`fd_type()`] for how the type is determined.
+	pub fn check_type(&self, expected: FdType) -> std::io::Result<()> {
+		for fd in self.0.clone() {
+			let actual = fd_type(fd)?;
+			if actual != expected {
+				return Err(std::io::Error::new(
+					std::io::ErrorKind::InvalidData,
+					format!("received file descriptor {fd} is a {actual:?}, expected a {expected:?}"),
+				));
+			}
+		}
+		Ok(())
+	}
+}
+
+/// An iterator that takes ownership of received file descriptors.
+///
+/// The file descriptors that are not consumed are closed when the iterator is dropped.
+///
+/// See [`SocketAncillary::take_fds()`].
+pub struct ScmRightsOwned<'a> {
+	messages: Option<Messages<'a>>,
+	current: Option<ScmRights<'a>>,
+}
+
+impl Iterator for ScmRightsOwned<'_> {
+	type Item = OwnedFd;
+
+	fn next(&mut self) -> Option<OwnedFd> {
+		loop {
+			if let Some(fd) = self.current.as_mut().and_then(|fds| fds.next()) {
+				// SAFETY: The file descriptor was received from the kernel, and `SocketAncillary::fds_owned` ensures it is taken only once.
+				return Some(unsafe { OwnedFd::from_raw_fd(fd) });
+			}
+			self.current = loop {
+				match self.messages.as_mut()?.next()? {
+					Ok(AncillaryData::ScmRights(fds)) => break Some(fds),
+					_ => continue,
+				}
+			};
+		}
+	}
+}
+
+impl Drop for ScmRightsOwned<'_> {
+	fn drop(&mut self) {
+		self.for_each(drop);
+	}
+}
+
+/// The type of a file descriptor, as determined by [`fd_type()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum FdType {
+	/// A regular file.
+	RegularFile,
+
+	/// An anonymous memory file created with `memfd_create()`.
+	///
+	/// This is only detected on Linux and Android.
+	/// On other platforms, memory files are reported as [`FdType::RegularFile`].
+	MemFd,
+
+	/// A directory.
+	Directory,
+
+	/// A socket.
+	Socket,
+
+	/// A pipe or FIFO.
+	Fifo,
+
+	/// A character device.
+	CharDevice,
+
+	/// A block device.
+	BlockDevice,
+
+	/// A symbolic link (only possible for file descriptors opened with `O_PATH`).
+	Symlink,
+
+	/// A file descriptor of a type not known to this crate.
+	Other,
+}
+
+/// Determine the type of a file descriptor.
+///
+/// This can be used to check that a received file descriptor is of the expected type before using it,
+/// to guard against misbehaving peers.
+///
+/// The type is determined with `fstat()`.
+/// On Linux and Android, regular files are checked for being a memory file by looking at `/proc/self/fd`.
+pub fn fd_type(fd: RawFd) -> std::io::Result<FdType> {
+	let mut stat: libc::stat = unsafe { zeroed() };
+	if unsafe { libc::fstat(fd, &mut stat) } == -1 {
+		return Err(std::io::Error::last_os_error());
+	}
+	let fd_type = match stat.st_mode & libc::S_IFMT {
+		libc::S_IFREG => {
+			if is_memfd(fd) {
+				FdType::MemFd
+			} else {
+				FdType::RegularFile
+			}
+		},
+		libc::S_IFDIR => FdType::Directory,
+		libc::S_IFSOCK => FdType::Socket,
+		libc::S_IFIFO => FdType::Fifo,
+		libc::S_IFCHR => FdType::CharDevice,
+		libc::S_IFBLK => FdType::BlockDevice,
+		libc::S_IFLNK => FdType::Symlink,
+		_ => FdType::Other,
+	};
+	Ok(fd_type)
+}
+
+/// Check if a regular file is a memory file created with `memfd_create()`.
+#[cfg(any(target_os = "android", target_os = "linux",))]
+fn is_memfd(fd: RawFd) -> bool {
+	use std::os::unix::ffi::OsStrExt;
+	match std::fs::read_link(format!("/proc/self/fd/{fd}")) {
+		Ok(target) => target.as_os_str().as_bytes().starts_with(b"/memfd:"),
+		Err(_) => false,
+	}
+}
+
+/// Check if a regular file is a memory file created with `memfd_create()`.
+#[cfg(not(any(target_os = "android", target_os = "linux",)))]
+fn is_memfd(_fd: RawFd) -> bool {
+	false
+}
+
 /// This control message contains unix credentials.
 ///
 /// The level is equal to `SOL_SOCKET` and the type is equal to `SCM_CREDENTIALS` or `SCM_CREDS`.
-#[cfg(any(target_os = "android", target_os = "linux",))]
-pub struct ScmCredentials<'a>(AncillaryDataIter<'a, libc::ucred>);
+#[cfg(any(
+	target_os = "android",
+	target_os = "linux",
+	target_os = "freebsd",
+	target_os = "dragonfly"
+))]
+pub struct ScmCredentials<'a>(AncillaryDataIter<'a, RawCred>);
 
-#[cfg(any(target_os = "android", target_os = "linux",))]
+#[cfg(any(
+	target_os = "android",
+	target_os = "linux",
+	target_os = "freebsd",
+	target_os = "dragonfly"
+))]
 impl<'a> Iterator for ScmCredentials<'a> {
 	type Item = SocketCred;
 
@@ -197,16 +727,130 @@ pub enum AncillaryError {
 		/// The cmsg_type field of the ancillary data.
 		cmsg_type: i32,
 	},
+
+	/// The length of the control message is invalid or extends beyond the end of the ancillary data.
+	///
+	/// No more control messages are parsed after this error.
+	Truncated {
+		/// The cmsg_level field of the ancillary data.
+		cmsg_level: i32,
+
+		/// The cmsg_type field of the ancillary data.
+		cmsg_type: i32,
+	},
+
+	/// The data of a control message with built-in support is not valid for its type.
+	///
+	/// For example, the length of a `SCM_RIGHTS` message is not a multiple of the size of a file descriptor,
+	/// or it holds a negative file descriptor.
+	/// The message is skipped entirely, so no file descriptors are taken from it.
+	/// Parsing continues with the next control message.
+	Malformed {
+		/// The cmsg_level field of the ancillary data.
+		cmsg_level: i32,
+
+		/// The cmsg_type field of the ancillary data.
+		cmsg_type: i32,
+	},
 }
 
 /// This enum represent one control message of variable type.
+#[non_exhaustive]
 pub enum AncillaryData<'a> {
 	/// Ancillary data holding file descriptors.
 	ScmRights(ScmRights<'a>),
 
 	/// Ancillary data holding unix credentials.
-	#[cfg(any(target_os = "android", target_os = "linux",))]
+	#[cfg(any(
+		target_os = "android",
+		target_os = "linux",
+		target_os = "freebsd",
+		target_os = "dragonfly"
+	))]
 	ScmCredentials(ScmCredentials<'a>),
+
+	/// The time at which the datagram was queued by the kernel (`SCM_TIMESTAMPNS`), as a duration since the Unix epoch.
+	///
+	/// This is only received if enabled with [`UnixSeqpacket::set_receive_timestamp()`](crate::UnixSeqpacket::set_receive_timestamp).
+	#[cfg(any(target_os = "android", target_os = "linux",))]
+	Timestamp(std::time::Duration),
+
+	/// A control message of a type without built-in support or registered parser, yielded with [`UnknownPolicy::Yield`].
+	Unknown {
+		/// The cmsg_level field of the ancillary data.
+		cmsg_level: i32,
+
+		/// The cmsg_type field of the ancillary data.
+		cmsg_type: i32,
+
+		/// The data of the control message, without the header (`cmsg_len - CMSG_LEN(0)` bytes).
+		data: &'a [u8],
+	},
+
+	/// Ancillary data of a type without built-in support, parsed by a function registered with [`register_parser()`].
+	Custom {
+		/// The cmsg_level field of the ancillary data.
+		cmsg_level: i32,
+
+		/// The cmsg_type field of the ancillary data.
+		cmsg_type: i32,
+
+		/// The value returned by the registered parser.
+		value: Box<dyn Any + Send + Sync>,
+	},
+}
+
+/// A function that parses the data of a control message, registered with [`register_parser()`].
+///
+/// The function receives the data of the control message, without the header.
+pub type CustomParser = fn(&[u8]) -> Box<dyn Any + Send + Sync>;
+
+/// The parsers registered with [`register_parser()`].
+static CUSTOM_PARSERS: RwLock<Vec<(i32, i32, CustomParser)>> = RwLock::new(Vec::new());
+
+/// Register a parser for control messages with the given level and type.
+///
+/// When [`SocketAncillary::messages()`] encounters a control message of a type without built-in support,
+/// it looks for a registered parser and yields the parsed value as [`AncillaryData::Custom`].
+/// Without a registered parser, such messages are reported as [`AncillaryError::Unknown`].
+///
+/// The built-in parsing of `SCM_RIGHTS`, `SCM_CREDENTIALS` and `SCM_TIMESTAMPNS` always takes precedence:
+/// parsers registered for those types are never called.
+///
+/// The registry is global to the process.
+/// Registering a parser for a level and type that already have a parser replaces the old one.
+pub fn register_parser(cmsg_level: i32, cmsg_type: i32, parser: CustomParser) {
+	let mut parsers = CUSTOM_PARSERS.write().unwrap_or_else(|e| e.into_inner());
+	parsers.retain(|&(level, kind, _)| (level, kind) != (cmsg_level, cmsg_type));
+	parsers.push((cmsg_level, cmsg_type, parser));
+}
+
+/// Remove the parser for control messages with the given level and type.
+///
+/// Returns `true` if a parser was registered.
+pub fn unregister_parser(cmsg_level: i32, cmsg_type: i32) -> bool {
+	let mut parsers = CUSTOM_PARSERS.write().unwrap_or_else(|e| e.into_inner());
+	let len = parsers.len();
+	parsers.retain(|&(level, kind, _)| (level, kind) != (cmsg_level, cmsg_type));
+	parsers.len() != len
+}
+
+/// Parse a control message without built-in support with a registered parser, if there is one.
+fn parse_custom<'a>(cmsg_level: i32, cmsg_type: i32, data: &[u8]) -> Result<AncillaryData<'a>, AncillaryError> {
+	let parser = CUSTOM_PARSERS
+		.read()
+		.unwrap_or_else(|e| e.into_inner())
+		.iter()
+		.find(|&&(level, kind, _)| (level, kind) == (cmsg_level, cmsg_type))
+		.map(|&(_, _, parser)| parser);
+	match parser {
+		Some(parser) => Ok(AncillaryData::Custom {
+			cmsg_level,
+			cmsg_type,
+			value: parser(data),
+		}),
+		None => Err(AncillaryError::Unknown { cmsg_level, cmsg_type }),
+	}
 }
 
 impl<'a> AncillaryData<'a> {
@@ -228,8 +872,13 @@ impl<'a> AncillaryData<'a> {
 	/// # Safety
 	///
 	/// `data` must contain a valid control message and the control message must be type of
-	/// `SOL_SOCKET` and level of `SCM_CREDENTIALS` or `SCM_CREDENTIALS`.
-	#[cfg(any(target_os = "android", target_os = "linux",))]
+	/// `SOL_SOCKET` and level of `SCM_CREDENTIALS` or `SCM_CREDS`.
+	#[cfg(any(
+		target_os = "android",
+		target_os = "linux",
+		target_os = "freebsd",
+		target_os = "dragonfly"
+	))]
 	#[allow(clippy::wrong_self_convention)]
 	unsafe fn as_credentials(data: &'a [u8]) -> Self {
 		let ancillary_data_iter = AncillaryDataIter::new(data);
@@ -239,40 +888,191 @@ impl<'a> AncillaryData<'a> {
 
 	fn try_from_cmsghdr(cmsg: &'a libc::cmsghdr) -> Result<Self, AncillaryError> {
 		unsafe {
-			let cmsg_len_zero = libc::CMSG_LEN(0);
-			let data_len = cmsg.cmsg_len as usize - cmsg_len_zero as usize;
-			let data = libc::CMSG_DATA(cmsg).cast();
-			let data = from_raw_parts(data, data_len);
+			let data = cmsg_data(cmsg);
 
 			match cmsg.cmsg_level {
 				libc::SOL_SOCKET => match cmsg.cmsg_type {
-					libc::SCM_RIGHTS => Ok(AncillaryData::as_rights(data)),
+					libc::SCM_RIGHTS if valid_fds(data) => Ok(AncillaryData::as_rights(data)),
+					#[cfg(any(
+						target_os = "android",
+						target_os = "linux",
+						target_os = "freebsd",
+						target_os = "dragonfly"
+					))]
+					SCM_CREDS if data.len().is_multiple_of(size_of::<RawCred>()) => Ok(AncillaryData::as_credentials(data)),
+					#[cfg(any(
+						target_os = "android",
+						target_os = "linux",
+						target_os = "freebsd",
+						target_os = "dragonfly"
+					))]
+					SCM_CREDS => Err(AncillaryError::Malformed {
+						cmsg_level: libc::SOL_SOCKET,
+						cmsg_type: SCM_CREDS,
+					}),
 					#[cfg(any(target_os = "android", target_os = "linux",))]
-					libc::SCM_CREDENTIALS => Ok(AncillaryData::as_credentials(data)),
-					cmsg_type => Err(AncillaryError::Unknown {
+					libc::SCM_TIMESTAMPNS => match parse_timestamp(data) {
+						Some(timestamp) => Ok(AncillaryData::Timestamp(timestamp)),
+						None => Err(AncillaryError::Malformed {
+							cmsg_level: libc::SOL_SOCKET,
+							cmsg_type: libc::SCM_TIMESTAMPNS,
+						}),
+					},
+					libc::SCM_RIGHTS => Err(AncillaryError::Malformed {
 						cmsg_level: libc::SOL_SOCKET,
-						cmsg_type,
+						cmsg_type: libc::SCM_RIGHTS,
 					}),
+					cmsg_type => parse_custom(libc::SOL_SOCKET, cmsg_type, data),
 				},
-				cmsg_level => Err(AncillaryError::Unknown {
-					cmsg_level,
-					cmsg_type: cmsg.cmsg_type,
-				}),
+				cmsg_level => parse_custom(cmsg_level, cmsg.cmsg_type, data),
 			}
 		}
 	}
 }
 
+/// Check that the data of a `SCM_RIGHTS` message is a whole number of non-negative file descriptors.
+fn valid_fds(data: &[u8]) -> bool {
+	data.len().is_multiple_of(size_of::<RawFd>())
+		&& data
+			.chunks_exact(size_of::<RawFd>())
+			.all(|fd| RawFd::from_ne_bytes(fd.try_into().unwrap()) >= 0)
+}
+
+/// Parse the data of a `SCM_TIMESTAMPNS` message.
+///
+/// Returns `None` if the data does not hold exactly one valid `timespec`.
+#[cfg(any(target_os = "android", target_os = "linux",))]
+fn parse_timestamp(data: &[u8]) -> Option<std::time::Duration> {
+	if data.len() != size_of::<libc::timespec>() {
+		return None;
+	}
+	// SAFETY: The length was checked, and any byte pattern is a valid timespec.
+	let timestamp: libc::timespec = unsafe { read_unaligned(data.as_ptr().cast()) };
+	let secs = u64::try_from(timestamp.tv_sec).ok()?;
+	let nanos = u32::try_from(timestamp.tv_nsec)
+		.ok()
+		.filter(|&nanos| nanos < 1_000_000_000)?;
+	Some(std::time::Duration::new(secs, nanos))
+}
+
+/// Get the data of a control message, without the header.
+///
+/// # Safety
+///
+/// The length of the control message must be validated: it must cover the header and stay inside the buffer.
+unsafe fn cmsg_data(cmsg: &libc::cmsghdr) -> &[u8] {
+	let cmsg_len_zero = libc::CMSG_LEN(0);
+	// This is not a no-op on all platforms.
+	#[allow(clippy::unnecessary_cast)]
+	let data_len = cmsg.cmsg_len as usize - cmsg_len_zero as usize;
+	from_raw_parts(libc::CMSG_DATA(cmsg).cast(), data_len)
+}
+
+/// What to do with control messages of unknown types while iterating over [`Messages`].
+///
+/// Control messages for which a parser is registered with [`register_parser()`] are not unknown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnknownPolicy {
+	/// Yield an [`AncillaryError::Unknown`] error for each unknown control message.
+	#[default]
+	Error,
+
+	/// Skip unknown control messages.
+	Skip,
+
+	/// Skip unknown control messages, but keep them so they can be inspected with [`Messages::unknown()`].
+	Collect,
+
+	/// Yield an [`AncillaryData::Unknown`] value with the raw data for each unknown control message.
+	Yield,
+}
+
+/// A control message of an unknown type, collected with [`UnknownPolicy::Collect`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownMessage<'a> {
+	/// The cmsg_level field of the control message.
+	pub cmsg_level: i32,
+
+	/// The cmsg_type field of the control message.
+	pub cmsg_type: i32,
+
+	/// The data of the control message, without the header.
+	pub data: &'a [u8],
+}
+
 /// This struct is used to iterate through the control messages.
 pub struct Messages<'a> {
 	buffer: &'a [u8],
 	current: Option<&'a libc::cmsghdr>,
+	done: bool,
+	unknown_policy: UnknownPolicy,
+	unknown: Vec<UnknownMessage<'a>>,
+}
+
+impl<'a> Messages<'a> {
+	/// Set what to do with control messages of unknown types.
+	///
+	/// By default, an [`AncillaryError::Unknown`] error is yielded for each of them.
+	pub fn unknown_policy(mut self, policy: UnknownPolicy) -> Self {
+		self.unknown_policy = policy;
+		self
+	}
+
+	/// Get the unknown control messages that were skipped so far.
+	///
+	/// This is only filled when the policy is [`UnknownPolicy::Collect`].
+	pub fn unknown(&self) -> &[UnknownMessage<'a>] {
+		&self.unknown
+	}
+
+	/// Get the unknown control messages that were skipped so far, consuming the iterator.
+	///
+	/// This is only filled when the policy is [`UnknownPolicy::Collect`].
+	pub fn into_unknown(self) -> Vec<UnknownMessage<'a>> {
+		self.unknown
+	}
 }
 
 impl<'a> Iterator for Messages<'a> {
 	type Item = Result<AncillaryData<'a>, AncillaryError>;
 
 	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			match self.next_message()? {
+				Err(AncillaryError::Unknown { cmsg_level, cmsg_type }) => match self.unknown_policy {
+					UnknownPolicy::Error => return Some(Err(AncillaryError::Unknown { cmsg_level, cmsg_type })),
+					UnknownPolicy::Skip => continue,
+					UnknownPolicy::Collect => {
+						// The message passed validation, so the length can be trusted.
+						let data = unsafe { cmsg_data(self.current?) };
+						self.unknown.push(UnknownMessage {
+							cmsg_level,
+							cmsg_type,
+							data,
+						});
+					},
+					UnknownPolicy::Yield => {
+						// The message passed validation, so the length can be trusted.
+						let data = unsafe { cmsg_data(self.current?) };
+						return Some(Ok(AncillaryData::Unknown {
+							cmsg_level,
+							cmsg_type,
+							data,
+						}));
+					},
+				},
+				result => return Some(result),
+			}
+		}
+	}
+}
+
+impl<'a> Messages<'a> {
+	/// Parse the next control message, regardless of the policy for unknown messages.
+	fn next_message(&mut self) -> Option<Result<AncillaryData<'a>, AncillaryError>> {
+		if self.done {
+			return None;
+		}
 		unsafe {
 			let mut msg: libc::msghdr = zeroed();
 			msg.msg_control = self.buffer.as_ptr() as *mut _;
@@ -286,18 +1086,85 @@ impl<'a> Iterator for Messages<'a> {
 
 			let cmsg = cmsg.as_ref()?;
 			self.current = Some(cmsg);
+
+			// Do not trust the length of the message: it must cover the header and stay inside the buffer.
+			let offset = cmsg as *const libc::cmsghdr as usize - self.buffer.as_ptr() as usize;
+			// This is not a no-op on all platforms.
+			#[allow(clippy::unnecessary_cast)]
+			let cmsg_len = cmsg.cmsg_len as usize;
+			if cmsg_len < libc::CMSG_LEN(0) as usize || cmsg_len > self.buffer.len() - offset {
+				self.done = true;
+				return Some(Err(AncillaryError::Truncated {
+					cmsg_level: cmsg.cmsg_level,
+					cmsg_type: cmsg.cmsg_type,
+				}));
+			}
+
 			let ancillary_result = AncillaryData::try_from_cmsghdr(cmsg);
 			Some(ancillary_result)
 		}
 	}
 }
 
+/// This struct is used to iterate through the control messages together with their raw bytes.
+///
+/// See [`SocketAncillary::messages_with_raw()`].
+pub struct MessagesWithRaw<'a> {
+	inner: Messages<'a>,
+}
+
+impl<'a> Iterator for MessagesWithRaw<'a> {
+	type Item = (Result<AncillaryData<'a>, AncillaryError>, &'a [u8]);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let parsed = self.inner.next()?;
+		let cmsg = self.inner.current?;
+		let buffer = self.inner.buffer;
+		let offset = cmsg as *const libc::cmsghdr as usize - buffer.as_ptr() as usize;
+		// This is not a no-op on all platforms.
+		#[allow(clippy::unnecessary_cast)]
+		let len = (cmsg.cmsg_len as usize).min(buffer.len() - offset);
+		Some((parsed, &buffer[offset..][..len]))
+	}
+}
+
+/// The buffer of a [`SocketAncillary`], which is either borrowed or owned by a [`SocketAncillaryBuf`].
+#[derive(Debug)]
+pub(crate) enum Buffer<'a> {
+	Borrowed(&'a mut [u8]),
+	Owned(Vec<u8>),
+}
+
+impl core::ops::Deref for Buffer<'_> {
+	type Target = [u8];
+
+	fn deref(&self) -> &[u8] {
+		match self {
+			Self::Borrowed(buffer) => buffer,
+			Self::Owned(buffer) => buffer,
+		}
+	}
+}
+
+impl core::ops::DerefMut for Buffer<'_> {
+	fn deref_mut(&mut self) -> &mut [u8] {
+		match self {
+			Self::Borrowed(buffer) => buffer,
+			Self::Owned(buffer) => buffer,
+		}
+	}
+}
+
 /// A Unix socket Ancillary data struct.
 #[derive(Debug)]
 pub struct SocketAncillary<'a> {
-	pub(crate) buffer: &'a mut [u8],
+	pub(crate) buffer: Buffer<'a>,
 	pub(crate) length: usize,
 	pub(crate) truncated: bool,
+	/// If true, the file descriptors in the buffer were received from the kernel and are owned by this buffer.
+	///
+	/// Only receive operations set this flag, so [`Self::take_fds()`] never closes file descriptors added for sending.
+	pub(crate) fds_owned: bool,
 }
 
 impl<'a> SocketAncillary<'a> {
@@ -312,9 +1179,34 @@ impl<'a> SocketAncillary<'a> {
 	/// ```
 	pub fn new(buffer: &'a mut [u8]) -> Self {
 		SocketAncillary {
-			buffer,
+			buffer: Buffer::Borrowed(buffer),
 			length: 0,
 			truncated: false,
+			fds_owned: false,
+		}
+	}
+
+	/// Create an ancillary data buffer that looks like it was filled by a receive operation.
+	///
+	/// The first `length` bytes of `buffer` are used as the received control messages,
+	/// and `truncated` is reported by [`Self::truncated()`].
+	/// This can be used to test the parsing of hand-crafted control messages without real sockets.
+	///
+	/// The file descriptors in the buffer are not owned by the ancillary data,
+	/// so [`Self::take_fds()`] returns an empty iterator and never closes them.
+	///
+	/// This is only available with the `test-util` feature.
+	///
+	/// # Panics
+	/// This function panics if `length` is larger than the size of `buffer`.
+	#[cfg(feature = "test-util")]
+	pub fn from_raw_received(buffer: &'a mut [u8], length: usize, truncated: bool) -> Self {
+		assert!(length <= buffer.len(), "length exceeds the size of the buffer");
+		SocketAncillary {
+			buffer: Buffer::Borrowed(buffer),
+			length,
+			truncated,
+			fds_owned: false,
 		}
 	}
 
@@ -338,10 +1230,90 @@ impl<'a> SocketAncillary<'a> {
 		Messages {
 			buffer: &self.buffer[..self.length],
 			current: None,
+			done: false,
+			unknown_policy: UnknownPolicy::Error,
+			unknown: Vec::new(),
+		}
+	}
+
+	/// Returns an iterator over the control messages, together with the raw bytes of each message.
+	///
+	/// The raw bytes include the control message header, but not the padding after the message.
+	/// This can be used to log the raw form of control messages, also when they are parsed successfully.
+	pub fn messages_with_raw(&self) -> MessagesWithRaw<'_> {
+		MessagesWithRaw { inner: self.messages() }
+	}
+
+	/// Get the level and type of each control message in the buffer.
+	///
+	/// This only walks the headers of the control messages: the payloads are not parsed.
+	/// The result holds one `(cmsg_level, cmsg_type)` pair per control message, in order.
+	pub fn message_types(&self) -> Vec<(i32, i32)> {
+		let mut types = Vec::new();
+		unsafe {
+			let mut msg: libc::msghdr = zeroed();
+			msg.msg_control = self.buffer.as_ptr() as *mut _;
+			msg.msg_controllen = self.length as _;
+
+			let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+			while let Some(header) = cmsg.as_ref() {
+				types.push((header.cmsg_level, header.cmsg_type));
+				let next = libc::CMSG_NXTHDR(&msg, cmsg);
+				if next == cmsg {
+					break;
+				}
+				cmsg = next;
+			}
+		}
+		types
+	}
+
+	/// Get the total number of file descriptors in all `SCM_RIGHTS` control messages.
+	///
+	/// This only counts the file descriptors: it does not take ownership of them or close them,
+	/// and it can be called any number of times.
+	/// The file descriptors can still be retrieved afterwards with [`Self::messages()`].
+	pub fn fd_count(&self) -> usize {
+		self.messages()
+			.map(|message| match message {
+				Ok(AncillaryData::ScmRights(fds)) => fds.count(),
+				_ => 0,
+			})
+			.sum()
+	}
+
+	/// Take ownership of the file descriptors in all `SCM_RIGHTS` control messages.
+	///
+	/// The returned iterator yields each received file descriptor as an [`OwnedFd`].
+	/// File descriptors that are not consumed are closed when the iterator is dropped,
+	/// so no received file descriptor can leak.
+	///
+	/// Only file descriptors received by a receive operation are owned by the ancillary data.
+	/// If the buffer was not filled by a receive operation, for example because file descriptors were added with [`Self::add_fds()`],
+	/// this returns an empty iterator and nothing is closed.
+	///
+	/// The file descriptors can be taken only once per received message:
+	/// calling this function again returns an empty iterator until the buffer is filled by a new receive operation.
+	/// After this function has been called, [`Self::messages()`] still reports the raw file descriptors,
+	/// but they are no longer owned by the ancillary data and must not be used or closed through it.
+	pub fn take_fds(&mut self) -> ScmRightsOwned<'_> {
+		let messages = if self.fds_owned {
+			self.fds_owned = false;
+			Some(self.messages())
+		} else {
+			None
+		};
+		ScmRightsOwned {
+			messages,
+			current: None,
 		}
 	}
 
 	/// Is `true` if during a recv operation the ancillary was truncated.
+	///
+	/// This flag is only set by receive operations, and only cleared by receive operations and [`Self::clear()`].
+	/// Adding data to the buffer or sending it does not change the flag.
+	/// A newly created ancillary data buffer is not truncated.
 	pub fn truncated(&self) -> bool {
 		self.truncated
 	}
@@ -352,9 +1324,50 @@ impl<'a> SocketAncillary<'a> {
 	/// If there was not enough space then no file descriptors was appended.
 	/// Technically, that means this operation adds a control message with the level `SOL_SOCKET`
 	/// and type `SCM_RIGHTS`.
+	///
+	/// See [`Self::try_add_fds()`] for a version that reports why the file descriptors could not be added.
 	pub fn add_fds(&mut self, fds: &[RawFd]) -> bool {
-		self.truncated = false;
-		add_to_ancillary_data(self.buffer, &mut self.length, fds, libc::SOL_SOCKET, libc::SCM_RIGHTS)
+		self.try_add_fds(fds).is_ok()
+	}
+
+	/// Add file descriptors to the ancillary data, or report why that is not possible.
+	///
+	/// This is the same as [`Self::add_fds()`], but it returns an error instead of `false`:
+	/// * [`AncillaryAddError::BufferFull`] if there is not enough free space in the buffer,
+	/// * [`AncillaryAddError::LengthOverflow`] if the size of the control message would overflow,
+	/// * [`AncillaryAddError::CmsgChainCorrupt`] if the control messages already in the buffer are corrupt.
+	///
+	/// If an error is returned, no file descriptors are added.
+	///
+	/// Adding file descriptors to a buffer filled by a receive operation releases the ownership of the received file descriptors:
+	/// [`Self::take_fds()`] will no longer return them, so they should be taken first.
+	pub fn try_add_fds(&mut self, fds: &[RawFd]) -> Result<(), AncillaryAddError> {
+		add_to_ancillary_data(
+			&mut self.buffer,
+			&mut self.length,
+			fds,
+			libc::SOL_SOCKET,
+			libc::SCM_RIGHTS,
+		)?;
+		self.fds_owned = false;
+		Ok(())
+	}
+
+	/// Check if there is enough space in the buffer to add `count` credentials using [`Self::add_creds()`].
+	///
+	/// This returns `false` if `count` is not exactly one, since [`Self::add_creds()`] rejects that.
+	#[cfg(any(
+		target_os = "android",
+		target_os = "linux",
+		target_os = "freebsd",
+		target_os = "dragonfly"
+	))]
+	pub fn can_add_creds(&self, count: usize) -> bool {
+		if count != 1 {
+			return false;
+		}
+		let space = unsafe { libc::CMSG_SPACE(size_of::<RawCred>() as u32) as usize };
+		self.length + space <= self.buffer.len()
 	}
 
 	/// Add credentials to the ancillary data.
@@ -364,15 +1377,40 @@ impl<'a> SocketAncillary<'a> {
 	/// Technically, that means this operation adds a control message with the level `SOL_SOCKET`
 	/// and type `SCM_CREDENTIALS` or `SCM_CREDS`.
 	///
-	#[cfg(any(target_os = "android", target_os = "linux",))]
+	/// The kernel only accepts a `SCM_CREDENTIALS` message that holds exactly one credential,
+	/// and if a datagram has multiple `SCM_CREDENTIALS` messages, only the last one is used.
+	/// For that reason, this function returns `false` and adds nothing if `creds` does not contain exactly one credential.
+	///
+	/// On FreeBSD and DragonFly, a `SCM_CREDS` message is added instead.
+	/// The kernel replaces its contents with the real credentials of the sending process,
+	/// and the receiver does not need to enable anything to receive them.
+	#[cfg(any(
+		target_os = "android",
+		target_os = "linux",
+		target_os = "freebsd",
+		target_os = "dragonfly"
+	))]
 	pub fn add_creds(&mut self, creds: &[SocketCred]) -> bool {
-		self.truncated = false;
+		match creds {
+			[cred] => self.add_cred(cred).is_ok(),
+			_ => false,
+		}
+	}
+
+	/// Add a control message with a single credential.
+	#[cfg(any(
+		target_os = "android",
+		target_os = "linux",
+		target_os = "freebsd",
+		target_os = "dragonfly"
+	))]
+	fn add_cred(&mut self, cred: &SocketCred) -> Result<(), AncillaryAddError> {
 		add_to_ancillary_data(
-			self.buffer,
+			&mut self.buffer,
 			&mut self.length,
-			creds,
+			std::slice::from_ref(cred),
 			libc::SOL_SOCKET,
-			libc::SCM_CREDENTIALS,
+			SCM_CREDS,
 		)
 	}
 
@@ -380,5 +1418,349 @@ impl<'a> SocketAncillary<'a> {
 	pub fn clear(&mut self) {
 		self.length = 0;
 		self.truncated = false;
+		self.fds_owned = false;
+	}
+}
+
+/// An owned ancillary data buffer that grows as needed.
+///
+/// This dereferences to a [`SocketAncillary`], so it can be passed to all functions that accept one.
+/// Unlike [`SocketAncillary::add_fds()`], [`SocketAncillaryBuf::add_fds()`] grows the buffer if needed.
+///
+/// # Example
+///
+/// ```no_run
+/// # async fn foo(socket: tokio_seqpacket::UnixSeqpacket, files: Vec<std::fs::File>) -> std::io::Result<()> {
+/// use std::io::IoSlice;
+/// use std::os::unix::io::AsRawFd;
+/// use tokio_seqpacket::ancillary::SocketAncillaryBuf;
+///
+/// let mut ancillary: SocketAncillaryBuf = files.iter().map(|file| file.as_raw_fd()).collect();
+/// socket.send_vectored_with_ancillary(&[IoSlice::new(b"Here, have some files.")], &mut ancillary).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct SocketAncillaryBuf {
+	inner: SocketAncillary<'static>,
+}
+
+impl SocketAncillaryBuf {
+	/// Create a new empty ancillary data buffer.
+	pub fn new() -> Self {
+		Self::with_capacity(0)
+	}
+
+	/// Create a new empty ancillary data buffer with room for `capacity` bytes.
+	///
+	/// This can be used to receive ancillary data.
+	pub fn with_capacity(capacity: usize) -> Self {
+		Self {
+			inner: SocketAncillary {
+				buffer: Buffer::Owned(vec![0; capacity]),
+				length: 0,
+				truncated: false,
+				fds_owned: false,
+			},
+		}
+	}
+
+	/// Create an ancillary data buffer holding the given file descriptors.
+	///
+	/// The file descriptors are split over multiple `SCM_RIGHTS` control messages of at most 253 file descriptors each.
+	/// Note that Linux also limits the total number of file descriptors in a single datagram to 253,
+	/// so sending more will fail on Linux.
+	pub fn from_fds(fds: impl IntoIterator<Item = RawFd>) -> Self {
+		let fds: Vec<RawFd> = fds.into_iter().collect();
+		let mut ancillary = Self::new();
+		ancillary.add_fds(&fds);
+		ancillary
+	}
+
+	/// Add file descriptors to the ancillary data, growing the buffer if needed.
+	///
+	/// The file descriptors are split over multiple `SCM_RIGHTS` control messages of at most 253 file descriptors each.
+	///
+	/// The function returns `false` only if the buffer size would overflow.
+	/// In that case, no file descriptors are added.
+	pub fn add_fds(&mut self, fds: &[RawFd]) -> bool {
+		self.push_fds(fds).is_ok()
+	}
+
+	/// Add file descriptors to the ancillary data, growing the buffer if needed.
+	///
+	/// This is the same as [`Self::add_fds()`], but it returns `self` so calls can be chained:
+	///
+	/// ```
+	/// # fn foo(fds: &[std::os::unix::io::RawFd]) -> Result<(), tokio_seqpacket::ancillary::AncillaryAddError> {
+	/// # use tokio_seqpacket::ancillary::SocketAncillaryBuf;
+	/// let mut ancillary = SocketAncillaryBuf::new();
+	/// ancillary.push_fds(&fds[..1])?.push_fds(&fds[1..])?;
+	/// # Ok(())
+	/// # }
+	/// ```
+	///
+	/// If the buffer size would overflow, an error is returned and no file descriptors are added.
+	pub fn push_fds(&mut self, fds: &[RawFd]) -> Result<&mut Self, AncillaryAddError> {
+		let needed = fds
+			.chunks(MAX_FDS_PER_MESSAGE)
+			.try_fold(self.inner.length, |total, chunk| {
+				total.checked_add(fds_space(chunk.len()))
+			})
+			.ok_or(AncillaryAddError::LengthOverflow)?;
+		self.grow_to(needed);
+		for chunk in fds.chunks(MAX_FDS_PER_MESSAGE) {
+			self.inner.try_add_fds(chunk)?;
+		}
+		Ok(self)
+	}
+
+	/// Add credentials to the ancillary data, growing the buffer if needed.
+	///
+	/// Calls can be chained, like with [`Self::push_fds()`].
+	#[cfg(any(
+		target_os = "android",
+		target_os = "linux",
+		target_os = "freebsd",
+		target_os = "dragonfly"
+	))]
+	pub fn push_creds(&mut self, cred: SocketCred) -> Result<&mut Self, AncillaryAddError> {
+		let needed = self
+			.inner
+			.length
+			.checked_add(cmsg_space_for_creds(1))
+			.ok_or(AncillaryAddError::LengthOverflow)?;
+		self.grow_to(needed);
+		self.inner.add_cred(&cred)?;
+		Ok(self)
+	}
+
+	/// Add credentials to the ancillary data, growing the buffer if needed.
+	///
+	/// Like [`SocketAncillary::add_creds()`], this returns `false` and adds nothing if `creds` does not contain exactly one credential.
+	/// Otherwise, it returns `false` only if the buffer size would overflow.
+	#[cfg(any(
+		target_os = "android",
+		target_os = "linux",
+		target_os = "freebsd",
+		target_os = "dragonfly"
+	))]
+	pub fn add_creds(&mut self, creds: &[SocketCred]) -> bool {
+		match creds {
+			[cred] => self.push_creds(cred.clone()).is_ok(),
+			_ => false,
+		}
+	}
+
+	/// Grow the buffer so it has room for `count` more file descriptors.
+	///
+	/// The room is computed the same way as [`Self::add_fds()`] splits file descriptors over control messages,
+	/// so for an empty buffer and up to 253 file descriptors, this grows the buffer to `CMSG_SPACE(count * size_of::<RawFd>())` bytes.
+	/// This can be used to prepare a buffer for receiving a dynamic number of file descriptors.
+	/// The buffer never shrinks.
+	///
+	/// # Panics
+	/// This function panics if the buffer size would overflow, like [`Vec::reserve()`].
+	pub fn reserve_fds(&mut self, count: usize) {
+		let full = count / MAX_FDS_PER_MESSAGE;
+		let rest = count % MAX_FDS_PER_MESSAGE;
+		let needed = full
+			.checked_mul(fds_space(MAX_FDS_PER_MESSAGE))
+			.and_then(|space| space.checked_add(if rest > 0 { fds_space(rest) } else { 0 }))
+			.and_then(|space| space.checked_add(self.inner.length))
+			.expect("capacity overflow");
+		self.grow_to(needed);
+	}
+
+	/// Make sure the buffer has room for at least `capacity` bytes.
+	fn grow_to(&mut self, capacity: usize) {
+		if let Buffer::Owned(buffer) = &mut self.inner.buffer {
+			if buffer.len() < capacity {
+				buffer.resize(capacity, 0);
+			}
+		}
+	}
+}
+
+impl Default for SocketAncillaryBuf {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl core::ops::Deref for SocketAncillaryBuf {
+	type Target = SocketAncillary<'static>;
+
+	fn deref(&self) -> &Self::Target {
+		&self.inner
+	}
+}
+
+impl core::ops::DerefMut for SocketAncillaryBuf {
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		&mut self.inner
+	}
+}
+
+impl FromIterator<RawFd> for SocketAncillaryBuf {
+	fn from_iter<I: IntoIterator<Item = RawFd>>(iter: I) -> Self {
+		Self::from_fds(iter)
+	}
+}
+
+/// An error that occurred while adding a control message to ancillary data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AncillaryAddError {
+	/// The size of the control message or the ancillary data would overflow.
+	LengthOverflow,
+
+	/// The buffer does not have enough free space for the control messages.
+	BufferFull {
+		/// The number of bytes needed for the new control messages.
+		needed: usize,
+
+		/// The number of free bytes left in the buffer.
+		available: usize,
+	},
+
+	/// The control messages already in the buffer are corrupt, so the end of the last message can not be found.
+	CmsgChainCorrupt,
+}
+
+impl std::fmt::Display for AncillaryAddError {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			Self::LengthOverflow => write!(f, "size of ancillary data would overflow"),
+			Self::BufferFull { needed, available } => write!(
+				f,
+				"ancillary data buffer is too small: need {} bytes, but only {} are available",
+				needed, available
+			),
+			Self::CmsgChainCorrupt => write!(f, "control messages in the ancillary data buffer are corrupt"),
+		}
+	}
+}
+
+impl std::error::Error for AncillaryAddError {}
+
+/// A builder for a combination of control messages.
+///
+/// The builder computes the space needed for all control messages up front,
+/// so they are either all added to the buffer, or none of them are.
+///
+/// # Example
+///
+/// ```
+/// # fn foo(fds: &[std::os::unix::io::RawFd]) -> Result<(), tokio_seqpacket::ancillary::AncillaryAddError> {
+/// use tokio_seqpacket::ancillary::{ControlBuilder, SocketAncillary};
+///
+/// let mut buffer = [0; 128];
+/// let mut ancillary = SocketAncillary::new(&mut buffer);
+/// ControlBuilder::new().fds(fds).build_into(&mut ancillary)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct ControlBuilder<'a> {
+	fds: &'a [RawFd],
+	#[cfg(any(
+		target_os = "android",
+		target_os = "linux",
+		target_os = "freebsd",
+		target_os = "dragonfly"
+	))]
+	creds: Option<SocketCred>,
+}
+
+impl<'a> ControlBuilder<'a> {
+	/// Create a new builder without any control messages.
+	pub fn new() -> Self {
+		Self {
+			fds: &[],
+			#[cfg(any(
+				target_os = "android",
+				target_os = "linux",
+				target_os = "freebsd",
+				target_os = "dragonfly"
+			))]
+			creds: None,
+		}
+	}
+
+	/// Set the file descriptors to add.
+	///
+	/// The file descriptors are split over multiple `SCM_RIGHTS` control messages of at most 253 file descriptors each.
+	pub fn fds(mut self, fds: &'a [RawFd]) -> Self {
+		self.fds = fds;
+		self
+	}
+
+	/// Set the credentials to add.
+	#[cfg(any(
+		target_os = "android",
+		target_os = "linux",
+		target_os = "freebsd",
+		target_os = "dragonfly"
+	))]
+	pub fn creds(mut self, cred: SocketCred) -> Self {
+		self.creds = Some(cred);
+		self
+	}
+
+	/// Get the number of bytes needed for all control messages.
+	///
+	/// Returns [`AncillaryAddError::LengthOverflow`] if the size overflows.
+	pub fn space(&self) -> Result<usize, AncillaryAddError> {
+		let space = self
+			.fds
+			.chunks(MAX_FDS_PER_MESSAGE)
+			.try_fold(0usize, |total, chunk| total.checked_add(fds_space(chunk.len())))
+			.ok_or(AncillaryAddError::LengthOverflow)?;
+		#[cfg(any(
+			target_os = "android",
+			target_os = "linux",
+			target_os = "freebsd",
+			target_os = "dragonfly"
+		))]
+		let space = match self.creds {
+			Some(_) => space
+				.checked_add(cmsg_space_for_creds(1))
+				.ok_or(AncillaryAddError::LengthOverflow)?,
+			None => space,
+		};
+		Ok(space)
+	}
+
+	/// Add all control messages to the ancillary data.
+	///
+	/// If the control messages do not all fit in the free space of the buffer,
+	/// this returns [`AncillaryAddError::BufferFull`] and nothing is added.
+	pub fn build_into(&self, ancillary: &mut SocketAncillary) -> Result<(), AncillaryAddError> {
+		let needed = self.space()?;
+		let available = ancillary.capacity() - ancillary.len();
+		if needed > available {
+			return Err(AncillaryAddError::BufferFull { needed, available });
+		}
+
+		for chunk in self.fds.chunks(MAX_FDS_PER_MESSAGE) {
+			ancillary.try_add_fds(chunk)?;
+		}
+		#[cfg(any(
+			target_os = "android",
+			target_os = "linux",
+			target_os = "freebsd",
+			target_os = "dragonfly"
+		))]
+		if let Some(cred) = &self.creds {
+			ancillary.add_cred(cred)?;
+		}
+		Ok(())
+	}
+}
+
+impl Default for ControlBuilder<'_> {
+	fn default() -> Self {
+		Self::new()
 	}
 }
@@ -0,0 +1,210 @@
+//! Blocking seqpacket sockets.
+//!
+//! The API of [`UnixSeqpacket`] mirrors [`std::os::unix::net::UnixStream`] and [`std::os::unix::net::UnixDatagram`],
+//! to make it easy to port code that uses blocking seqpacket sockets from other crates.
+//! Use [`UnixSeqpacket::into_async()`] to turn a blocking socket into an async [`crate::UnixSeqpacket`].
+
+use filedesc::FileDesc;
+use std::io::{IoSlice, IoSliceMut};
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd, RawFd};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::ancillary::SocketAncillary;
+use crate::{sys, SocketAddr, UCred};
+
+/// A blocking Unix seqpacket socket.
+///
+/// Unlike the sockets created by the rest of this crate, these sockets are created in blocking mode.
+pub struct UnixSeqpacket {
+	fd: FileDesc,
+}
+
+impl std::fmt::Debug for UnixSeqpacket {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		f.debug_struct("UnixSeqpacket")
+			.field("fd", &self.fd.as_raw_fd())
+			.finish()
+	}
+}
+
+impl UnixSeqpacket {
+	/// Connect a new seqpacket socket to the given address.
+	pub fn connect<P: AsRef<Path>>(address: P) -> std::io::Result<Self> {
+		let socket = sys::local_seqpacket_socket()?;
+		sys::set_nonblocking(&socket, false)?;
+		sys::connect(&socket, address)?;
+		Ok(Self { fd: socket })
+	}
+
+	/// Create a pair of connected seqpacket sockets.
+	pub fn pair() -> std::io::Result<(Self, Self)> {
+		let (a, b) = sys::local_seqpacket_pair()?;
+		sys::set_nonblocking(&a, false)?;
+		sys::set_nonblocking(&b, false)?;
+		Ok((Self { fd: a }, Self { fd: b }))
+	}
+
+	/// Create a new handle to the same socket.
+	pub fn try_clone(&self) -> std::io::Result<Self> {
+		Ok(Self {
+			fd: self.fd.duplicate()?,
+		})
+	}
+
+	/// Get the address of the local socket.
+	///
+	/// Note that connected sockets are usually unnamed.
+	pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+		sys::get_local_socket_addr(&self.fd)
+	}
+
+	/// Get the address of the peer socket.
+	///
+	/// If the peer is a socket that was accepted by a listener, this is the address of the listener.
+	pub fn peer_addr(&self) -> std::io::Result<SocketAddr> {
+		sys::get_peer_socket_addr(&self.fd)
+	}
+
+	/// Get the effective credentials of the process which called `connect` or `pair`.
+	pub fn peer_cred(&self) -> std::io::Result<UCred> {
+		UCred::from_socket_peer(&self.fd)
+	}
+
+	/// Put the socket in blocking or non-blocking mode.
+	///
+	/// In non-blocking mode, operations that can not complete right away fail with an error of kind [`std::io::ErrorKind::WouldBlock`].
+	pub fn set_nonblocking(&self, nonblocking: bool) -> std::io::Result<()> {
+		sys::set_nonblocking(&self.fd, nonblocking)
+	}
+
+	/// Set the timeout for receive operations (`SO_RCVTIMEO`).
+	///
+	/// A timeout of [`None`] means that receive operations block indefinitely.
+	/// A zero duration is rejected with an error.
+	pub fn set_read_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()> {
+		sys::set_read_timeout(&self.fd, timeout)
+	}
+
+	/// Get the timeout for receive operations (`SO_RCVTIMEO`).
+	pub fn read_timeout(&self) -> std::io::Result<Option<Duration>> {
+		sys::get_read_timeout(&self.fd)
+	}
+
+	/// Set the timeout for send operations (`SO_SNDTIMEO`).
+	///
+	/// A timeout of [`None`] means that send operations block indefinitely.
+	/// A zero duration is rejected with an error.
+	pub fn set_write_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()> {
+		sys::set_write_timeout(&self.fd, timeout)
+	}
+
+	/// Get the timeout for send operations (`SO_SNDTIMEO`).
+	pub fn write_timeout(&self) -> std::io::Result<Option<Duration>> {
+		sys::get_write_timeout(&self.fd)
+	}
+
+	/// Get and clear the value of the `SO_ERROR` option.
+	pub fn take_error(&self) -> std::io::Result<Option<std::io::Error>> {
+		sys::take_socket_error(&self.fd)
+	}
+
+	/// Send data on the socket to the connected peer.
+	pub fn send(&self, buffer: &[u8]) -> std::io::Result<usize> {
+		sys::send(&self.fd, buffer)
+	}
+
+	/// Send data on the socket to the connected peer.
+	pub fn send_vectored(&self, buffer: &[IoSlice]) -> std::io::Result<usize> {
+		sys::send_msg(&self.fd, buffer, &mut SocketAncillary::new(&mut []))
+	}
+
+	/// Send data with ancillary data on the socket to the connected peer.
+	pub fn send_vectored_with_ancillary(
+		&self,
+		buffer: &[IoSlice],
+		ancillary: &mut SocketAncillary,
+	) -> std::io::Result<usize> {
+		sys::send_msg(&self.fd, buffer, ancillary)
+	}
+
+	/// Receive data on the socket from the connected peer.
+	pub fn recv(&self, buffer: &mut [u8]) -> std::io::Result<usize> {
+		sys::recv(&self.fd, buffer)
+	}
+
+	/// Receive data on the socket from the connected peer.
+	pub fn recv_vectored(&self, buffer: &mut [IoSliceMut]) -> std::io::Result<usize> {
+		sys::recv_msg(&self.fd, buffer, &mut SocketAncillary::new(&mut []))
+	}
+
+	/// Receive data with ancillary data on the socket from the connected peer.
+	///
+	/// Note that you should always wrap or close any file descriptors received this way.
+	/// If you do not, the received file descriptors will stay open until the process is terminated.
+	pub fn recv_vectored_with_ancillary(
+		&self,
+		buffer: &mut [IoSliceMut],
+		ancillary: &mut SocketAncillary,
+	) -> std::io::Result<usize> {
+		sys::recv_msg(&self.fd, buffer, ancillary)
+	}
+
+	/// Shuts down the read, write, or both halves of this connection.
+	pub fn shutdown(&self, how: std::net::Shutdown) -> std::io::Result<()> {
+		sys::shutdown(&self.fd, how)
+	}
+
+	/// Put the socket in non-blocking mode and register it with the tokio runtime.
+	///
+	/// Registration of the file descriptor with the tokio runtime may fail.
+	/// In that case, the socket is closed and an error is returned.
+	pub fn into_async(self) -> std::io::Result<crate::UnixSeqpacket> {
+		sys::set_nonblocking(&self.fd, true)?;
+		crate::UnixSeqpacket::new(self.fd)
+	}
+}
+
+impl AsFd for UnixSeqpacket {
+	fn as_fd(&self) -> BorrowedFd<'_> {
+		self.fd.as_fd()
+	}
+}
+
+impl AsRawFd for UnixSeqpacket {
+	fn as_raw_fd(&self) -> RawFd {
+		self.fd.as_raw_fd()
+	}
+}
+
+impl IntoRawFd for UnixSeqpacket {
+	fn into_raw_fd(self) -> RawFd {
+		self.fd.into_raw_fd()
+	}
+}
+
+impl FromRawFd for UnixSeqpacket {
+	/// Wrap a raw file descriptor.
+	///
+	/// The socket is used as-is: it is not put in blocking mode.
+	///
+	/// # Safety
+	/// This function is unsafe because the socket assumes it is the sole owner of the file descriptor.
+	unsafe fn from_raw_fd(fd: RawFd) -> Self {
+		Self {
+			fd: FileDesc::from_raw_fd(fd),
+		}
+	}
+}
+
+impl From<OwnedFd> for UnixSeqpacket {
+	fn from(fd: OwnedFd) -> Self {
+		unsafe { Self::from_raw_fd(fd.into_raw_fd()) }
+	}
+}
+
+impl From<UnixSeqpacket> for OwnedFd {
+	fn from(socket: UnixSeqpacket) -> Self {
+		unsafe { OwnedFd::from_raw_fd(socket.into_raw_fd()) }
+	}
+}
@@ -0,0 +1,28 @@
+use std::future::Future;
+use std::task::Poll;
+use tokio_util::sync::CancellationToken;
+
+use crate::UnixSeqpacket;
+
+impl UnixSeqpacket {
+	/// Receive data on the socket from the connected peer, unless the token is cancelled first.
+	///
+	/// Returns `Ok(Some(len))` if a datagram was received, or `Ok(None)` if the token was cancelled.
+	///
+	/// The token is checked before trying to receive a datagram,
+	/// so if it is already cancelled, no datagram is consumed.
+	/// If the token is cancelled while waiting, the datagram stays in the receive queue.
+	///
+	/// This function is only available with the `tokio-util` feature.
+	pub async fn recv_or_cancel(&self, buffer: &mut [u8], token: &CancellationToken) -> std::io::Result<Option<usize>> {
+		let mut cancelled = std::pin::pin!(token.cancelled());
+		let mut recv = std::pin::pin!(self.recv(buffer));
+		std::future::poll_fn(|cx| {
+			if cancelled.as_mut().poll(cx).is_ready() {
+				return Poll::Ready(Ok(None));
+			}
+			recv.as_mut().poll(cx).map_ok(Some)
+		})
+		.await
+	}
+}
@@ -0,0 +1,228 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+use crate::{sys, UnixSeqpacket};
+
+/// The maximum number of datagrams to queue before flushing them right away.
+const MAX_QUEUED_DATAGRAMS: usize = 64;
+
+/// A socket that coalesces small datagrams sent within a time window.
+///
+/// [`Self::send()`] queues the datagram instead of sending it right away, and returns immediately.
+/// The queue is flushed when the window since the first queued datagram has passed, or when the queue is full.
+/// All queued datagrams are sent together with a single `sendmmsg()` call on Linux and Android.
+/// The datagrams are not merged: the peer still receives each datagram separately.
+///
+/// This can reduce the number of system calls for protocols that send many small messages,
+/// at the cost of added latency.
+///
+/// The queue is flushed by a background task that is started when the socket is wrapped, and stopped when the wrapper is dropped.
+/// Datagrams that are still queued when the wrapper is dropped are discarded,
+/// so call [`Self::flush()`] or [`Self::into_inner()`] first.
+///
+/// # Errors
+/// If a background flush fails, the datagrams that were not sent stay queued,
+/// and the error is returned by the next call to [`Self::send()`] or [`Self::flush()`].
+/// A call to [`Self::flush()`] tries to send the remaining datagrams again.
+///
+/// # Ordering
+/// Sending through [`Self::get_ref()`] bypasses the queue, so those datagrams can overtake queued datagrams.
+/// Call [`Self::flush()`] first if the order matters.
+pub struct CoalescingSocket {
+	shared: Arc<Shared>,
+	task: JoinHandle<()>,
+}
+
+/// State shared with the flush task.
+struct Shared {
+	socket: UnixSeqpacket,
+	queue: Mutex<Queue>,
+
+	/// Held while flushing, to make sure batches are sent in order.
+	flush_lock: tokio::sync::Mutex<()>,
+
+	/// Notified when the first datagram of a new batch is queued.
+	batch_started: Notify,
+}
+
+struct Queue {
+	window: Duration,
+	datagrams: Vec<Vec<u8>>,
+	error: Option<std::io::Error>,
+}
+
+impl CoalescingSocket {
+	/// Wrap a socket to coalesce the datagrams sent within `window`.
+	///
+	/// This must be called from within a tokio runtime, since the queue is flushed by a spawned task.
+	pub fn new(socket: UnixSeqpacket, window: Duration) -> Self {
+		let shared = Arc::new(Shared {
+			socket,
+			queue: Mutex::new(Queue {
+				window,
+				datagrams: Vec::new(),
+				error: None,
+			}),
+			flush_lock: tokio::sync::Mutex::new(()),
+			batch_started: Notify::new(),
+		});
+		let task = tokio::spawn(flush_periodically(shared.clone()));
+		Self { shared, task }
+	}
+
+	/// Get a reference to the wrapped socket.
+	///
+	/// The socket can be used to receive datagrams, or to inspect and change socket options.
+	/// Datagrams sent through it are not queued, so they can overtake datagrams queued with [`Self::send()`].
+	pub fn get_ref(&self) -> &UnixSeqpacket {
+		&self.shared.socket
+	}
+
+	/// Change the coalescing window.
+	///
+	/// The new window applies from the next batch.
+	pub fn set_window(&self, window: Duration) {
+		self.shared.queue.lock().unwrap().window = window;
+	}
+
+	/// Get the coalescing window.
+	pub fn window(&self) -> Duration {
+		self.shared.queue.lock().unwrap().window
+	}
+
+	/// Get the number of datagrams that are queued and not yet sent.
+	pub fn queued(&self) -> usize {
+		self.shared.queue.lock().unwrap().datagrams.len()
+	}
+
+	/// Queue a datagram to be sent to the connected peer.
+	///
+	/// The datagram is copied into the queue, and `Ok(buffer.len())` is returned once it is queued, not once it is sent.
+	/// If the queue is full, it is flushed before this function returns.
+	///
+	/// If a background flush failed, that error is returned and the datagram is not queued.
+	pub async fn send(&self, buffer: &[u8]) -> std::io::Result<usize> {
+		let full = {
+			let mut queue = self.shared.queue.lock().unwrap();
+			if let Some(error) = queue.error.take() {
+				return Err(error);
+			}
+			queue.datagrams.push(buffer.to_vec());
+			if queue.datagrams.len() == 1 {
+				self.shared.batch_started.notify_one();
+			}
+			queue.datagrams.len() >= MAX_QUEUED_DATAGRAMS
+		};
+
+		if full {
+			self.shared.flush().await?;
+		}
+		Ok(buffer.len())
+	}
+
+	/// Send all queued datagrams now.
+	///
+	/// This also returns any error that occurred while flushing the queue in the background,
+	/// after trying to send the remaining datagrams again.
+	pub async fn flush(&self) -> std::io::Result<()> {
+		let error = self.shared.queue.lock().unwrap().error.take();
+		let result = self.shared.flush().await;
+		match error {
+			Some(error) => Err(error),
+			None => result,
+		}
+	}
+
+	/// Send all queued datagrams, stop the background task and return the wrapped socket.
+	///
+	/// If flushing fails, the error is returned and the queued datagrams are discarded.
+	pub async fn into_inner(mut self) -> std::io::Result<UnixSeqpacket> {
+		self.task.abort();
+		// The task holds a reference to the shared state until it is dropped.
+		let _ = (&mut self.task).await;
+		let shared = self.shared.clone();
+		drop(self);
+		if let Some(error) = shared.queue.lock().unwrap().error.take() {
+			return Err(error);
+		}
+		shared.flush().await?;
+		match Arc::try_unwrap(shared) {
+			Ok(shared) => Ok(shared.socket),
+			Err(_) => unreachable!("the flush task was stopped, so the shared state is not shared anymore"),
+		}
+	}
+}
+
+impl Drop for CoalescingSocket {
+	fn drop(&mut self) {
+		self.task.abort();
+	}
+}
+
+impl std::fmt::Debug for CoalescingSocket {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		f.debug_struct("CoalescingSocket")
+			.field("socket", &self.shared.socket)
+			.field("window", &self.window())
+			.field("queued", &self.queued())
+			.finish()
+	}
+}
+
+impl Shared {
+	/// Send all queued datagrams.
+	///
+	/// If sending fails or the future is dropped, the datagrams that were not sent are put back at the front of the queue.
+	async fn flush(&self) -> std::io::Result<()> {
+		let _flush_lock = self.flush_lock.lock().await;
+		let mut batch = Batch {
+			queue: &self.queue,
+			datagrams: std::mem::take(&mut self.queue.lock().unwrap().datagrams),
+			sent: 0,
+		};
+
+		let io = self.socket.as_async_fd();
+		while batch.sent < batch.datagrams.len() {
+			let mut ready_guard = io.writable().await?;
+			match ready_guard.try_io(|inner| sys::send_many(inner.get_ref(), &batch.datagrams[batch.sent..])) {
+				Ok(result) => batch.sent += result?,
+				Err(_would_block) => continue,
+			}
+		}
+		Ok(())
+	}
+}
+
+/// A batch of datagrams taken from the queue to be sent.
+///
+/// When dropped, the datagrams that were not sent are put back at the front of the queue.
+struct Batch<'a> {
+	queue: &'a Mutex<Queue>,
+	datagrams: Vec<Vec<u8>>,
+	sent: usize,
+}
+
+impl Drop for Batch<'_> {
+	fn drop(&mut self) {
+		let mut queue = self.queue.lock().unwrap();
+		let mut datagrams = std::mem::take(&mut self.datagrams);
+		datagrams.drain(..self.sent);
+		// Datagrams queued in the meantime go after the unsent ones, and the allocation is reused for the next batch.
+		datagrams.append(&mut queue.datagrams);
+		queue.datagrams = datagrams;
+	}
+}
+
+/// Flush the queue one window after the first datagram of each batch was queued.
+async fn flush_periodically(shared: Arc<Shared>) {
+	loop {
+		shared.batch_started.notified().await;
+		let window = shared.queue.lock().unwrap().window;
+		tokio::time::sleep(window).await;
+		if let Err(error) = shared.flush().await {
+			shared.queue.lock().unwrap().error = Some(error);
+		}
+	}
+}
@@ -0,0 +1,301 @@
+use std::time::Duration;
+
+use crate::{sys, UnixSeqpacket};
+
+/// A snapshot of the configurable options of a socket.
+///
+/// Use [`UnixSeqpacket::config()`] to read the configuration of a socket,
+/// and [`UnixSeqpacket::apply_config()`] to apply it to another socket.
+///
+/// With the `serde` feature enabled, the configuration can be serialized and deserialized.
+/// This can be used to store the setup of a connection and reproduce it later.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct SocketConfig {
+	/// The size of the send buffer in bytes (`SO_SNDBUF`).
+	///
+	/// This is the value as reported by the kernel.
+	/// On Linux, this is double the value that was requested, to account for bookkeeping overhead.
+	pub send_buffer_size: usize,
+
+	/// The size of the receive buffer in bytes (`SO_RCVBUF`).
+	///
+	/// This is the value as reported by the kernel.
+	/// On Linux, this is double the value that was requested, to account for bookkeeping overhead.
+	pub recv_buffer_size: usize,
+
+	/// If true, the credentials of the peer are received with each message (`SO_PASSCRED`).
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	pub pass_credentials: bool,
+
+	/// The timeout for blocking receive operations (`SO_RCVTIMEO`).
+	///
+	/// This has no effect on the async functions of this crate,
+	/// but it does apply if the file descriptor is used in blocking mode.
+	pub read_timeout: Option<Duration>,
+
+	/// The timeout for blocking send operations (`SO_SNDTIMEO`).
+	///
+	/// This has no effect on the async functions of this crate,
+	/// but it does apply if the file descriptor is used in blocking mode.
+	pub write_timeout: Option<Duration>,
+
+	/// If true, the file descriptor is closed when the process calls `exec` (`FD_CLOEXEC`).
+	pub close_on_exec: bool,
+}
+
+impl UnixSeqpacket {
+	/// Read the current configuration of the socket.
+	///
+	/// The result can be applied to another socket with [`Self::apply_config()`].
+	pub fn config(&self) -> std::io::Result<SocketConfig> {
+		let socket = self.as_async_fd().get_ref();
+		Ok(SocketConfig {
+			send_buffer_size: sys::get_send_buffer_size(socket)?,
+			recv_buffer_size: sys::get_recv_buffer_size(socket)?,
+			#[cfg(any(target_os = "android", target_os = "linux"))]
+			pass_credentials: sys::get_passcred(socket)?,
+			read_timeout: sys::get_read_timeout(socket)?,
+			write_timeout: sys::get_write_timeout(socket)?,
+			close_on_exec: socket.get_close_on_exec()?,
+		})
+	}
+
+	/// Apply a configuration to the socket.
+	///
+	/// The buffer sizes are interpreted as values reported by [`Self::config()`].
+	/// On Linux, the kernel doubles the requested buffer sizes, so they are halved before being applied.
+	/// That way, applying the configuration of one socket to another results in the same reported sizes.
+	///
+	/// The kernel may still clamp buffer sizes to system-wide limits.
+	pub fn apply_config(&self, config: &SocketConfig) -> std::io::Result<()> {
+		let socket = self.as_async_fd().get_ref();
+		sys::set_send_buffer_size(socket, kernel_buffer_size(config.send_buffer_size))?;
+		sys::set_recv_buffer_size(socket, kernel_buffer_size(config.recv_buffer_size))?;
+		#[cfg(any(target_os = "android", target_os = "linux"))]
+		sys::set_passcred(socket, config.pass_credentials)?;
+		sys::set_read_timeout(socket, config.read_timeout)?;
+		sys::set_write_timeout(socket, config.write_timeout)?;
+		socket.set_close_on_exec(config.close_on_exec)?;
+		Ok(())
+	}
+
+	/// Get the size of the send buffer in bytes (`SO_SNDBUF`).
+	///
+	/// This is the value as reported by the kernel.
+	/// On Linux, this is double the value that was requested with [`Self::set_send_buffer_size()`],
+	/// to account for bookkeeping overhead.
+	pub fn send_buffer_size(&self) -> std::io::Result<usize> {
+		sys::get_send_buffer_size(self.as_async_fd().get_ref())
+	}
+
+	/// Set the size of the send buffer in bytes (`SO_SNDBUF`).
+	///
+	/// On Linux, the kernel doubles the requested size to account for bookkeeping overhead,
+	/// so [`Self::send_buffer_size()`] reports a larger value than requested.
+	/// The kernel also clamps the size to a system-wide limit (`/proc/sys/net/core/wmem_max` on Linux).
+	/// On Linux and Android, [`Self::set_send_buffer_size_force()`] can be used to ignore that limit.
+	pub fn set_send_buffer_size(&self, size: usize) -> std::io::Result<()> {
+		sys::set_send_buffer_size(self.as_async_fd().get_ref(), size)
+	}
+
+	/// Get the size of the receive buffer in bytes (`SO_RCVBUF`).
+	///
+	/// This is the value as reported by the kernel.
+	/// On Linux, this is double the value that was requested with [`Self::set_recv_buffer_size()`],
+	/// to account for bookkeeping overhead.
+	pub fn recv_buffer_size(&self) -> std::io::Result<usize> {
+		sys::get_recv_buffer_size(self.as_async_fd().get_ref())
+	}
+
+	/// Set the size of the receive buffer in bytes (`SO_RCVBUF`).
+	///
+	/// On Linux, the kernel doubles the requested size to account for bookkeeping overhead,
+	/// so [`Self::recv_buffer_size()`] reports a larger value than requested.
+	/// The kernel also clamps the size to a system-wide limit (`/proc/sys/net/core/rmem_max` on Linux).
+	/// On Linux and Android, [`Self::set_recv_buffer_size_force()`] can be used to ignore that limit.
+	pub fn set_recv_buffer_size(&self, size: usize) -> std::io::Result<()> {
+		sys::set_recv_buffer_size(self.as_async_fd().get_ref(), size)
+	}
+
+	/// Set the size of the send buffer, ignoring the system-wide limit (`SO_SNDBUFFORCE`).
+	///
+	/// Like with `SO_SNDBUF`, the kernel doubles the requested size to account for bookkeeping overhead.
+	/// The resulting size can be read with [`Self::config()`].
+	///
+	/// This requires the `CAP_NET_ADMIN` capability.
+	/// Without it, an error of kind [`std::io::ErrorKind::PermissionDenied`] is returned.
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	pub fn set_send_buffer_size_force(&self, size: usize) -> std::io::Result<()> {
+		sys::set_send_buffer_size_force(self.as_async_fd().get_ref(), size)
+	}
+
+	/// Set the size of the receive buffer, ignoring the system-wide limit (`SO_RCVBUFFORCE`).
+	///
+	/// Like with `SO_RCVBUF`, the kernel doubles the requested size to account for bookkeeping overhead.
+	/// The resulting size can be read with [`Self::config()`].
+	///
+	/// This requires the `CAP_NET_ADMIN` capability.
+	/// Without it, an error of kind [`std::io::ErrorKind::PermissionDenied`] is returned.
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	pub fn set_recv_buffer_size_force(&self, size: usize) -> std::io::Result<()> {
+		sys::set_recv_buffer_size_force(self.as_async_fd().get_ref(), size)
+	}
+
+	/// Check if the file descriptor is closed when the process calls `exec` (`FD_CLOEXEC`).
+	///
+	/// Sockets created by this crate always have the flag set,
+	/// but a socket adopted with [`Self::from_raw_fd()`] keeps the flag it was created with.
+	/// The flag can be changed with [`Self::apply_config()`].
+	pub fn is_cloexec(&self) -> std::io::Result<bool> {
+		self.as_async_fd().get_ref().get_close_on_exec()
+	}
+
+	/// Check if the credentials of the peer are received with each message (`SO_PASSCRED`).
+	///
+	/// This is only available on Linux and Android.
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	pub fn passcred(&self) -> std::io::Result<bool> {
+		sys::get_passcred(self.as_async_fd().get_ref())
+	}
+
+	/// Enable or disable receiving the credentials of the peer with each message (`SO_PASSCRED`).
+	///
+	/// When enabled, the kernel attaches a `SCM_CREDENTIALS` control message to every received message,
+	/// even if the sender did not add one.
+	/// This is the same as setting [`SocketConfig::pass_credentials`] with [`Self::apply_config()`].
+	///
+	/// This is only available on Linux and Android.
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	pub fn set_passcred(&self, enable: bool) -> std::io::Result<()> {
+		sys::set_passcred(self.as_async_fd().get_ref(), enable)
+	}
+
+	/// Check if received datagrams are timestamped by the kernel (`SO_TIMESTAMPNS`).
+	///
+	/// This is only available on Linux and Android.
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	pub fn receive_timestamp(&self) -> std::io::Result<bool> {
+		sys::get_timestampns(self.as_async_fd().get_ref())
+	}
+
+	/// Enable or disable kernel timestamps for received datagrams (`SO_TIMESTAMPNS`).
+	///
+	/// When enabled, the kernel records the time at which each datagram is queued,
+	/// and attaches it as a `SCM_TIMESTAMPNS` control message when the datagram is received.
+	/// The timestamp is reported as [`AncillaryData::Timestamp`](crate::ancillary::AncillaryData::Timestamp).
+	/// It can be combined with other control messages, such as `SCM_RIGHTS`, in the same datagram.
+	///
+	/// This is only available on Linux and Android.
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	pub fn set_receive_timestamp(&self, enable: bool) -> std::io::Result<()> {
+		sys::set_timestampns(self.as_async_fd().get_ref(), enable)
+	}
+
+	/// Get the priority of outgoing datagrams (`SO_PRIORITY`).
+	///
+	/// This is only available on Linux and Android.
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	pub fn priority(&self) -> std::io::Result<u32> {
+		sys::get_priority(self.as_async_fd().get_ref())
+	}
+
+	/// Set the priority of outgoing datagrams (`SO_PRIORITY`).
+	///
+	/// Priorities from 0 to 6 can be set by any process.
+	/// Higher priorities require the `CAP_NET_ADMIN` capability.
+	/// Without it, an error of kind [`std::io::ErrorKind::PermissionDenied`] is returned.
+	///
+	/// This is only available on Linux and Android.
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	pub fn set_priority(&self, priority: u32) -> std::io::Result<()> {
+		sys::set_priority(self.as_async_fd().get_ref(), priority)
+	}
+}
+
+/// Convert a buffer size as reported by the kernel to the value that should be requested.
+fn kernel_buffer_size(reported: usize) -> usize {
+	if cfg!(any(target_os = "android", target_os = "linux")) {
+		reported / 2
+	} else {
+		reported
+	}
+}
+
+/// Options for creating a listener with [`UnixSeqpacketListener::bind_configured()`](crate::UnixSeqpacketListener::bind_configured).
+///
+/// # Example
+/// ```no_run
+/// # fn foo() -> std::io::Result<()> {
+/// use tokio_seqpacket::{ListenerConfig, UnixSeqpacketListener};
+///
+/// let config = ListenerConfig::new().backlog(64).mode(0o660).unlink_on_drop(true);
+/// let listener = UnixSeqpacketListener::bind_configured("/run/foo.sock", &config)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListenerConfig {
+	pub(crate) backlog: std::os::raw::c_int,
+	pub(crate) mode: Option<u32>,
+	pub(crate) unlink_on_drop: bool,
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	pub(crate) pass_credentials: bool,
+}
+
+impl ListenerConfig {
+	/// Create a new configuration with the default options.
+	///
+	/// By default, the backlog is 128, the permissions of the socket file are determined by the umask of the process,
+	/// the socket file is not removed when the listener is dropped and `SO_PASSCRED` is not enabled.
+	pub fn new() -> Self {
+		Self {
+			backlog: crate::listener::DEFAULT_BACKLOG,
+			mode: None,
+			unlink_on_drop: false,
+			#[cfg(any(target_os = "android", target_os = "linux"))]
+			pass_credentials: false,
+		}
+	}
+
+	/// Set the size of the connection queue.
+	///
+	/// See [`UnixSeqpacketListener::bind_with_backlog()`](crate::UnixSeqpacketListener::bind_with_backlog)
+	/// for how the backlog is capped by the kernel and how negative values are handled.
+	pub fn backlog(mut self, backlog: std::os::raw::c_int) -> Self {
+		self.backlog = backlog;
+		self
+	}
+
+	/// Set the permissions of the socket file.
+	///
+	/// The permissions are changed after the socket is bound, but before it starts listening,
+	/// so no connections can be accepted before the permissions are applied.
+	pub fn mode(mut self, mode: u32) -> Self {
+		self.mode = Some(mode);
+		self
+	}
+
+	/// Remove the socket file when the listener is dropped.
+	pub fn unlink_on_drop(mut self, unlink_on_drop: bool) -> Self {
+		self.unlink_on_drop = unlink_on_drop;
+		self
+	}
+
+	/// Enable `SO_PASSCRED` on the listener.
+	///
+	/// Accepted connections inherit this option,
+	/// so they receive the credentials of the peer with each message.
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	pub fn pass_credentials(mut self, pass_credentials: bool) -> Self {
+		self.pass_credentials = pass_credentials;
+		self
+	}
+}
+
+impl Default for ListenerConfig {
+	fn default() -> Self {
+		Self::new()
+	}
+}
@@ -0,0 +1,242 @@
+/// The ancillary data of a received message did not fit in the control buffer.
+///
+/// This is returned wrapped in an [`std::io::Error`] with kind [`std::io::ErrorKind::InvalidData`]
+/// by functions that allocate the control buffer themselves.
+/// Use [`std::io::Error::get_ref()`] and [`downcast_ref()`](std::error::Error::downcast_ref) to inspect it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ControlTruncated {
+	pub(crate) capacity: usize,
+}
+
+impl ControlTruncated {
+	/// The size of the control buffer that was used to receive the message.
+	pub fn capacity(&self) -> usize {
+		self.capacity
+	}
+}
+
+impl std::fmt::Display for ControlTruncated {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(
+			f,
+			"ancillary data of received datagram was truncated to {} bytes",
+			self.capacity
+		)
+	}
+}
+
+impl std::error::Error for ControlTruncated {}
+
+impl From<ControlTruncated> for std::io::Error {
+	fn from(other: ControlTruncated) -> Self {
+		std::io::Error::new(std::io::ErrorKind::InvalidData, other)
+	}
+}
+
+/// The socket at the given path is not a seqpacket socket.
+///
+/// This is returned wrapped in an [`std::io::Error`] with kind [`std::io::ErrorKind::InvalidInput`]
+/// by [`UnixSeqpacket::connect()`](crate::UnixSeqpacket::connect) when the kernel reports `EPROTOTYPE`,
+/// which happens when the other socket is a stream or datagram socket.
+/// Use [`std::io::Error::get_ref()`] and [`downcast_ref()`](std::error::Error::downcast_ref) to inspect it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SocketTypeMismatch {
+	pub(crate) path: std::path::PathBuf,
+}
+
+impl SocketTypeMismatch {
+	/// The path that the connection was attempted to.
+	pub fn path(&self) -> &std::path::Path {
+		&self.path
+	}
+}
+
+impl std::fmt::Display for SocketTypeMismatch {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(
+			f,
+			"socket at {} is not a seqpacket socket (it may be a stream or datagram socket)",
+			self.path.display()
+		)
+	}
+}
+
+impl std::error::Error for SocketTypeMismatch {}
+
+impl From<SocketTypeMismatch> for std::io::Error {
+	fn from(other: SocketTypeMismatch) -> Self {
+		std::io::Error::new(std::io::ErrorKind::InvalidInput, other)
+	}
+}
+
+/// A file descriptor could not be sent.
+///
+/// This is returned wrapped in an [`std::io::Error`] by [`UnixSeqpacket::send_and_close_fd()`](crate::UnixSeqpacket::send_and_close_fd),
+/// so the caller gets the file descriptor back.
+/// The [`std::io::Error`] has the same kind as the original error.
+/// Use [`std::io::Error::into_inner()`] and [`downcast()`](https://doc.rust-lang.org/std/boxed/struct.Box.html#method.downcast) to recover it.
+#[derive(Debug)]
+pub struct FdNotSent {
+	pub(crate) error: std::io::Error,
+	pub(crate) fd: std::os::unix::io::OwnedFd,
+}
+
+impl FdNotSent {
+	/// The error that occurred while sending.
+	pub fn error(&self) -> &std::io::Error {
+		&self.error
+	}
+
+	/// Get the file descriptor that was not sent.
+	pub fn into_fd(self) -> std::os::unix::io::OwnedFd {
+		self.fd
+	}
+
+	/// Split the error into the original error and the file descriptor that was not sent.
+	pub fn into_parts(self) -> (std::io::Error, std::os::unix::io::OwnedFd) {
+		(self.error, self.fd)
+	}
+}
+
+impl std::fmt::Display for FdNotSent {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "failed to send file descriptor: {}", self.error)
+	}
+}
+
+impl std::error::Error for FdNotSent {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		Some(&self.error)
+	}
+}
+
+impl From<FdNotSent> for std::io::Error {
+	fn from(other: FdNotSent) -> Self {
+		std::io::Error::new(other.error.kind(), other)
+	}
+}
+
+/// An I/O error on a socket with a label set with [`UnixSeqpacket::set_label()`](crate::UnixSeqpacket::set_label).
+///
+/// This is returned wrapped in an [`std::io::Error`] with the same kind as the original error.
+/// Use [`std::io::Error::get_ref()`] and [`downcast_ref()`](std::error::Error::downcast_ref) to inspect it.
+#[derive(Debug)]
+pub struct LabeledError {
+	pub(crate) operation: &'static str,
+	pub(crate) label: std::sync::Arc<str>,
+	pub(crate) error: std::io::Error,
+}
+
+impl LabeledError {
+	/// The operation that failed, like `"send"` or `"recv"`.
+	pub fn operation(&self) -> &'static str {
+		self.operation
+	}
+
+	/// The label of the socket.
+	pub fn label(&self) -> &str {
+		&self.label
+	}
+
+	/// The original error.
+	pub fn error(&self) -> &std::io::Error {
+		&self.error
+	}
+
+	/// Get the original error.
+	pub fn into_error(self) -> std::io::Error {
+		self.error
+	}
+}
+
+impl std::fmt::Display for LabeledError {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(
+			f,
+			"{} failed on connection '{}': {}",
+			self.operation, self.label, self.error
+		)
+	}
+}
+
+impl std::error::Error for LabeledError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		Some(&self.error)
+	}
+}
+
+impl From<LabeledError> for std::io::Error {
+	fn from(other: LabeledError) -> Self {
+		std::io::Error::new(other.error.kind(), other)
+	}
+}
+
+/// A received datagram was larger than the limit set with [`UnixSeqpacket::set_max_payload()`](crate::UnixSeqpacket::set_max_payload).
+///
+/// This is returned wrapped in an [`std::io::Error`] with kind [`std::io::ErrorKind::InvalidData`].
+/// The oversized datagram has been discarded.
+/// Use [`std::io::Error::get_ref()`] and [`downcast_ref()`](std::error::Error::downcast_ref) to inspect it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PayloadTooLarge {
+	pub(crate) size: usize,
+	pub(crate) max: usize,
+}
+
+impl PayloadTooLarge {
+	/// The size of the discarded datagram.
+	pub fn size(&self) -> usize {
+		self.size
+	}
+
+	/// The maximum payload size that was set on the socket.
+	pub fn max(&self) -> usize {
+		self.max
+	}
+}
+
+impl std::fmt::Display for PayloadTooLarge {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(
+			f,
+			"discarded datagram of {} bytes, which exceeds the maximum payload size of {} bytes",
+			self.size, self.max
+		)
+	}
+}
+
+impl std::error::Error for PayloadTooLarge {}
+
+impl From<PayloadTooLarge> for std::io::Error {
+	fn from(other: PayloadTooLarge) -> Self {
+		std::io::Error::new(std::io::ErrorKind::InvalidData, other)
+	}
+}
+
+/// A [`Sender`](crate::Sender) and [`Receiver`](crate::Receiver) could not be combined into a socket.
+///
+/// This is returned by [`Sender::reunite()`](crate::Sender::reunite) and [`Receiver::reunite()`](crate::Receiver::reunite)
+/// if the handles belong to different sockets, or if other handles to the socket still exist.
+/// Use [`Self::into_parts()`] to get the handles back.
+#[derive(Debug)]
+pub struct ReuniteError {
+	pub(crate) sender: crate::Sender,
+	pub(crate) receiver: crate::Receiver,
+}
+
+impl ReuniteError {
+	/// Get the sender and receiver that could not be combined.
+	pub fn into_parts(self) -> (crate::Sender, crate::Receiver) {
+		(self.sender, self.receiver)
+	}
+}
+
+impl std::fmt::Display for ReuniteError {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(
+			f,
+			"failed to reunite sender and receiver: they belong to different sockets or other handles still exist"
+		)
+	}
+}
+
+impl std::error::Error for ReuniteError {}
@@ -0,0 +1,111 @@
+use std::path::{Path, PathBuf};
+
+use crate::UnixSeqpacket;
+
+/// A seqpacket connection that fails over to other addresses when the connection breaks.
+///
+/// The socket connects to the first reachable address in its list.
+/// If a send or receive fails because the connection was lost
+/// (an error of kind [`ConnectionReset`], [`BrokenPipe`] or [`NotConnected`]),
+/// the socket reconnects to the next reachable address and retries the operation once.
+///
+/// Note that a datagram that was queued on the old connection but not yet read by the peer is lost.
+/// A peer that closes the connection normally is reported by a receive of zero bytes,
+/// which does not trigger a failover.
+///
+/// [`ConnectionReset`]: std::io::ErrorKind::ConnectionReset
+/// [`BrokenPipe`]: std::io::ErrorKind::BrokenPipe
+/// [`NotConnected`]: std::io::ErrorKind::NotConnected
+#[derive(Debug)]
+pub struct FailoverSocket {
+	addresses: Vec<PathBuf>,
+	current: usize,
+	socket: UnixSeqpacket,
+}
+
+impl FailoverSocket {
+	/// Connect to the first reachable address in the list.
+	///
+	/// If no address can be connected to, the error of the last attempt is returned.
+	/// If the list is empty, an error of kind [`std::io::ErrorKind::InvalidInput`] is returned.
+	pub async fn connect<P: AsRef<Path>>(addresses: impl IntoIterator<Item = P>) -> std::io::Result<Self> {
+		let addresses: Vec<PathBuf> = addresses.into_iter().map(|path| path.as_ref().to_owned()).collect();
+		if addresses.is_empty() {
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidInput,
+				"no addresses to connect to",
+			));
+		}
+		let (current, socket) = connect_from(&addresses, 0).await?;
+		Ok(Self {
+			addresses,
+			current,
+			socket,
+		})
+	}
+
+	/// Get the address of the current connection.
+	pub fn current_address(&self) -> &Path {
+		&self.addresses[self.current]
+	}
+
+	/// Get the underlying socket of the current connection.
+	pub fn socket(&self) -> &UnixSeqpacket {
+		&self.socket
+	}
+
+	/// Send data on the current connection, failing over to the next address if the connection was lost.
+	pub async fn send(&mut self, buffer: &[u8]) -> std::io::Result<usize> {
+		match self.socket.send(buffer).await {
+			Err(e) if is_connection_lost(&e) => {
+				self.failover().await?;
+				self.socket.send(buffer).await
+			},
+			result => result,
+		}
+	}
+
+	/// Receive data on the current connection, failing over to the next address if the connection was lost.
+	pub async fn recv(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+		match self.socket.recv(buffer).await {
+			Err(e) if is_connection_lost(&e) => {
+				self.failover().await?;
+				self.socket.recv(buffer).await
+			},
+			result => result,
+		}
+	}
+
+	/// Replace the current connection with a connection to the next reachable address.
+	///
+	/// The addresses are tried in order, starting after the current address and wrapping around,
+	/// so the current address is tried last.
+	async fn failover(&mut self) -> std::io::Result<()> {
+		let (current, socket) = connect_from(&self.addresses, self.current + 1).await?;
+		self.current = current;
+		self.socket = socket;
+		Ok(())
+	}
+}
+
+/// Connect to the first reachable address, starting at index `start` and wrapping around.
+async fn connect_from(addresses: &[PathBuf], start: usize) -> std::io::Result<(usize, UnixSeqpacket)> {
+	let mut last_error = None;
+	for offset in 0..addresses.len() {
+		let index = (start + offset) % addresses.len();
+		match UnixSeqpacket::connect(&addresses[index]).await {
+			Ok(socket) => return Ok((index, socket)),
+			Err(e) => last_error = Some(e),
+		}
+	}
+	Err(last_error.unwrap_or_else(|| std::io::ErrorKind::InvalidInput.into()))
+}
+
+/// Check if an error means that the connection was lost.
+fn is_connection_lost(error: &std::io::Error) -> bool {
+	use std::io::ErrorKind;
+	matches!(
+		error.kind(),
+		ErrorKind::ConnectionReset | ErrorKind::BrokenPipe | ErrorKind::NotConnected
+	)
+}
@@ -0,0 +1,153 @@
+use std::sync::Mutex;
+use std::task::{Context, Poll, Waker};
+
+use crate::UnixSeqpacket;
+
+/// Accounting for file descriptors that were sent but not yet acknowledged by the peer.
+#[derive(Default)]
+pub(crate) struct FdLimit {
+	state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+	max: Option<usize>,
+	in_flight: usize,
+	waiters: Vec<Waker>,
+}
+
+impl FdLimit {
+	/// Check if a limit is set.
+	pub(crate) fn is_enabled(&self) -> bool {
+		self.state.lock().unwrap().max.is_some()
+	}
+
+	/// Check if there is room for `count` more file descriptors.
+	///
+	/// If there is room, `send` is called while holding the lock,
+	/// and the file descriptors are counted as in flight if it succeeds.
+	/// If there is no room, the current task is woken up when file descriptors are acknowledged.
+	pub(crate) fn poll_send_with(
+		&self,
+		cx: &mut Context,
+		count: usize,
+		send: impl FnOnce(&mut Context) -> Poll<std::io::Result<usize>>,
+	) -> Poll<std::io::Result<usize>> {
+		let mut state = self.state.lock().unwrap();
+		let max = match state.max {
+			Some(max) => max,
+			None => return send(cx),
+		};
+
+		if count > max {
+			return Poll::Ready(Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidInput,
+				format!("can not send {} file descriptors with at most {} in flight", count, max),
+			)));
+		}
+
+		if state.in_flight + count > max {
+			if !state.waiters.iter().any(|waker| waker.will_wake(cx.waker())) {
+				state.waiters.push(cx.waker().clone());
+			}
+			return Poll::Pending;
+		}
+
+		let result = std::task::ready!(send(cx));
+		if result.is_ok() {
+			state.in_flight += count;
+		}
+		Poll::Ready(result)
+	}
+
+	/// Check if there is room for `count` more file descriptors without registering for wake-up.
+	///
+	/// If there is room, `send` is called while holding the lock,
+	/// and the file descriptors are counted as in flight if it succeeds.
+	/// If there is no room, an error of kind [`std::io::ErrorKind::WouldBlock`] is returned.
+	pub(crate) fn try_send_with(
+		&self,
+		count: usize,
+		send: impl FnOnce() -> std::io::Result<usize>,
+	) -> std::io::Result<usize> {
+		let mut state = self.state.lock().unwrap();
+		let max = match state.max {
+			Some(max) => max,
+			None => return send(),
+		};
+
+		if count > max {
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidInput,
+				format!("can not send {} file descriptors with at most {} in flight", count, max),
+			));
+		}
+
+		if state.in_flight + count > max {
+			return Err(std::io::ErrorKind::WouldBlock.into());
+		}
+
+		let result = send();
+		if result.is_ok() {
+			state.in_flight += count;
+		}
+		result
+	}
+
+	/// Wake up all tasks waiting for room.
+	fn wake_all(state: &mut State) {
+		for waker in state.waiters.drain(..) {
+			waker.wake();
+		}
+	}
+}
+
+impl UnixSeqpacket {
+	/// Limit the number of file descriptors that can be in flight to the peer.
+	///
+	/// A file descriptor is in flight from the moment it is sent until the application calls [`Self::acknowledge_fds()`].
+	/// Normally, the peer sends a small acknowledgement message for every file descriptor it consumed,
+	/// and the application calls [`Self::acknowledge_fds()`] when it receives that message.
+	/// The format of the acknowledgements is up to the application.
+	///
+	/// When the limit is reached, [`Self::send_vectored_with_ancillary()`]
+	/// and [`Self::poll_send_vectored_with_ancillary()`] wait until enough file descriptors are acknowledged.
+	/// A message with more file descriptors than the limit is rejected with an error of kind [`std::io::ErrorKind::InvalidInput`].
+	///
+	/// This prevents a slow peer from accumulating an unbounded number of file descriptors,
+	/// each of which holds on to kernel resources.
+	///
+	/// Only file descriptors sent while a limit is set are counted.
+	pub fn set_max_fds_in_flight(&self, max: usize) {
+		let mut state = self.fd_limit.state.lock().unwrap();
+		state.max = Some(max);
+		FdLimit::wake_all(&mut state);
+	}
+
+	/// Remove the limit on the number of file descriptors in flight.
+	///
+	/// This also resets the number of file descriptors in flight to zero.
+	pub fn clear_max_fds_in_flight(&self) {
+		let mut state = self.fd_limit.state.lock().unwrap();
+		state.max = None;
+		state.in_flight = 0;
+		FdLimit::wake_all(&mut state);
+	}
+
+	/// Get the number of file descriptors that were sent but not yet acknowledged.
+	///
+	/// See [`Self::set_max_fds_in_flight()`].
+	pub fn fds_in_flight(&self) -> usize {
+		self.fd_limit.state.lock().unwrap().in_flight
+	}
+
+	/// Mark `count` file descriptors as consumed by the peer.
+	///
+	/// This wakes up tasks waiting to send more file descriptors.
+	/// See [`Self::set_max_fds_in_flight()`].
+	pub fn acknowledge_fds(&self, count: usize) {
+		let mut state = self.fd_limit.state.lock().unwrap();
+		state.in_flight = state.in_flight.saturating_sub(count);
+		FdLimit::wake_all(&mut state);
+	}
+}
@@ -0,0 +1,114 @@
+use std::io::{IoSlice, IoSliceMut};
+use std::time::Duration;
+
+use crate::ancillary::{AncillaryData, CredFields, SocketAncillary, SocketCred};
+use crate::{UCred, UnixSeqpacket};
+
+/// The result of [`UnixSeqpacket::handshake()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HandshakeResult {
+	/// The capabilities that both sides support.
+	pub capabilities: u64,
+
+	/// The capabilities that the peer announced.
+	pub peer_capabilities: u64,
+
+	/// The credentials of the peer, as verified by the kernel.
+	pub peer_cred: UCred,
+}
+
+impl UnixSeqpacket {
+	/// Exchange credentials and capabilities with the peer.
+	///
+	/// Both sides of the connection must call this function as the first thing they do after connecting.
+	/// Each side sends a single datagram with its capabilities as a big-endian `u64`, and its credentials in a `SCM_CREDENTIALS` message.
+	/// The kernel verifies the credentials, so a process can not pretend to be someone else.
+	///
+	/// The credentials received from the peer are compared with the credentials recorded when the connection was made (see [`Self::peer_cred()`]).
+	/// If the user or group ID differs, an error of kind [`std::io::ErrorKind::PermissionDenied`] is returned.
+	/// The process IDs are not compared, since the connection may have been handed to another process.
+	///
+	/// `SO_PASSCRED` is enabled for the duration of the handshake, and restored to its previous value afterwards.
+	///
+	/// If the handshake does not complete within `timeout`, an error of kind [`std::io::ErrorKind::TimedOut`] is returned.
+	/// If the peer sends a malformed handshake, an error of kind [`std::io::ErrorKind::InvalidData`] is returned.
+	///
+	/// This function is only available on Linux and Android.
+	pub async fn handshake(&self, my_caps: u64, timeout: Duration) -> std::io::Result<HandshakeResult> {
+		let passcred = self.passcred()?;
+		self.set_passcred(true)?;
+		let result = match tokio::time::timeout(timeout, self.exchange_handshake(my_caps)).await {
+			Ok(result) => result,
+			Err(_elapsed) => Err(std::io::Error::new(
+				std::io::ErrorKind::TimedOut,
+				"timed out waiting for handshake",
+			)),
+		};
+		self.set_passcred(passcred)?;
+		result
+	}
+
+	/// Send our handshake and receive the handshake of the peer.
+	async fn exchange_handshake(&self, my_caps: u64) -> std::io::Result<HandshakeResult> {
+		let mut own_cred = SocketCred::new();
+		// SAFETY: These functions are always successful and have no side effects.
+		unsafe {
+			own_cred.set_pid(libc::getpid());
+			own_cred.set_uid(libc::getuid());
+			own_cred.set_gid(libc::getgid());
+		}
+
+		let mut control = [0; 64];
+		let mut ancillary = SocketAncillary::new(&mut control);
+		let added = ancillary.add_creds(&[own_cred]);
+		debug_assert!(added);
+		self.send_vectored_with_ancillary(&[IoSlice::new(&my_caps.to_be_bytes())], &mut ancillary)
+			.await?;
+
+		// One extra byte to detect datagrams that are too long.
+		let mut caps = [0u8; 9];
+		let mut control = [0; 64];
+		let mut ancillary = SocketAncillary::new(&mut control);
+		let len = self
+			.recv_vectored_with_ancillary(&mut [IoSliceMut::new(&mut caps)], &mut ancillary)
+			.await?;
+		// Close any file descriptors that the peer sent along.
+		drop(ancillary.take_fds());
+
+		if len != 8 {
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				format!("received handshake of {} bytes, expected 8 bytes", len),
+			));
+		}
+		let peer_capabilities = u64::from_be_bytes(caps[..8].try_into().unwrap());
+
+		let received_cred = ancillary
+			.messages()
+			.filter_map(|message| match message {
+				Ok(AncillaryData::ScmCredentials(mut creds)) => creds.next(),
+				_ => None,
+			})
+			.next()
+			.ok_or_else(|| {
+				std::io::Error::new(
+					std::io::ErrorKind::InvalidData,
+					"received handshake without credentials",
+				)
+			})?;
+
+		let peer_cred = self.peer_cred()?;
+		if !received_cred.matches(&SocketCred::from(peer_cred), CredFields::UID | CredFields::GID) {
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::PermissionDenied,
+				"credentials in handshake do not match the credentials of the peer",
+			));
+		}
+
+		Ok(HandshakeResult {
+			capabilities: my_caps & peer_capabilities,
+			peer_capabilities,
+			peer_cred,
+		})
+	}
+}
@@ -0,0 +1,89 @@
+use filedesc::FileDesc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::oneshot;
+use tokio::time::Instant;
+
+use crate::sys;
+
+/// Closes a connection that has not received anything for too long.
+///
+/// A background task waits for the deadline and shuts down the socket when it passes.
+/// The task stops when this object is dropped.
+pub(crate) struct IdleTimeout {
+	state: Arc<State>,
+	_stop: oneshot::Sender<()>,
+}
+
+struct State {
+	timeout: Duration,
+	started: Instant,
+	/// The time of the last receive, in nanoseconds since `started`.
+	last_activity: AtomicU64,
+	expired: AtomicBool,
+}
+
+impl IdleTimeout {
+	/// Start watching a socket.
+	///
+	/// The background task uses a duplicate of the file descriptor to shut down the socket.
+	/// This must be called from within a tokio runtime.
+	pub(crate) fn start(socket: &FileDesc, timeout: Duration) -> std::io::Result<Self> {
+		let socket = socket.duplicate()?;
+		let state = Arc::new(State {
+			timeout,
+			started: Instant::now(),
+			last_activity: AtomicU64::new(0),
+			expired: AtomicBool::new(false),
+		});
+		let (stop, stopped) = oneshot::channel();
+		tokio::spawn(watch(socket, state.clone(), stopped));
+		Ok(Self { state, _stop: stop })
+	}
+
+	/// Get the idle timeout.
+	pub(crate) fn timeout(&self) -> Duration {
+		self.state.timeout
+	}
+
+	/// Record a successful receive, which restarts the idle timeout.
+	pub(crate) fn touch(&self) {
+		let elapsed = u64::try_from(self.state.started.elapsed().as_nanos()).unwrap_or(u64::MAX);
+		self.state.last_activity.store(elapsed, Ordering::Relaxed);
+	}
+
+	/// Return an error if the socket was shut down because it was idle for too long.
+	pub(crate) fn check(&self) -> std::io::Result<()> {
+		if self.state.expired.load(Ordering::Acquire) {
+			Err(std::io::Error::new(
+				std::io::ErrorKind::TimedOut,
+				format!("connection was closed after being idle for {:?}", self.state.timeout),
+			))
+		} else {
+			Ok(())
+		}
+	}
+}
+
+impl State {
+	/// Get the time at which the socket expires if nothing else is received.
+	fn deadline(&self) -> Instant {
+		self.started + Duration::from_nanos(self.last_activity.load(Ordering::Relaxed)) + self.timeout
+	}
+}
+
+/// Shut down `socket` when nothing was received for the idle timeout, or stop when `stopped` resolves.
+async fn watch(socket: FileDesc, state: Arc<State>, mut stopped: oneshot::Receiver<()>) {
+	loop {
+		let deadline = state.deadline();
+		if tokio::time::timeout_at(deadline, &mut stopped).await.is_ok() {
+			return;
+		}
+		if state.deadline() <= Instant::now() {
+			state.expired.store(true, Ordering::Release);
+			sys::shutdown(&socket, std::net::Shutdown::Both).ok();
+			return;
+		}
+	}
+}
@@ -25,6 +25,15 @@
 //! As such, all I/O functions now take `&self` instead of `&mut self`,
 //! and the `split()` API has been deprecated.
 //!
+//! # Features
+//!
+//! The following optional features are available:
+//! * `bytemuck`: add [`UnixSeqpacket::send_pod()`] and [`UnixSeqpacket::recv_pod()`] to send and receive plain-old-data types.
+//! * `serde`: implement [`serde::Serialize`] and [`serde::Deserialize`] for [`SocketConfig`].
+//! * `test-util`: add `SocketAncillary::from_raw_received()` to test the parsing of hand-crafted control messages.
+//! * `tokio-util`: add [`UnixSeqpacket::recv_or_cancel()`] to receive with a [`CancellationToken`](https://docs.rs/tokio-util/latest/tokio_util/sync/struct.CancellationToken.html).
+//! * `tracing`: emit a warning with [`tracing`](https://docs.rs/tracing) when a received message is truncated.
+//!
 //! # Example
 //! ```no_run
 //! # async fn foo() -> Result<(), Box<dyn std::error::Error>> {
@@ -51,16 +60,54 @@ macro_rules! ready {
 	};
 }
 
+mod address;
 pub mod ancillary;
+pub mod blocking;
+#[cfg(feature = "tokio-util")]
+mod cancel;
+mod coalesce;
+mod config;
+mod error;
+mod failover;
+mod fd_limit;
+#[cfg(any(target_os = "android", target_os = "linux"))]
+mod handshake;
+mod idle;
 mod listener;
+#[cfg(feature = "bytemuck")]
+mod pod;
+mod pool;
 mod socket;
+mod split;
+mod suspend;
 mod sys;
 mod ucred;
 
+pub use address::SocketAddr;
+pub use coalesce::CoalescingSocket;
+pub use config::{ListenerConfig, SocketConfig};
+pub use error::{ControlTruncated, FdNotSent, LabeledError, PayloadTooLarge, ReuniteError, SocketTypeMismatch};
+pub use failover::FailoverSocket;
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub use handshake::HandshakeResult;
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub use listener::IncomingWithCred;
 pub use listener::UnixSeqpacketListener;
-pub use socket::UnixSeqpacket;
+pub use pool::{BufferPool, PooledMessage};
+pub use socket::{RecvSized, RecvState, UnixSeqpacket};
+pub use split::{Receiver, Sender};
+pub use suspend::SuspendedSocket;
 pub use ucred::UCred;
 
+/// Get the maximum number of I/O slices that can be used in a single vectored send or receive.
+///
+/// The vectored functions of [`UnixSeqpacket`] return an error of kind [`std::io::ErrorKind::InvalidInput`]
+/// if they are given more slices than this.
+/// This is queried with `sysconf(_SC_IOV_MAX)`.
+pub fn iov_max() -> usize {
+	sys::iov_max()
+}
+
 #[doc(hidden)]
 #[deprecated(
 	since = "0.4.0",
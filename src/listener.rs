@@ -1,15 +1,42 @@
 use filedesc::FileDesc;
 use std::os::raw::c_int;
-use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, IntoRawFd, OwnedFd};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd};
 use std::path::{Path, PathBuf};
 use std::task::{Context, Poll};
+use std::time::Duration;
 use tokio::io::unix::AsyncFd;
 
-use crate::{sys, UnixSeqpacket};
+#[cfg(any(target_os = "android", target_os = "linux"))]
+use crate::ancillary::SocketCred;
+use crate::{sys, ListenerConfig, SocketAddr, UnixSeqpacket};
+
+/// The size of the connection queue that is used if no backlog is given, or if the given backlog is negative.
+pub(crate) const DEFAULT_BACKLOG: c_int = 128;
 
 /// Listener for Unix seqpacket sockets.
 pub struct UnixSeqpacketListener {
 	io: AsyncFd<FileDesc>,
+	unlink_guard: Option<UnlinkGuard>,
+	idle_timeout: Option<Duration>,
+}
+
+/// Removes a socket file when dropped.
+struct UnlinkGuard {
+	path: PathBuf,
+}
+
+impl UnlinkGuard {
+	/// Don't remove the file after all.
+	fn disarm(self) {
+		std::mem::forget(self)
+	}
+}
+
+impl Drop for UnlinkGuard {
+	fn drop(&mut self) {
+		let _ = std::fs::remove_file(&self.path);
+	}
 }
 
 impl std::fmt::Debug for UnixSeqpacketListener {
@@ -37,14 +64,18 @@ impl TryFrom<OwnedFd> for UnixSeqpacketListener {
 impl UnixSeqpacketListener {
 	fn new(socket: FileDesc) -> std::io::Result<Self> {
 		let io = AsyncFd::new(socket)?;
-		Ok(Self { io })
+		Ok(Self {
+			io,
+			unlink_guard: None,
+			idle_timeout: None,
+		})
 	}
 
 	/// Bind a new seqpacket listener to the given address.
 	///
 	/// The create listener will be ready to accept new connections.
 	pub fn bind<P: AsRef<Path>>(address: P) -> std::io::Result<Self> {
-		Self::bind_with_backlog(address, 128)
+		Self::bind_with_backlog(address, DEFAULT_BACKLOG)
 	}
 
 	/// Bind a new seqpacket listener to the given address.
@@ -52,25 +83,87 @@ impl UnixSeqpacketListener {
 	/// The create listener will be ready to accept new connections.
 	///
 	/// The `backlog` parameter is used to determine the size of connection queue.
+	/// The kernel silently caps the backlog to a system-wide maximum (`SOMAXCONN`),
+	/// which is `/proc/sys/net/core/somaxconn` on Linux.
+	/// A negative backlog is replaced by the default of 128.
 	/// See `man 3 listen` for more information.
 	pub fn bind_with_backlog<P: AsRef<Path>>(address: P, backlog: c_int) -> std::io::Result<Self> {
 		let socket = sys::local_seqpacket_socket()?;
 		sys::bind(&socket, address)?;
-		sys::listen(&socket, backlog)?;
+		listen(&socket, backlog)?;
+		Self::new(socket)
+	}
+
+	/// Bind a new seqpacket listener to an abstract address.
+	///
+	/// The name should not include the leading null byte.
+	/// See [`SocketAddr::from_abstract_name()`].
+	/// Abstract addresses do not exist in the file system, so there is no socket file to clean up.
+	///
+	/// If `name` is empty, the kernel picks a unique abstract address.
+	/// Use [`Self::local_socket_addr()`] to find out which one.
+	///
+	/// This is only available on Linux and Android.
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	pub fn bind_abstract(name: &[u8]) -> std::io::Result<Self> {
+		let address = if name.is_empty() {
+			SocketAddr::autobind()
+		} else {
+			SocketAddr::from_abstract_name(name)?
+		};
+		let socket = sys::local_seqpacket_socket()?;
+		sys::bind_addr(&socket, &address)?;
+		listen(&socket, DEFAULT_BACKLOG)?;
 		Self::new(socket)
 	}
 
+	/// Bind a new seqpacket listener to the given address with the given configuration.
+	///
+	/// This creates the socket, applies all options, binds it and starts listening.
+	/// If any step fails, the socket is closed and the socket file is removed if it was created.
+	///
+	/// If [`ListenerConfig::unlink_on_drop()`] is enabled, the socket file is removed when the listener is dropped.
+	pub fn bind_configured<P: AsRef<Path>>(address: P, config: &ListenerConfig) -> std::io::Result<Self> {
+		let address = address.as_ref();
+		let socket = sys::local_seqpacket_socket()?;
+		#[cfg(any(target_os = "android", target_os = "linux"))]
+		sys::set_passcred(&socket, config.pass_credentials)?;
+		sys::bind(&socket, address)?;
+
+		// From here on, remove the socket file if anything fails.
+		let unlink_guard = UnlinkGuard {
+			path: address.to_owned(),
+		};
+		if let Some(mode) = config.mode {
+			std::fs::set_permissions(address, std::fs::Permissions::from_mode(mode))?;
+		}
+		listen(&socket, config.backlog)?;
+		let mut listener = Self::new(socket)?;
+
+		if config.unlink_on_drop {
+			listener.unlink_guard = Some(unlink_guard);
+		} else {
+			unlink_guard.disarm();
+		}
+		Ok(listener)
+	}
+
 	/// Wrap a raw file descriptor as [`UnixSeqpacket`].
 	///
-	/// Registration of the file descriptor with the tokio runtime may fail.
-	/// For that reason, this function returns a [`std::io::Result`].
+	/// This checks that the file descriptor is a `SOCK_SEQPACKET` socket,
+	/// puts it in non-blocking mode and registers it with the tokio runtime.
+	/// Any of these steps may fail, so this function returns a [`std::io::Result`].
+	/// If this function fails, the file descriptor is closed.
 	///
 	/// # Safety
 	/// This function is unsafe because the socket assumes it is the sole owner of the file descriptor.
 	/// Usage of this function could accidentally allow violating this contract
 	/// which can cause memory unsafety in code that relies on it being true.
 	pub unsafe fn from_raw_fd(fd: std::os::unix::io::RawFd) -> std::io::Result<Self> {
-		Self::new(FileDesc::from_raw_fd(fd))
+		let socket = FileDesc::from_raw_fd(fd);
+		sys::ensure_seqpacket(&socket)?;
+		sys::set_nonblocking(&socket, true)?;
+		Self::new(socket)
 	}
 
 	/// Get the raw file descriptor of the socket.
@@ -79,20 +172,157 @@ impl UnixSeqpacketListener {
 	}
 
 	/// Deregister the socket from the tokio runtime and return the inner file descriptor.
+	///
+	/// If the listener was configured to remove the socket file when dropped, the file is not removed.
 	pub fn into_raw_fd(self) -> std::os::unix::io::RawFd {
+		if let Some(unlink_guard) = self.unlink_guard {
+			unlink_guard.disarm();
+		}
 		self.io.into_inner().into_raw_fd()
 	}
 
+	/// Prepare the listener to be inherited by a new process, and return the raw file descriptor.
+	///
+	/// This deregisters the listener from the tokio runtime and clears the `close-on-exec` flag,
+	/// so the file descriptor stays open when the process calls `exec`.
+	/// The new process can adopt the listener with [`Self::from_inherited()`].
+	/// This can be used for zero-downtime restarts.
+	///
+	/// If the listener was configured to remove the socket file when dropped, the file is not removed.
+	///
+	/// If clearing the `close-on-exec` flag fails, the listener is closed and an error is returned.
+	pub fn into_inheritable(self) -> std::io::Result<std::os::unix::io::RawFd> {
+		if let Some(unlink_guard) = self.unlink_guard {
+			unlink_guard.disarm();
+		}
+		let socket = self.io.into_inner();
+		socket.set_close_on_exec(false)?;
+		Ok(socket.into_raw_fd())
+	}
+
+	/// Adopt a listener that was inherited from another process.
+	///
+	/// This sets the `close-on-exec` flag and non-blocking mode on the file descriptor,
+	/// and registers it with the tokio runtime.
+	/// See [`Self::into_inheritable()`].
+	///
+	/// If this function fails, the file descriptor is closed.
+	///
+	/// # Safety
+	/// The file descriptor must be a valid listening seqpacket socket,
+	/// and it must not be owned by anything else.
+	pub unsafe fn from_inherited(fd: std::os::unix::io::RawFd) -> std::io::Result<Self> {
+		let socket = FileDesc::from_raw_fd(fd);
+		socket.set_close_on_exec(true)?;
+		sys::set_nonblocking(&socket, true)?;
+		Self::new(socket)
+	}
+
 	/// Get the socket address of the local half of this connection.
 	pub fn local_addr(&self) -> std::io::Result<PathBuf> {
 		sys::get_local_address(self.io.get_ref())
 	}
 
+	/// Get the socket address of the listener.
+	///
+	/// Unlike [`Self::local_addr()`], this also works for abstract addresses.
+	/// The address can be passed to [`UnixSeqpacket::connect_addr()`].
+	pub fn local_socket_addr(&self) -> std::io::Result<SocketAddr> {
+		sys::get_local_socket_addr(self.io.get_ref())
+	}
+
+	/// Change the size of the connection queue of the listener.
+	///
+	/// This calls `listen()` again with the new backlog.
+	/// Connections that are already queued are kept.
+	///
+	/// Like with [`Self::bind_with_backlog()`], the kernel silently caps the backlog to `SOMAXCONN`,
+	/// and a negative backlog is replaced by the default of 128.
+	pub fn set_backlog(&self, backlog: c_int) -> std::io::Result<()> {
+		listen(self.io.get_ref(), backlog)
+	}
+
 	/// Get and clear the value of the `SO_ERROR` option.
 	pub fn take_error(&self) -> std::io::Result<Option<std::io::Error>> {
 		sys::take_socket_error(self.io.get_ref())
 	}
 
+	/// Close accepted connections that do not receive anything for `timeout`.
+	///
+	/// This applies to all connections accepted by this listener after the call.
+	/// Each connection gets a background task that shuts down the socket
+	/// if no receive function of the socket succeeded within the timeout.
+	/// Every successful receive restarts the timeout.
+	/// After the socket was closed, the receive functions return an error of kind [`std::io::ErrorKind::TimedOut`],
+	/// and the peer sees the end of the connection.
+	///
+	/// This can be used to protect a server against clients that connect but never send anything.
+	/// Note that only received datagrams count as activity: sending does not restart the timeout.
+	/// Set the timeout to `None` to disable it, which is the default.
+	pub fn set_idle_timeout(&mut self, timeout: Option<Duration>) {
+		self.idle_timeout = timeout;
+	}
+
+	/// Get the idle timeout for accepted connections.
+	///
+	/// See [`Self::set_idle_timeout()`].
+	pub fn idle_timeout(&self) -> Option<Duration> {
+		self.idle_timeout
+	}
+
+	/// Register an accepted connection with the tokio runtime and start the idle timeout, if any.
+	fn wrap_accepted(&self, socket: FileDesc) -> std::io::Result<UnixSeqpacket> {
+		let mut socket = UnixSeqpacket::new(socket)?;
+		if let Some(timeout) = self.idle_timeout {
+			socket.start_idle_timeout(timeout)?;
+		}
+		Ok(socket)
+	}
+
+	/// Register accepted connections with the tokio runtime.
+	fn wrap_accepted_many(
+		&self,
+		accepted: Vec<(FileDesc, SocketAddr)>,
+	) -> std::io::Result<Vec<(UnixSeqpacket, SocketAddr)>> {
+		accepted
+			.into_iter()
+			.map(|(socket, address)| Ok((self.wrap_accepted(socket)?, address)))
+			.collect()
+	}
+
+	/// Check if there is a connection ready to accept, without accepting it.
+	///
+	/// Note that unlike [`Self::readable`], only the last task calling this function will be woken up.
+	/// For that reason, it is preferable to use the async functions rather than polling functions when possible.
+	pub fn poll_readable(&self, cx: &mut Context) -> Poll<std::io::Result<()>> {
+		loop {
+			let mut ready_guard = ready!(self.io.poll_read_ready(cx)?);
+			if sys::is_readable(self.io.get_ref())? {
+				ready_guard.retain_ready();
+				return Poll::Ready(Ok(()));
+			}
+			ready_guard.clear_ready();
+		}
+	}
+
+	/// Wait until there is a connection ready to accept, without accepting it.
+	///
+	/// The readiness is verified with the kernel before this function returns,
+	/// so a following call to [`Self::accept`] will not have to wait,
+	/// unless another task accepted the connection first.
+	///
+	/// This can be used to coordinate startup or to accept connections in batches.
+	pub async fn readable(&self) -> std::io::Result<()> {
+		loop {
+			let mut ready_guard = self.io.readable().await?;
+			if sys::is_readable(self.io.get_ref())? {
+				ready_guard.retain_ready();
+				return Ok(());
+			}
+			ready_guard.clear_ready();
+		}
+	}
+
 	/// Check if there is a connection ready to accept.
 	///
 	/// Note that unlike [`Self::accept`], only the last task calling this function will be woken up.
@@ -110,7 +340,7 @@ impl UnixSeqpacketListener {
 			}
 		};
 
-		Poll::Ready(Ok(UnixSeqpacket::new(socket)?))
+		Poll::Ready(self.wrap_accepted(socket))
 	}
 
 	/// Accept a new incoming connection on the listener.
@@ -130,10 +360,132 @@ impl UnixSeqpacketListener {
 			}
 		};
 
-		UnixSeqpacket::new(socket)
+		self.wrap_accepted(socket)
+	}
+
+	/// Check if there are connections ready to accept, and accept up to `max` of them at once.
+	///
+	/// Note that unlike [`Self::accept_many`], only the last task calling this function will be woken up.
+	/// For that reason, it is preferable to use the async functions rather than polling functions when possible.
+	pub fn poll_accept_many(
+		&mut self,
+		cx: &mut Context,
+		max: usize,
+	) -> Poll<std::io::Result<Vec<(UnixSeqpacket, SocketAddr)>>> {
+		if max == 0 {
+			return Poll::Ready(Ok(Vec::new()));
+		}
+		let accepted = loop {
+			let mut ready_guard = ready!(self.io.poll_read_ready(cx)?);
+
+			match ready_guard.try_io(|inner| sys::accept_many(inner.get_ref(), max)) {
+				Ok(x) => break x?,
+				Err(_would_block) => continue,
+			}
+		};
+
+		Poll::Ready(self.wrap_accepted_many(accepted))
+	}
+
+	/// Accept a burst of incoming connections together with the address of each peer.
+	///
+	/// This waits until at least one connection is ready,
+	/// and then accepts up to `max` connections that are already pending without waiting for more.
+	/// If `max` is zero, this returns an empty list right away.
+	///
+	/// The address of a peer is unnamed unless the peer bound its socket to an address before connecting.
+	///
+	/// An error is only returned if no connection could be accepted at all.
+	/// If accepting fails after some connections were accepted, those connections are returned,
+	/// and the error is reported by the next call.
+	pub async fn accept_many(&mut self, max: usize) -> std::io::Result<Vec<(UnixSeqpacket, SocketAddr)>> {
+		if max == 0 {
+			return Ok(Vec::new());
+		}
+		let accepted = loop {
+			let mut ready_guard = self.io.readable().await?;
+
+			match ready_guard.try_io(|inner| sys::accept_many(inner.get_ref(), max)) {
+				Ok(x) => break x?,
+				Err(_would_block) => continue,
+			}
+		};
+
+		self.wrap_accepted_many(accepted)
+	}
+
+	/// Accept the next connection for which `predicate` approves the credentials of the peer.
+	///
+	/// The credentials of every accepted connection are fetched with `SO_PEERCRED` and passed to `predicate`.
+	/// Connections that are rejected by the predicate, or for which the credentials can not be fetched,
+	/// are closed right away, and the function continues with the next connection.
+	///
+	/// This is only available on Linux and Android.
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	pub async fn accept_filtered(
+		&mut self,
+		mut predicate: impl FnMut(&SocketCred) -> bool,
+	) -> std::io::Result<(UnixSeqpacket, SocketCred)> {
+		loop {
+			let socket = self.accept().await?;
+			match with_cred(socket) {
+				Ok((socket, cred)) if predicate(&cred) => return Ok((socket, cred)),
+				_ => continue,
+			}
+		}
+	}
+
+	/// Get a stream of accepted connections together with the credentials of the peer.
+	///
+	/// The credentials are fetched with `SO_PEERCRED` for every accepted connection.
+	/// If that fails, the connection is closed and the error is yielded instead.
+	///
+	/// This is only available on Linux and Android.
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	pub fn incoming_with_cred(&mut self) -> IncomingWithCred<'_> {
+		IncomingWithCred { listener: self }
+	}
+}
+
+/// A stream of accepted connections with the credentials of the peer.
+///
+/// Created with [`UnixSeqpacketListener::incoming_with_cred()`].
+/// The methods mirror the `Stream` trait from the `futures` crate, but the stream never ends:
+/// [`Self::next()`] and [`Self::poll_next()`] always yield [`Some`].
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub struct IncomingWithCred<'a> {
+	listener: &'a mut UnixSeqpacketListener,
+}
+
+#[cfg(any(target_os = "android", target_os = "linux"))]
+impl IncomingWithCred<'_> {
+	/// Try to accept the next connection without blocking.
+	///
+	/// See [`UnixSeqpacketListener::poll_accept()`].
+	pub fn poll_next(&mut self, cx: &mut Context) -> Poll<Option<std::io::Result<(UnixSeqpacket, SocketCred)>>> {
+		let result = ready!(self.listener.poll_accept(cx)).and_then(with_cred);
+		Poll::Ready(Some(result))
+	}
+
+	/// Accept the next connection and get the credentials of the peer.
+	pub async fn next(&mut self) -> Option<std::io::Result<(UnixSeqpacket, SocketCred)>> {
+		Some(self.listener.accept().await.and_then(with_cred))
 	}
 }
 
+/// Start listening on a socket, replacing a negative backlog with the default.
+fn listen(socket: &FileDesc, backlog: c_int) -> std::io::Result<()> {
+	let backlog = if backlog < 0 { DEFAULT_BACKLOG } else { backlog };
+	sys::listen(socket, backlog)
+}
+
+/// Get the credentials of the peer of an accepted connection.
+#[cfg(any(target_os = "android", target_os = "linux"))]
+fn with_cred(socket: UnixSeqpacket) -> std::io::Result<(UnixSeqpacket, SocketCred)> {
+	let cred = SocketCred::from(socket.peer_cred()?);
+	Ok((socket, cred))
+}
+
 impl AsRawFd for UnixSeqpacketListener {
 	fn as_raw_fd(&self) -> std::os::unix::io::RawFd {
 		self.as_raw_fd()
@@ -145,3 +497,21 @@ impl IntoRawFd for UnixSeqpacketListener {
 		self.into_raw_fd()
 	}
 }
+
+impl FromRawFd for UnixSeqpacketListener {
+	/// Wrap a raw file descriptor as [`UnixSeqpacketListener`].
+	///
+	/// # Panics
+	/// This function panics if the file descriptor is not a `SOCK_SEQPACKET` socket
+	/// or if it can not be registered with the tokio runtime.
+	/// Use the inherent [`UnixSeqpacketListener::from_raw_fd()`] to handle these errors.
+	///
+	/// # Safety
+	/// See [`UnixSeqpacketListener::from_raw_fd()`].
+	unsafe fn from_raw_fd(fd: std::os::unix::io::RawFd) -> Self {
+		match UnixSeqpacketListener::from_raw_fd(fd) {
+			Ok(listener) => listener,
+			Err(e) => panic!("failed to adopt file descriptor {fd} as UnixSeqpacketListener: {e}"),
+		}
+	}
+}
@@ -0,0 +1,58 @@
+use std::io::IoSliceMut;
+
+use crate::UnixSeqpacket;
+
+impl UnixSeqpacket {
+	/// Send a plain-old-data value as a single datagram.
+	///
+	/// The datagram holds the bytes of the value in memory, so both sides must agree on the layout of `T`.
+	/// Use `#[repr(C)]` types to get a well-defined layout.
+	///
+	/// This function is only available with the `bytemuck` feature.
+	pub async fn send_pod<T: bytemuck::Pod>(&self, value: &T) -> std::io::Result<usize> {
+		self.send(bytemuck::bytes_of(value)).await
+	}
+
+	/// Receive a plain-old-data value from a single datagram.
+	///
+	/// If the size of the datagram is not exactly `size_of::<T>()`,
+	/// the datagram is discarded and an error of kind [`std::io::ErrorKind::InvalidData`] is returned.
+	///
+	/// This function is only available with the `bytemuck` feature.
+	pub async fn recv_pod<T: bytemuck::Pod>(&self) -> std::io::Result<T> {
+		let mut value = T::zeroed();
+		// One extra byte to detect datagrams that are too long.
+		let mut extra = [0u8; 1];
+		let size = std::mem::size_of::<T>();
+		let len = self
+			.recv_vectored(&mut [
+				IoSliceMut::new(bytemuck::bytes_of_mut(&mut value)),
+				IoSliceMut::new(&mut extra),
+			])
+			.await?;
+
+		if len < size {
+			Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				format!(
+					"received datagram of {} bytes, but {} needs {} bytes",
+					len,
+					std::any::type_name::<T>(),
+					size
+				),
+			))
+		} else if len > size {
+			Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				format!(
+					"received datagram of more than {} bytes, but {} needs {} bytes",
+					size,
+					std::any::type_name::<T>(),
+					size
+				),
+			))
+		} else {
+			Ok(value)
+		}
+	}
+}
@@ -0,0 +1,128 @@
+use std::sync::{Arc, Mutex};
+
+use crate::UnixSeqpacket;
+
+/// A pool of receive buffers that are reused for multiple messages.
+///
+/// Use [`Self::recv_from()`] to receive a message into a buffer from the pool.
+/// The returned [`PooledMessage`] gives the buffer back to the pool when it is dropped.
+/// If the pool is empty, a new buffer is allocated.
+///
+/// Cloning the pool is cheap: all clones share the same buffers.
+#[derive(Debug, Clone)]
+pub struct BufferPool {
+	inner: Arc<PoolInner>,
+}
+
+#[derive(Debug)]
+struct PoolInner {
+	buffer_size: usize,
+	max_buffers: usize,
+	buffers: Mutex<PoolBuffers>,
+}
+
+#[derive(Debug)]
+struct PoolBuffers {
+	free: Vec<Vec<u8>>,
+	allocated: usize,
+}
+
+impl BufferPool {
+	/// Create a new pool that keeps up to `max_buffers` buffers of `buffer_size` bytes each.
+	///
+	/// Buffers are allocated on demand.
+	/// If more than `max_buffers` buffers are in use at the same time,
+	/// the extra buffers are freed instead of returned to the pool.
+	pub fn new(max_buffers: usize, buffer_size: usize) -> Self {
+		Self {
+			inner: Arc::new(PoolInner {
+				buffer_size,
+				max_buffers,
+				buffers: Mutex::new(PoolBuffers {
+					free: Vec::with_capacity(max_buffers),
+					allocated: 0,
+				}),
+			}),
+		}
+	}
+
+	/// Get the size of the buffers in the pool.
+	///
+	/// Messages larger than this size are truncated when received.
+	pub fn buffer_size(&self) -> usize {
+		self.inner.buffer_size
+	}
+
+	/// Get the total number of buffers that have been allocated by this pool.
+	///
+	/// This can be used to verify that buffers are reused.
+	pub fn allocated(&self) -> usize {
+		self.inner.buffers.lock().unwrap().allocated
+	}
+
+	/// Get the number of buffers that are currently available in the pool.
+	pub fn available(&self) -> usize {
+		self.inner.buffers.lock().unwrap().free.len()
+	}
+
+	/// Receive a message from a socket into a buffer from the pool.
+	///
+	/// If the message is larger than [`Self::buffer_size()`], the remainder of the message is discarded.
+	///
+	/// This function is cancellation safe: if the future is dropped, the buffer is returned to the pool.
+	pub async fn recv_from(&self, socket: &UnixSeqpacket) -> std::io::Result<PooledMessage> {
+		let mut message = PooledMessage {
+			buffer: self.take(),
+			len: 0,
+			pool: self.inner.clone(),
+		};
+		message.len = socket.recv(&mut message.buffer).await?;
+		Ok(message)
+	}
+
+	/// Take a buffer from the pool, or allocate a new one if the pool is empty.
+	fn take(&self) -> Vec<u8> {
+		let mut buffers = self.inner.buffers.lock().unwrap();
+		match buffers.free.pop() {
+			Some(buffer) => buffer,
+			None => {
+				buffers.allocated += 1;
+				vec![0; self.inner.buffer_size]
+			},
+		}
+	}
+}
+
+/// A message received into a buffer from a [`BufferPool`].
+///
+/// This dereferences to the received bytes.
+/// When dropped, the buffer is returned to the pool.
+#[derive(Debug)]
+pub struct PooledMessage {
+	buffer: Vec<u8>,
+	len: usize,
+	pool: Arc<PoolInner>,
+}
+
+impl std::ops::Deref for PooledMessage {
+	type Target = [u8];
+
+	fn deref(&self) -> &[u8] {
+		&self.buffer[..self.len]
+	}
+}
+
+impl AsRef<[u8]> for PooledMessage {
+	fn as_ref(&self) -> &[u8] {
+		self
+	}
+}
+
+impl Drop for PooledMessage {
+	fn drop(&mut self) {
+		let mut buffers = self.pool.buffers.lock().unwrap();
+		if buffers.free.len() < self.pool.max_buffers {
+			buffers.free.push(std::mem::take(&mut self.buffer));
+		}
+	}
+}
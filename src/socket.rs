@@ -1,20 +1,115 @@
 use filedesc::FileDesc;
+use std::any::Any;
 use std::io::{IoSlice, IoSliceMut};
-use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, IntoRawFd, OwnedFd};
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd, RawFd};
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use std::task::{Context, Poll};
+use std::time::Duration;
 use tokio::io::unix::AsyncFd;
+use tokio::io::{Interest, Ready};
 
-use crate::ancillary::SocketAncillary;
-use crate::{sys, UCred};
+#[cfg(any(target_os = "android", target_os = "linux"))]
+use crate::ancillary::SocketCred;
+use crate::ancillary::{self, SocketAncillary, SocketAncillaryBuf};
+use crate::fd_limit::FdLimit;
+use crate::idle::IdleTimeout;
+use crate::{
+	sys, ControlTruncated, FdNotSent, LabeledError, PayloadTooLarge, SocketAddr, SocketTypeMismatch, SuspendedSocket,
+	UCred,
+};
 
 /// Unix seqpacket socket.
-///
-/// Note that there are no functions to get the local or remote address of the connection.
-/// That is because connected Unix sockets are always anonymous,
-/// which means that the address contains no useful information.
 pub struct UnixSeqpacket {
 	io: AsyncFd<FileDesc>,
+	/// Set when a receive watermark is installed, so receives without a watermark don't need to take the lock.
+	has_recv_watermark: AtomicBool,
+	recv_watermark: Mutex<Option<RecvWatermark>>,
+	max_control_bytes: AtomicUsize,
+	max_payload: AtomicUsize,
+	track_peak_recv_queue: AtomicBool,
+	peak_recv_queue: AtomicUsize,
+	user_data: Mutex<Option<UserData>>,
+	label: Mutex<Option<Arc<str>>>,
+	pub(crate) fd_limit: FdLimit,
+	idle_timeout: Option<IdleTimeout>,
+}
+
+/// Settings of a socket that are not stored by the kernel.
+///
+/// These are preserved when the socket is suspended and resumed.
+pub(crate) struct Settings {
+	recv_watermark: Option<RecvWatermark>,
+	max_control_bytes: usize,
+	max_payload: usize,
+	track_peak_recv_queue: bool,
+	user_data: Option<UserData>,
+	label: Option<Arc<str>>,
+	fd_limit: FdLimit,
+	idle_timeout: Option<Duration>,
+}
+
+impl Default for Settings {
+	fn default() -> Self {
+		Self {
+			recv_watermark: None,
+			max_control_bytes: usize::MAX,
+			max_payload: usize::MAX,
+			track_peak_recv_queue: false,
+			user_data: None,
+			label: None,
+			fd_limit: Default::default(),
+			idle_timeout: None,
+		}
+	}
+}
+
+/// The size in words of the control buffer used by [`UnixSeqpacket::recv_into()`] and [`UnixSeqpacket::recv_fds_into()`].
+///
+/// This is enough for `fds_space(MAX_FDS_PER_MESSAGE)` on all supported platforms.
+/// Using `u64` words makes sure the buffer is properly aligned for control message headers.
+const RECV_INTO_CONTROL_WORDS: usize = 136;
+
+/// A type-erased value attached to a socket with [`UnixSeqpacket::set_user_data()`].
+type UserData = Arc<dyn Any + Send + Sync>;
+
+/// The result of [`UnixSeqpacket::recv_sized()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecvSized {
+	/// The number of bytes that were copied into the buffer.
+	pub copied: usize,
+
+	/// The full size of the datagram.
+	///
+	/// This is never less than [`Self::copied`].
+	pub full_len: usize,
+}
+
+impl RecvSized {
+	/// Check if the datagram did not fit in the buffer.
+	pub fn is_truncated(&self) -> bool {
+		self.full_len > self.copied
+	}
+}
+
+/// The result of [`UnixSeqpacket::recv_or_eof()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecvState {
+	/// A datagram with the given number of bytes was received.
+	Data(usize),
+
+	/// An empty datagram was received.
+	EmptyDatagram,
+
+	/// The peer has shut down the connection, and there are no more datagrams to receive.
+	Eof,
+}
+
+/// A callback to run when the receive queue grows beyond a threshold.
+struct RecvWatermark {
+	bytes: usize,
+	callback: Arc<dyn Fn(usize) + Send + Sync>,
 }
 
 impl std::fmt::Debug for UnixSeqpacket {
@@ -41,15 +136,93 @@ impl TryFrom<OwnedFd> for UnixSeqpacket {
 
 impl UnixSeqpacket {
 	pub(crate) fn new(socket: FileDesc) -> std::io::Result<Self> {
+		Self::with_settings(socket, Settings::default())
+	}
+
+	pub(crate) fn with_settings(socket: FileDesc, settings: Settings) -> std::io::Result<Self> {
 		let io = AsyncFd::new(socket)?;
-		Ok(Self { io })
+		let mut socket = Self {
+			io,
+			has_recv_watermark: AtomicBool::new(settings.recv_watermark.is_some()),
+			recv_watermark: Mutex::new(settings.recv_watermark),
+			max_control_bytes: AtomicUsize::new(settings.max_control_bytes),
+			max_payload: AtomicUsize::new(settings.max_payload),
+			track_peak_recv_queue: AtomicBool::new(settings.track_peak_recv_queue),
+			peak_recv_queue: AtomicUsize::new(0),
+			user_data: Mutex::new(settings.user_data),
+			label: Mutex::new(settings.label),
+			fd_limit: settings.fd_limit,
+			idle_timeout: None,
+		};
+		if let Some(timeout) = settings.idle_timeout {
+			socket.start_idle_timeout(timeout)?;
+		}
+		Ok(socket)
+	}
+
+	/// Close the socket if nothing is received for `timeout`.
+	///
+	/// This must be called from within a tokio runtime.
+	pub(crate) fn start_idle_timeout(&mut self, timeout: Duration) -> std::io::Result<()> {
+		self.idle_timeout = Some(IdleTimeout::start(self.io.get_ref(), timeout)?);
+		Ok(())
+	}
+
+	/// Return an error if the socket was closed by the idle timeout.
+	fn check_idle(&self) -> std::io::Result<()> {
+		match &self.idle_timeout {
+			Some(idle_timeout) => idle_timeout.check(),
+			None => Ok(()),
+		}
+	}
+
+	/// Called after every successful receive operation, passing through the received length.
+	fn after_recv(&self, len: usize) -> usize {
+		if let Some(idle_timeout) = &self.idle_timeout {
+			idle_timeout.touch();
+		}
+
+		let has_watermark = self.has_recv_watermark.load(Ordering::Relaxed);
+		let track_peak = self.track_peak_recv_queue.load(Ordering::Relaxed);
+		if !has_watermark && !track_peak {
+			return len;
+		}
+
+		let queued = sys::recv_queue_len(self.io.get_ref()).ok();
+		if let (true, Some(queued)) = (track_peak, queued) {
+			// The received datagram was still in the queue just before this receive.
+			self.peak_recv_queue
+				.fetch_max(queued.saturating_add(len), Ordering::Relaxed);
+		}
+
+		if !has_watermark {
+			return len;
+		}
+
+		let callback = match (&*self.recv_watermark.lock().unwrap(), queued) {
+			(Some(watermark), Some(queued)) if queued > watermark.bytes => Some((watermark.callback.clone(), queued)),
+			_ => None,
+		};
+
+		// Run the callback without holding the lock, so it can change the watermark if it wants.
+		if let Some((callback, queued)) = callback {
+			callback(queued);
+		}
+
+		len
 	}
 
 	/// Connect a new seqpacket socket to the given address.
+	///
+	/// If the socket at the address is not a seqpacket socket,
+	/// this returns an error of kind [`std::io::ErrorKind::InvalidInput`] that wraps a [`SocketTypeMismatch`] error.
 	pub async fn connect<P: AsRef<Path>>(address: P) -> std::io::Result<Self> {
 		let socket = sys::local_seqpacket_socket()?;
-		if let Err(e) = sys::connect(&socket, address) {
-			if e.kind() != std::io::ErrorKind::WouldBlock {
+		if let Err(e) = sys::connect(&socket, &address) {
+			if e.raw_os_error() == Some(libc::EPROTOTYPE) {
+				let path = address.as_ref().to_owned();
+				return Err(SocketTypeMismatch { path }.into());
+			} else if e.kind() != std::io::ErrorKind::WouldBlock {
 				return Err(e);
 			}
 		}
@@ -59,7 +232,49 @@ impl UnixSeqpacket {
 		Ok(socket)
 	}
 
+	/// Connect a new seqpacket socket to the given socket address.
+	///
+	/// This accepts both pathname and abstract addresses,
+	/// so an address obtained earlier from the crate can be used to reconnect.
+	/// If the address is unnamed, an error of kind [`std::io::ErrorKind::InvalidInput`] is returned.
+	///
+	/// Like [`Self::connect()`], this returns a [`SocketTypeMismatch`] error
+	/// if the socket at a pathname address is not a seqpacket socket.
+	pub async fn connect_addr(address: &SocketAddr) -> std::io::Result<Self> {
+		let socket = sys::local_seqpacket_socket()?;
+		if let Err(e) = sys::connect_addr(&socket, address) {
+			if let (Some(libc::EPROTOTYPE), Some(path)) = (e.raw_os_error(), address.as_pathname()) {
+				let path = path.to_owned();
+				return Err(SocketTypeMismatch { path }.into());
+			} else if e.kind() != std::io::ErrorKind::WouldBlock {
+				return Err(e);
+			}
+		}
+
+		let socket = Self::new(socket)?;
+		socket.io.writable().await?.retain_ready();
+		Ok(socket)
+	}
+
+	/// Connect a new seqpacket socket to an abstract address.
+	///
+	/// The name should not include the leading null byte.
+	/// See [`SocketAddr::from_abstract_name()`].
+	///
+	/// This is only available on Linux and Android.
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	pub async fn connect_abstract(name: &[u8]) -> std::io::Result<Self> {
+		Self::connect_addr(&SocketAddr::from_abstract_name(name)?).await
+	}
+
 	/// Create a pair of connected seqpacket sockets.
+	///
+	/// The sockets are created with `socketpair()` in non-blocking mode with the `close-on-exec` flag set,
+	/// and both are registered with the tokio runtime.
+	/// Each socket can be moved to a different task and used independently.
+	///
+	/// This is useful for communication within a process, for passing one end to a child process,
+	/// and for tests.
 	pub fn pair() -> std::io::Result<(Self, Self)> {
 		let (a, b) = sys::local_seqpacket_pair()?;
 		Ok((Self::new(a)?, Self::new(b)?))
@@ -67,15 +282,20 @@ impl UnixSeqpacket {
 
 	/// Wrap a raw file descriptor as [`UnixSeqpacket`].
 	///
-	/// Registration of the file descriptor with the tokio runtime may fail.
-	/// For that reason, this function returns a [`std::io::Result`].
+	/// This checks that the file descriptor is a `SOCK_SEQPACKET` socket,
+	/// puts it in non-blocking mode and registers it with the tokio runtime.
+	/// Any of these steps may fail, so this function returns a [`std::io::Result`].
+	/// If this function fails, the file descriptor is closed.
 	///
 	/// # Safety
 	/// This function is unsafe because the socket assumes it is the sole owner of the file descriptor.
 	/// Usage of this function could accidentally allow violating this contract
 	/// which can cause memory unsafety in code that relies on it being true.
 	pub unsafe fn from_raw_fd(fd: std::os::unix::io::RawFd) -> std::io::Result<Self> {
-		Self::new(FileDesc::from_raw_fd(fd))
+		let socket = FileDesc::from_raw_fd(fd);
+		sys::ensure_seqpacket(&socket)?;
+		sys::set_nonblocking(&socket, true)?;
+		Self::new(socket)
 	}
 
 	/// Get the raw file descriptor of the socket.
@@ -91,6 +311,30 @@ impl UnixSeqpacket {
 		self.io.into_inner().into_raw_fd()
 	}
 
+	/// Deregister the socket from the tokio runtime without closing it.
+	///
+	/// This can be used to temporarily hand the file descriptor to blocking code.
+	/// Use [`SuspendedSocket::resume()`] to register the socket with the tokio runtime again.
+	///
+	/// If the socket was accepted by a listener with an idle timeout, the timeout is stopped while the socket is suspended,
+	/// and starts again from zero when the socket is resumed.
+	pub fn suspend(self) -> SuspendedSocket {
+		let settings = Settings {
+			recv_watermark: self.recv_watermark.into_inner().unwrap(),
+			max_control_bytes: self.max_control_bytes.into_inner(),
+			max_payload: self.max_payload.into_inner(),
+			track_peak_recv_queue: self.track_peak_recv_queue.into_inner(),
+			user_data: self.user_data.into_inner().unwrap(),
+			label: self.label.into_inner().unwrap(),
+			fd_limit: self.fd_limit,
+			idle_timeout: self.idle_timeout.as_ref().map(IdleTimeout::timeout),
+		};
+		SuspendedSocket {
+			fd: self.io.into_inner(),
+			settings,
+		}
+	}
+
 	#[doc(hidden)]
 	#[deprecated(
 		since = "0.4.0",
@@ -117,6 +361,35 @@ impl UnixSeqpacket {
 		&self.io
 	}
 
+	/// Wait for the socket to become readable or writable, and report which.
+	///
+	/// The returned [`Ready`] value holds all readiness states that were reported,
+	/// so it can contain both [`Ready::READABLE`] and [`Ready::WRITABLE`] at the same time.
+	///
+	/// The readiness is not cleared by this function, so calling it again will return immediately.
+	/// The readiness is cleared when one of the I/O functions of this socket would block.
+	pub async fn ready_any(&self) -> std::io::Result<Ready> {
+		let guard = self.io.ready(Interest::READABLE | Interest::WRITABLE).await?;
+		Ok(guard.ready())
+	}
+
+	/// Get the address of the local socket.
+	///
+	/// Note that connected sockets are usually unnamed,
+	/// but a socket that was accepted by a listener has the address of the listener.
+	pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+		sys::get_local_socket_addr(self.io.get_ref())
+	}
+
+	/// Get the address of the peer socket.
+	///
+	/// If this socket was connected to a listener, this is the address of the listener,
+	/// which may be a pathname or an abstract address.
+	/// Otherwise, the address is usually unnamed.
+	pub fn peer_addr(&self) -> std::io::Result<SocketAddr> {
+		sys::get_peer_socket_addr(self.io.get_ref())
+	}
+
 	/// Get the effective credentials of the process which called `connect` or `pair`.
 	///
 	/// Note that this is not necessarily the process that currently has the file descriptor
@@ -125,11 +398,362 @@ impl UnixSeqpacket {
 		UCred::from_socket_peer(&self.io)
 	}
 
+	/// Wait until the credentials of the peer are available, and return them.
+	///
+	/// Normally, the credentials of the peer are recorded by the kernel when the connection is made,
+	/// and [`Self::peer_cred()`] can be used directly.
+	/// However, the credentials can be missing if the socket was created in a way that does not record them,
+	/// for example by a proxy that passed along a connection before its credentials were known.
+	/// In that case, the kernel reports a PID of zero.
+	///
+	/// This function polls the credentials with an increasing delay (up to 100 milliseconds) until a non-zero PID is reported.
+	/// It does not give up by itself, so you may want to combine it with [`tokio::time::timeout()`].
+	///
+	/// On platforms where the PID of the peer is not available, this function returns the credentials immediately.
+	pub async fn peer_cred_when_ready(&self) -> std::io::Result<UCred> {
+		let mut delay = Duration::from_millis(1);
+		loop {
+			let cred = self.peer_cred()?;
+			if cred.pid() != Some(0) {
+				return Ok(cred);
+			}
+			tokio::time::sleep(delay).await;
+			delay = (delay * 2).min(Duration::from_millis(100));
+		}
+	}
+
+	/// Wait until the send buffer of the socket has room for `bytes` bytes.
+	///
+	/// The free space is computed as the size of the send buffer (`SO_SNDBUF`)
+	/// minus the number of bytes that are still queued in it (`SIOCOUTQ`).
+	/// Queued datagrams are removed from the send buffer when the peer receives them.
+	///
+	/// The kernel needs some extra space for bookkeeping for every datagram,
+	/// so a send of `bytes` bytes may still have to wait if the buffer is almost full.
+	/// Still, this can be used to pace large transfers.
+	///
+	/// There is no notification when the send buffer drains, so this function checks the free space periodically.
+	/// If `bytes` is larger than the size of the send buffer, an error of kind [`std::io::ErrorKind::InvalidInput`] is returned.
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	pub async fn await_send_capacity(&self, bytes: usize) -> std::io::Result<()> {
+		let socket = self.io.get_ref();
+		let mut delay = Duration::from_millis(1);
+		loop {
+			let buffer_size = sys::get_send_buffer_size(socket)?;
+			if bytes > buffer_size {
+				return Err(std::io::Error::new(
+					std::io::ErrorKind::InvalidInput,
+					format!(
+						"{} bytes can never fit in a send buffer of {} bytes",
+						bytes, buffer_size
+					),
+				));
+			}
+			if buffer_size.saturating_sub(sys::send_queue_len(socket)?) >= bytes {
+				return Ok(());
+			}
+			tokio::time::sleep(delay).await;
+			delay = (delay * 2).min(Duration::from_millis(100));
+		}
+	}
+
+	/// Get the inode number of the socket.
+	///
+	/// The inode number identifies the socket in the kernel.
+	/// On Linux, it can be used to find the socket in `/proc/net/unix` or in the `/proc/<pid>/fd` directory of other processes.
+	pub fn inode(&self) -> std::io::Result<u64> {
+		sys::get_inode(self.io.get_ref())
+	}
+
+	/// Get the number of bytes in the receive queue of the socket.
+	///
+	/// On Linux, this is the total size of all datagrams waiting to be received.
+	/// Other platforms may report a different value, such as the size of the next datagram only.
+	pub fn recv_queue_len(&self) -> std::io::Result<usize> {
+		sys::recv_queue_len(self.io.get_ref())
+	}
+
+	/// Get the largest receive queue length observed so far, in bytes.
+	///
+	/// Tracking is off by default, and must be enabled with [`Self::set_track_peak_recv_queue()`].
+	/// While it is enabled, the receive queue length is sampled after every successful receive, as in [`Self::recv_queue_len()`],
+	/// and includes the size of the datagram that was just received.
+	/// This can be used to choose a suitable size for the receive buffer with [`Self::set_recv_buffer_size()`].
+	///
+	/// Reading the value is cheap: it does not make any syscall.
+	pub fn peak_recv_queue(&self) -> usize {
+		self.peak_recv_queue.load(Ordering::Relaxed)
+	}
+
+	/// Enable or disable tracking of the peak receive queue length, reported by [`Self::peak_recv_queue()`].
+	///
+	/// Tracking costs an extra syscall after every successful receive, so it is disabled by default.
+	/// Disabling tracking keeps the peak observed so far.
+	pub fn set_track_peak_recv_queue(&self, enable: bool) {
+		self.track_peak_recv_queue.store(enable, Ordering::Relaxed);
+	}
+
+	/// Wait until at least `bytes` bytes are queued in the receive queue.
+	///
+	/// The queued bytes are counted as in [`Self::recv_queue_len()`]: the total size of all queued datagrams.
+	/// Datagram boundaries are still preserved, so this does not allow receiving multiple datagrams with one receive call.
+	/// It can be used to wait until all datagrams of a message of known size have arrived,
+	/// before receiving them one after the other without waiting.
+	///
+	/// There is no notification when more data is queued on a socket that is already readable,
+	/// so after the socket becomes readable, this function checks the queue periodically.
+	/// If the peer shuts down the connection before enough bytes are queued,
+	/// an error of kind [`std::io::ErrorKind::UnexpectedEof`] is returned.
+	///
+	/// This function is only available on Linux and Android.
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	pub async fn await_queued_bytes(&self, bytes: usize) -> std::io::Result<()> {
+		let socket = self.io.get_ref();
+		let mut delay = Duration::from_millis(1);
+		loop {
+			self.io.readable().await?.retain_ready();
+			let queued = sys::recv_queue_len(socket)?;
+			if queued >= bytes {
+				return Ok(());
+			}
+			if sys::peer_hung_up(socket)? {
+				return Err(std::io::Error::new(
+					std::io::ErrorKind::UnexpectedEof,
+					format!(
+						"peer shut down the connection with {} of {} bytes queued",
+						queued, bytes
+					),
+				));
+			}
+			tokio::time::sleep(delay).await;
+			delay = (delay * 2).min(Duration::from_millis(100));
+		}
+	}
+
+	/// Set a callback to run when the receive queue grows beyond a watermark.
+	///
+	/// After every successful receive operation, the size of the receive queue is checked (see [`Self::recv_queue_len`]).
+	/// If more than `bytes` bytes are still queued, the callback is called with the number of queued bytes.
+	/// This can be used to apply backpressure on whatever is producing the data for the peer.
+	///
+	/// The callback is called for every receive operation that leaves the queue above the watermark,
+	/// not only the first time the watermark is crossed.
+	/// Setting a new watermark replaces the previous one.
+	pub fn set_recv_watermark<F>(&self, bytes: usize, callback: F)
+	where
+		F: Fn(usize) + Send + Sync + 'static,
+	{
+		let mut watermark = self.recv_watermark.lock().unwrap();
+		*watermark = Some(RecvWatermark {
+			bytes,
+			callback: Arc::new(callback),
+		});
+		self.has_recv_watermark.store(true, Ordering::Relaxed);
+	}
+
+	/// Remove the receive queue watermark set with [`Self::set_recv_watermark`].
+	pub fn clear_recv_watermark(&self) {
+		let mut watermark = self.recv_watermark.lock().unwrap();
+		*watermark = None;
+		self.has_recv_watermark.store(false, Ordering::Relaxed);
+	}
+
+	/// Limit the size of control buffers that are allocated automatically.
+	///
+	/// Some functions, like [`Self::relay_fds_to()`], allocate a control buffer for the ancillary data themselves.
+	/// By default, that buffer is large enough for the maximum number of file descriptors that can be passed in one message.
+	/// This function caps the size of those buffers to `bytes`, to bound the memory used for messages from untrusted peers.
+	///
+	/// If the ancillary data of a received message does not fit in the capped buffer,
+	/// those functions return an error of kind [`std::io::ErrorKind::InvalidData`] that wraps a [`ControlTruncated`] error.
+	///
+	/// This does not affect functions that receive into a caller-provided [`SocketAncillary`].
+	pub fn set_max_control_bytes(&self, bytes: usize) {
+		self.max_control_bytes.store(bytes, Ordering::Relaxed);
+	}
+
+	/// Get the maximum size of automatically allocated control buffers.
+	///
+	/// See [`Self::set_max_control_bytes()`].
+	/// If no limit was set, this returns [`usize::MAX`].
+	pub fn max_control_bytes(&self) -> usize {
+		self.max_control_bytes.load(Ordering::Relaxed)
+	}
+
+	/// Limit the size of datagrams that are accepted by the receive functions.
+	///
+	/// Before receiving a datagram, the receive functions check the size of the next datagram.
+	/// If it is larger than `bytes`, the datagram is discarded together with its ancillary data,
+	/// and an error of kind [`std::io::ErrorKind::InvalidData`] that wraps a [`PayloadTooLarge`] error is returned.
+	/// The next call receives the next datagram as usual.
+	///
+	/// This protects against peers that send very large datagrams,
+	/// for example to make [`Self::recv_header_body()`] allocate large buffers.
+	/// Checking the size costs an extra system call per received datagram.
+	///
+	/// This does not affect [`Self::recv_many_credentialed()`] or the peek functions.
+	/// This is only available on Linux and Android,
+	/// since other platforms can not reliably report the size of the next datagram.
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	pub fn set_max_payload(&self, bytes: usize) {
+		self.max_payload.store(bytes, Ordering::Relaxed);
+	}
+
+	/// Get the maximum size of received datagrams.
+	///
+	/// See [`Self::set_max_payload()`].
+	/// If no limit was set, this returns [`usize::MAX`].
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	pub fn max_payload(&self) -> usize {
+		self.max_payload.load(Ordering::Relaxed)
+	}
+
+	/// Check the size of the next datagram against the limit set with [`Self::set_max_payload()`].
+	///
+	/// If the datagram is too large, it is discarded and an error is returned.
+	/// This also returns an error if the socket was closed by the idle timeout.
+	fn check_payload(&self, socket: &FileDesc) -> std::io::Result<()> {
+		self.check_idle()?;
+		let max = self.max_payload.load(Ordering::Relaxed);
+		if max == usize::MAX {
+			return Ok(());
+		}
+		let size = sys::peek_size(socket)?;
+		Self::check_payload_size(socket, size, max)
+	}
+
+	/// Discard the next datagram and return an error if `size` is larger than `max`.
+	fn check_payload_size(socket: &FileDesc, size: usize, max: usize) -> std::io::Result<()> {
+		if size <= max {
+			return Ok(());
+		}
+		sys::recv(socket, &mut [])?;
+		Err(PayloadTooLarge { size, max }.into())
+	}
+
+	/// Attach a value to the socket.
+	///
+	/// The value can be retrieved later with [`Self::user_data()`].
+	/// This replaces any value that was attached before, even if it has a different type.
+	pub fn set_user_data<T: Any + Send + Sync>(&self, value: T) {
+		*self.user_data.lock().unwrap() = Some(Arc::new(value));
+	}
+
+	/// Get the value attached to the socket with [`Self::set_user_data()`].
+	///
+	/// Returns [`None`] if no value is attached, or if the attached value is not of type `T`.
+	pub fn user_data<T: Any + Send + Sync>(&self) -> Option<Arc<T>> {
+		let user_data = self.user_data.lock().unwrap().clone()?;
+		user_data.downcast().ok()
+	}
+
+	/// Remove the value attached to the socket with [`Self::set_user_data()`].
+	pub fn clear_user_data(&self) {
+		*self.user_data.lock().unwrap() = None;
+	}
+
+	/// Set a label that identifies the connection in error messages.
+	///
+	/// When a label is set, errors returned by [`Self::send()`], [`Self::send_vectored()`], [`Self::send_vectored_with_ancillary()`],
+	/// [`Self::recv()`], [`Self::recv_vectored()`] and [`Self::recv_vectored_with_ancillary()`] are wrapped in a [`LabeledError`].
+	/// The wrapped error has the same kind as the original error,
+	/// but the message also mentions the failed operation and the label,
+	/// like `recv failed on connection 'worker-3': Connection reset by peer`.
+	pub fn set_label(&self, label: impl Into<String>) {
+		*self.label.lock().unwrap() = Some(label.into().into());
+	}
+
+	/// Get the label set with [`Self::set_label()`].
+	pub fn label(&self) -> Option<Arc<str>> {
+		self.label.lock().unwrap().clone()
+	}
+
+	/// Remove the label set with [`Self::set_label()`].
+	pub fn clear_label(&self) {
+		*self.label.lock().unwrap() = None;
+	}
+
+	/// Wrap an error in a [`LabeledError`] if the socket has a label.
+	fn with_label<T>(&self, operation: &'static str, result: std::io::Result<T>) -> std::io::Result<T> {
+		let error = match result {
+			Ok(value) => return Ok(value),
+			Err(error) => error,
+		};
+		match self.label() {
+			Some(label) => Err(LabeledError {
+				operation,
+				label,
+				error,
+			}
+			.into()),
+			None => Err(error),
+		}
+	}
+
+	/// Set or clear the peek offset of the socket (`SO_PEEK_OFF`).
+	///
+	/// When a peek offset is set, receiving with `MSG_PEEK` starts at the given offset in the receive queue,
+	/// and every peek advances the offset by the number of bytes that were peeked.
+	/// A normal receive that removes data from the queue decreases the offset by the number of bytes removed.
+	/// Set the offset to `None` to disable this behaviour, which is the default.
+	///
+	/// Note that the offset is not limited to a single datagram:
+	/// if the offset is larger than the next datagram, peeking starts in a later datagram.
+	/// A single peek still never returns data from more than one datagram.
+	///
+	/// This also affects functions of this crate that peek internally, such as [`Self::recv_header_body()`].
+	/// You should not use those while a peek offset is set.
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	pub fn set_peek_off(&self, offset: Option<usize>) -> std::io::Result<()> {
+		sys::set_peek_offset(self.io.get_ref(), offset)
+	}
+
+	/// Get the peek offset of the socket (`SO_PEEK_OFF`).
+	///
+	/// Returns `None` if no peek offset is set.
+	/// See [`Self::set_peek_off()`].
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	pub fn peek_off(&self) -> std::io::Result<Option<usize>> {
+		sys::get_peek_offset(self.io.get_ref())
+	}
+
+	/// Get the sizes of the datagrams that are waiting in the receive queue, without removing them.
+	///
+	/// This can be used to allocate buffers of the right size before receiving a batch of datagrams.
+	/// At most `max` sizes are returned, starting with the next datagram to be received.
+	///
+	/// The kernel has no direct way to report this, so this function peeks at each queued datagram with `MSG_PEEK | MSG_TRUNC`,
+	/// using [`Self::set_peek_off()`] to skip the datagrams that were already seen.
+	/// That costs two system calls per datagram, and the peek offset of the socket is temporarily changed.
+	/// You should not use peeking functions from other tasks while this function runs.
+	///
+	/// The peek offset can not move past an empty datagram, so the list stops at the first empty datagram.
+	/// The empty datagram and the datagrams after it are not reported.
+	/// Datagrams that arrive while this function runs may or may not be included.
+	///
+	/// This function is only available on Linux and Android.
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	pub fn queued_datagram_sizes(&self, max: usize) -> std::io::Result<Vec<usize>> {
+		sys::queued_datagram_sizes(self.io.get_ref(), max)
+	}
+
 	/// Get and clear the value of the `SO_ERROR` option.
 	pub fn take_error(&self) -> std::io::Result<Option<std::io::Error>> {
 		sys::take_socket_error(self.io.get_ref())
 	}
 
+	/// Check if the socket is ready to send data.
+	///
+	/// If the socket is not ready yet, the current task is scheduled to wake up when the socket becomes writeable.
+	/// Once this returns ready, use [`Self::try_send()`] or [`Self::try_send_vectored_with_ancillary()`] to send data.
+	/// If those return an error of kind [`std::io::ErrorKind::WouldBlock`], the socket is no longer ready and this function must be polled again.
+	///
+	/// Note that only the last task calling this function will be woken up.
+	pub fn poll_send_ready(&self, cx: &mut Context) -> Poll<std::io::Result<()>> {
+		ready!(self.io.poll_write_ready(cx)?).retain_ready();
+		Poll::Ready(Ok(()))
+	}
+
 	/// Try to send data on the socket to the connected peer without blocking.
 	///
 	/// If the socket is not ready yet, the current task is scheduled to wake up when the socket becomes writeable.
@@ -163,11 +787,61 @@ impl UnixSeqpacket {
 	///
 	/// Note that unlike [`Self::send_vectored_with_ancillary`], only the last task calling this function will be woken up.
 	/// For that reason, it is preferable to use the async functions rather than polling functions when possible.
+	///
+	/// If a limit is set with [`Self::set_max_fds_in_flight()`], this also waits until there is room for the file descriptors.
 	pub fn poll_send_vectored_with_ancillary(
 		&self,
 		cx: &mut Context,
 		buffer: &[IoSlice],
 		ancillary: &mut SocketAncillary,
+	) -> Poll<std::io::Result<usize>> {
+		let count = ancillary.fd_count();
+		if count > 0 {
+			self.fd_limit
+				.poll_send_with(cx, count, |cx| self.poll_send_msg(cx, buffer, ancillary))
+		} else {
+			self.poll_send_msg(cx, buffer, ancillary)
+		}
+	}
+
+	/// Send data on the socket to the connected peer without blocking and without registering for wake-up.
+	///
+	/// If the socket is not ready, an error of kind [`std::io::ErrorKind::WouldBlock`] is returned.
+	/// Readiness is tracked by the tokio runtime, so this may also return that error if the runtime did not yet see the socket become ready.
+	/// Use [`Self::poll_send_ready()`] to wait until the socket is ready.
+	pub fn try_send(&self, buffer: &[u8]) -> std::io::Result<usize> {
+		self.io.try_io(Interest::WRITABLE, |inner| sys::send(inner, buffer))
+	}
+
+	/// Send data with ancillary data on the socket to the connected peer without blocking and without registering for wake-up.
+	///
+	/// The data is sent as a single datagram, like with [`Self::send_vectored_with_ancillary()`].
+	/// If the socket is not ready, or if a limit set with [`Self::set_max_fds_in_flight()`] leaves no room for the file descriptors,
+	/// an error of kind [`std::io::ErrorKind::WouldBlock`] is returned.
+	/// Use [`Self::poll_send_ready()`] to wait until the socket is ready.
+	pub fn try_send_vectored_with_ancillary(
+		&self,
+		buffer: &[IoSlice],
+		ancillary: &mut SocketAncillary,
+	) -> std::io::Result<usize> {
+		let count = ancillary.fd_count();
+		let mut send = || {
+			self.io
+				.try_io(Interest::WRITABLE, |inner| sys::send_msg(inner, buffer, ancillary))
+		};
+		if count > 0 {
+			self.fd_limit.try_send_with(count, send)
+		} else {
+			send()
+		}
+	}
+
+	/// Send a message without checking the limit on file descriptors in flight.
+	fn poll_send_msg(
+		&self,
+		cx: &mut Context,
+		buffer: &[IoSlice],
+		ancillary: &mut SocketAncillary,
 	) -> Poll<std::io::Result<usize>> {
 		loop {
 			let mut ready_guard = ready!(self.io.poll_write_ready(cx)?);
@@ -184,14 +858,18 @@ impl UnixSeqpacket {
 	/// All calling tasks will try to complete the asynchronous action,
 	/// although the order in which they complete is not guaranteed.
 	pub async fn send(&self, buffer: &[u8]) -> std::io::Result<usize> {
-		loop {
-			let mut ready_guard = self.io.writable().await?;
+		let result = async {
+			loop {
+				let mut ready_guard = self.io.writable().await?;
 
-			match ready_guard.try_io(|inner| sys::send(inner.get_ref(), buffer)) {
-				Ok(result) => return result,
-				Err(_would_block) => continue,
+				match ready_guard.try_io(|inner| sys::send(inner.get_ref(), buffer)) {
+					Ok(result) => return result,
+					Err(_would_block) => continue,
+				}
 			}
 		}
+		.await;
+		self.with_label("send", result)
 	}
 
 	/// Send data on the socket to the connected peer.
@@ -206,6 +884,13 @@ impl UnixSeqpacket {
 
 	/// Send data with ancillary data on the socket to the connected peer.
 	///
+	/// All buffers are sent together as a single datagram with one `sendmsg()` call, so there is no need to concatenate them first.
+	/// The datagram is never truncated: if it does not fit in the send buffer of the socket,
+	/// the kernel rejects it with `EMSGSIZE` and nothing is sent.
+	/// If `ancillary` is empty, no control messages are sent and this behaves like [`Self::send_vectored()`].
+	///
+	/// If a limit is set with [`Self::set_max_fds_in_flight()`], this also waits until there is room for the file descriptors.
+	///
 	/// This function is safe to call concurrently from different tasks.
 	/// All calling tasks will try to complete the asynchronous action,
 	/// although the order in which they complete is not guaranteed.
@@ -214,13 +899,38 @@ impl UnixSeqpacket {
 		buffer: &[IoSlice<'_>],
 		ancillary: &mut SocketAncillary<'_>,
 	) -> std::io::Result<usize> {
-		loop {
-			let mut ready_guard = self.io.writable().await?;
-			match ready_guard.try_io(|inner| sys::send_msg(inner.get_ref(), buffer, ancillary)) {
-				Ok(result) => return result,
-				Err(_would_block) => continue,
+		let count = ancillary.fd_count();
+		let result = if count > 0 && self.fd_limit.is_enabled() {
+			std::future::poll_fn(|cx| {
+				self.fd_limit
+					.poll_send_with(cx, count, |cx| self.poll_send_msg(cx, buffer, ancillary))
+			})
+			.await
+		} else {
+			async {
+				loop {
+					let mut ready_guard = self.io.writable().await?;
+					match ready_guard.try_io(|inner| sys::send_msg(inner.get_ref(), buffer, ancillary)) {
+						Ok(result) => return result,
+						Err(_would_block) => continue,
+					}
+				}
 			}
-		}
+			.await
+		};
+		self.with_label("send", result)
+	}
+
+	/// Check if the socket has data ready to be received.
+	///
+	/// If there is no data ready yet, the current task is scheduled to wake up when the socket becomes readable.
+	/// Once this returns ready, use [`Self::try_recv()`] or [`Self::try_recv_vectored_with_ancillary()`] to receive data.
+	/// If those return an error of kind [`std::io::ErrorKind::WouldBlock`], the socket is no longer ready and this function must be polled again.
+	///
+	/// Note that only the last task calling this function will be woken up.
+	pub fn poll_recv_ready(&self, cx: &mut Context) -> Poll<std::io::Result<()>> {
+		ready!(self.io.poll_read_ready(cx)?).retain_ready();
+		Poll::Ready(Ok(()))
 	}
 
 	/// Try to receive data on the socket from the connected peer without blocking.
@@ -232,8 +942,11 @@ impl UnixSeqpacket {
 	pub fn poll_recv(&self, cx: &mut Context, buffer: &mut [u8]) -> Poll<std::io::Result<usize>> {
 		loop {
 			let mut ready_guard = ready!(self.io.poll_read_ready(cx)?);
-			match ready_guard.try_io(|inner| sys::recv(inner.get_ref(), buffer)) {
-				Ok(result) => return Poll::Ready(result),
+			match ready_guard.try_io(|inner| {
+				self.check_payload(inner.get_ref())
+					.and_then(|()| sys::recv(inner.get_ref(), buffer))
+			}) {
+				Ok(result) => return Poll::Ready(result.map(|len| self.after_recv(len))),
 				Err(_would_block) => continue,
 			}
 		}
@@ -271,28 +984,325 @@ impl UnixSeqpacket {
 		loop {
 			let mut ready_guard = ready!(self.io.poll_read_ready(cx)?);
 
-			match ready_guard.try_io(|inner| sys::recv_msg(inner.get_ref(), buffer, ancillary)) {
-				Ok(result) => return Poll::Ready(result),
+			match ready_guard.try_io(|inner| {
+				self.check_payload(inner.get_ref())
+					.and_then(|()| sys::recv_msg(inner.get_ref(), buffer, ancillary))
+			}) {
+				Ok(result) => return Poll::Ready(result.map(|len| self.after_recv(len))),
 				Err(_would_block) => continue,
 			}
 		}
 	}
 
+	/// Receive data on the socket from the connected peer without blocking and without registering for wake-up.
+	///
+	/// If there is no data ready, an error of kind [`std::io::ErrorKind::WouldBlock`] is returned.
+	/// Readiness is tracked by the tokio runtime, so this may also return that error if the runtime did not yet see the socket become ready.
+	/// Use [`Self::poll_recv_ready()`] to wait until data is ready.
+	pub fn try_recv(&self, buffer: &mut [u8]) -> std::io::Result<usize> {
+		self.io
+			.try_io(Interest::READABLE, |inner| {
+				self.check_payload(inner).and_then(|()| sys::recv(inner, buffer))
+			})
+			.map(|len| self.after_recv(len))
+	}
+
+	/// Receive data with ancillary data on the socket from the connected peer without blocking and without registering for wake-up.
+	///
+	/// A single datagram is received, like with [`Self::recv_vectored_with_ancillary()`].
+	/// If there is no data ready, an error of kind [`std::io::ErrorKind::WouldBlock`] is returned.
+	/// Use [`Self::poll_recv_ready()`] to wait until data is ready.
+	pub fn try_recv_vectored_with_ancillary(
+		&self,
+		buffer: &mut [IoSliceMut],
+		ancillary: &mut SocketAncillary,
+	) -> std::io::Result<usize> {
+		self.io
+			.try_io(Interest::READABLE, |inner| {
+				self.check_payload(inner)
+					.and_then(|()| sys::recv_msg(inner, buffer, ancillary))
+			})
+			.map(|len| self.after_recv(len))
+	}
+
 	/// Receive data on the socket from the connected peer.
 	///
 	/// This function is safe to call concurrently from different tasks.
 	/// All calling tasks will try to complete the asynchronous action,
 	/// although the order in which they complete is not guaranteed.
 	pub async fn recv(&self, buffer: &mut [u8]) -> std::io::Result<usize> {
+		let result = async {
+			loop {
+				let mut ready_guard = self.io.readable().await?;
+				match ready_guard.try_io(|inner| {
+					self.check_payload(inner.get_ref())
+						.and_then(|()| sys::recv(inner.get_ref(), buffer))
+				}) {
+					Ok(result) => return result.map(|len| self.after_recv(len)),
+					Err(_would_block) => continue,
+				}
+			}
+		}
+		.await;
+		self.with_label("recv", result)
+	}
+
+	/// Receive data on the socket and report the full size of the datagram.
+	///
+	/// This copies as much of the datagram as fits in `buffer`, and discards the rest.
+	/// Unlike [`Self::recv()`], it also reports the full size of the datagram (using `MSG_TRUNC`),
+	/// so a truncated datagram can be detected, and the right buffer size for the next receive is known.
+	///
+	/// This function is only available on Linux and Android.
+	///
+	/// This function is safe to call concurrently from different tasks.
+	/// All calling tasks will try to complete the asynchronous action,
+	/// although the order in which they complete is not guaranteed.
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	pub async fn recv_sized(&self, buffer: &mut [u8]) -> std::io::Result<RecvSized> {
 		loop {
 			let mut ready_guard = self.io.readable().await?;
-			match ready_guard.try_io(|inner| sys::recv(inner.get_ref(), buffer)) {
+			match ready_guard.try_io(|inner| {
+				self.check_payload(inner.get_ref()).and_then(|()| {
+					sys::recv_msg_full_len(
+						inner.get_ref(),
+						&mut [IoSliceMut::new(buffer)],
+						&mut SocketAncillary::new(&mut []),
+					)
+				})
+			}) {
+				Ok(result) => {
+					return result.map(|full_len| {
+						let copied = self.after_recv(full_len.min(buffer.len()));
+						RecvSized { copied, full_len }
+					})
+				},
+				Err(_would_block) => continue,
+			}
+		}
+	}
+
+	/// Receive the next datagram only if it fits in `buffer`.
+	///
+	/// This waits for a datagram and checks its size first.
+	/// If it fits, it is received into `buffer` and `Some(len)` is returned.
+	/// If it does not fit, it is left in the receive queue and `None` is returned,
+	/// so it can be received later with a larger buffer.
+	///
+	/// If another task receives from the same socket concurrently,
+	/// the checked datagram may be received by that task, and this function may receive a different datagram.
+	///
+	/// This function is only available on Linux and Android.
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	pub async fn recv_if_fits(&self, buffer: &mut [u8]) -> std::io::Result<Option<usize>> {
+		loop {
+			let mut ready_guard = self.io.readable().await?;
+			let result = ready_guard.try_io(|inner| {
+				self.check_idle()?;
+				let size = sys::peek_size(inner.get_ref())?;
+				Self::check_payload_size(inner.get_ref(), size, self.max_payload.load(Ordering::Relaxed))?;
+				if size > buffer.len() {
+					return Ok(None);
+				}
+				sys::recv(inner.get_ref(), buffer).map(Some)
+			});
+			match result {
+				Ok(result) => return result.map(|len| len.map(|len| self.after_recv(len))),
+				Err(_would_block) => continue,
+			}
+		}
+	}
+
+	/// Receive data with ancillary data on the socket and report the full size of the datagram.
+	///
+	/// This is the same as [`Self::recv_vectored_with_ancillary()`],
+	/// but like [`Self::recv_sized()`], it also reports the full size of the datagram (using `MSG_TRUNC`).
+	///
+	/// Truncation of the data and truncation of the ancillary data are reported independently:
+	/// the data was truncated if [`RecvSized::is_truncated()`] returns true,
+	/// and the ancillary data was truncated if [`SocketAncillary::truncated()`] returns true.
+	///
+	/// This function is only available on Linux and Android.
+	///
+	/// This function is safe to call concurrently from different tasks.
+	/// All calling tasks will try to complete the asynchronous action,
+	/// although the order in which they complete is not guaranteed.
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	pub async fn recv_vectored_with_ancillary_sized(
+		&self,
+		buffer: &mut [IoSliceMut<'_>],
+		ancillary: &mut SocketAncillary<'_>,
+	) -> std::io::Result<RecvSized> {
+		let capacity = buffer.iter().map(|buffer| buffer.len()).sum::<usize>();
+		loop {
+			let mut ready_guard = self.io.readable().await?;
+			match ready_guard.try_io(|inner| {
+				self.check_payload(inner.get_ref())
+					.and_then(|()| sys::recv_msg_full_len(inner.get_ref(), buffer, ancillary))
+			}) {
+				Ok(result) => {
+					return result.map(|full_len| {
+						let copied = self.after_recv(full_len.min(capacity));
+						RecvSized { copied, full_len }
+					})
+				},
+				Err(_would_block) => continue,
+			}
+		}
+	}
+
+	/// Receive data from the next datagram without removing it from the receive queue.
+	///
+	/// The datagram stays queued, so the next receive or peek returns the same datagram again.
+	/// The socket also stays readable.
+	/// This can be used to inspect a header before deciding how to receive the datagram.
+	///
+	/// The limit set with [`Self::set_max_payload()`] does not apply to this function.
+	///
+	/// This function is safe to call concurrently from different tasks.
+	/// All calling tasks will try to complete the asynchronous action,
+	/// although the order in which they complete is not guaranteed.
+	pub async fn peek(&self, buffer: &mut [u8]) -> std::io::Result<usize> {
+		self.peek_vectored_with_ancillary(&mut [IoSliceMut::new(buffer)], &mut SocketAncillary::new(&mut []))
+			.await
+	}
+
+	/// Receive data and ancillary data from the next datagram without removing it from the receive queue.
+	///
+	/// See [`Self::peek()`].
+	///
+	/// Note that the kernel installs new copies of any file descriptors in the ancillary data every time a datagram is peeked.
+	/// You should close them just like the file descriptors of a normal receive,
+	/// for example with [`SocketAncillary::take_fds()`].
+	///
+	/// This function is safe to call concurrently from different tasks.
+	/// All calling tasks will try to complete the asynchronous action,
+	/// although the order in which they complete is not guaranteed.
+	pub async fn peek_vectored_with_ancillary(
+		&self,
+		buffer: &mut [IoSliceMut<'_>],
+		ancillary: &mut SocketAncillary<'_>,
+	) -> std::io::Result<usize> {
+		loop {
+			let mut ready_guard = self.io.readable().await?;
+			match ready_guard.try_io(|inner| {
+				self.check_idle()
+					.and_then(|()| sys::peek_msg(inner.get_ref(), buffer, ancillary))
+			}) {
 				Ok(result) => return result,
 				Err(_would_block) => continue,
 			}
 		}
 	}
 
+	/// Send a keepalive to the connected peer.
+	///
+	/// By convention, a keepalive is an empty datagram.
+	/// On Linux and Android, the peer can use [`Self::recv_skip_keepalive()`] to receive data while skipping keepalives.
+	///
+	/// An empty datagram can not carry a marker in the ancillary data reliably:
+	/// credentials are only delivered if the peer enabled `SO_PASSCRED`,
+	/// and an empty list of file descriptors is dropped by the kernel.
+	/// Instead, the receiver tells keepalives apart from the end of the connection by checking if the peer hung up.
+	pub async fn send_control_only(&self) -> std::io::Result<()> {
+		loop {
+			let mut ready_guard = self.io.writable().await?;
+			match ready_guard.try_io(|inner| sys::send(inner.get_ref(), &[])) {
+				Ok(result) => return result.map(drop),
+				Err(_would_block) => continue,
+			}
+		}
+	}
+
+	/// Receive data on the socket from the connected peer, skipping keepalives.
+	///
+	/// Empty datagrams sent with [`Self::send_control_only()`] are received and discarded.
+	/// This function only returns `Ok(0)` when the peer has closed the connection.
+	///
+	/// Note that this means that empty datagrams can not be used for anything other than keepalives.
+	/// If the peer shut down the connection, a keepalive is only treated as the end of the connection
+	/// when no more data is queued behind it, so no data sent before the shutdown is lost.
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	pub async fn recv_skip_keepalive(&self, buffer: &mut [u8]) -> std::io::Result<usize> {
+		loop {
+			let len = self.recv(buffer).await?;
+			if len > 0 || self.at_eof()? {
+				return Ok(len);
+			}
+		}
+	}
+
+	/// Check if the peer shut down the connection and nothing more is queued, after receiving nothing.
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	fn at_eof(&self) -> std::io::Result<bool> {
+		let socket = self.io.get_ref();
+		Ok(sys::peer_hung_up(socket)? && sys::recv_queue_len(socket)? == 0)
+	}
+
+	/// Receive a datagram, and report whether it is empty or the connection was shut down.
+	///
+	/// Both an empty datagram and the end of the connection make [`Self::recv()`] return `Ok(0)`.
+	/// This function tells them apart by checking if the peer has shut down the connection after receiving nothing.
+	///
+	/// The end of the connection is only reported when nothing more is queued.
+	/// The kernel gives no way to tell them apart for sure, so an empty datagram that is the last thing queued
+	/// when the peer shuts down the connection is reported as [`RecvState::Eof`].
+	///
+	/// This function is only available on Linux and Android.
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	pub async fn recv_or_eof(&self, buffer: &mut [u8]) -> std::io::Result<RecvState> {
+		let len = self.recv(buffer).await?;
+		if len > 0 {
+			Ok(RecvState::Data(len))
+		} else if self.at_eof()? {
+			Ok(RecvState::Eof)
+		} else {
+			Ok(RecvState::EmptyDatagram)
+		}
+	}
+
+	/// Receive up to `max_messages` datagrams, passing each one to `handler`, and then yield to the scheduler.
+	///
+	/// This waits until at least one datagram is available.
+	/// After that, it keeps receiving datagrams that are already queued, until `max_messages` datagrams were handled or the queue is empty.
+	/// Then the task yields, even if more datagrams are queued,
+	/// so a busy connection can not monopolize a worker thread.
+	///
+	/// Each datagram is received into `buffer` and passed to `handler`.
+	/// An empty datagram, which also signals the end of the connection, ends the batch early.
+	///
+	/// Returns the number of datagrams that were passed to the handler.
+	pub async fn recv_up_to(
+		&self,
+		buffer: &mut [u8],
+		max_messages: usize,
+		mut handler: impl FnMut(&[u8]),
+	) -> std::io::Result<usize> {
+		let mut count = 0;
+		if max_messages > 0 {
+			let mut ready_guard = self.io.readable().await?;
+			loop {
+				match ready_guard.try_io(|inner| {
+					self.check_payload(inner.get_ref())
+						.and_then(|()| sys::recv(inner.get_ref(), buffer))
+				}) {
+					Ok(result) => {
+						let len = self.after_recv(result?);
+						handler(&buffer[..len]);
+						count += 1;
+						if len == 0 || count == max_messages {
+							break;
+						}
+					},
+					Err(_would_block) if count > 0 => break,
+					Err(_would_block) => ready_guard = self.io.readable().await?,
+				}
+			}
+		}
+		tokio::task::yield_now().await;
+		Ok(count)
+	}
+
 	/// Receive data on the socket from the connected peer.
 	///
 	/// This function is safe to call concurrently from different tasks.
@@ -320,24 +1330,565 @@ impl UnixSeqpacket {
 		buffer: &mut [IoSliceMut<'_>],
 		ancillary: &mut SocketAncillary<'_>,
 	) -> std::io::Result<usize> {
+		let result = async {
+			loop {
+				let mut ready_guard = self.io.readable().await?;
+
+				match ready_guard.try_io(|inner| {
+					self.check_payload(inner.get_ref())
+						.and_then(|()| sys::recv_msg(inner.get_ref(), buffer, ancillary))
+				}) {
+					Ok(result) => return result.map(|len| self.after_recv(len)),
+					Err(_would_block) => continue,
+				}
+			}
+		}
+		.await;
+		self.with_label("recv", result)
+	}
+
+	/// Try to receive data with ancillary data and the address of the sender without blocking.
+	///
+	/// This is the same as [`Self::poll_recv_vectored_with_ancillary`],
+	/// but it also returns the address of the sender, if the kernel provides one.
+	/// For connected sockets, this is normally an unnamed address.
+	///
+	/// Note that unlike [`Self::recv_vectored_with_ancillary_from`], only the last task calling this function will be woken up.
+	/// For that reason, it is preferable to use the async functions rather than polling functions when possible.
+	pub fn poll_recv_vectored_with_ancillary_from(
+		&self,
+		cx: &mut Context,
+		buffer: &mut [IoSliceMut],
+		ancillary: &mut SocketAncillary,
+	) -> Poll<std::io::Result<(usize, SocketAddr)>> {
+		loop {
+			let mut ready_guard = ready!(self.io.poll_read_ready(cx)?);
+
+			match ready_guard.try_io(|inner| {
+				self.check_payload(inner.get_ref())
+					.and_then(|()| sys::recv_msg_from(inner.get_ref(), buffer, ancillary))
+			}) {
+				Ok(result) => return Poll::Ready(result.map(|(len, address)| (self.after_recv(len), address))),
+				Err(_would_block) => continue,
+			}
+		}
+	}
+
+	/// Receive data with ancillary data and the address of the sender.
+	///
+	/// This is the same as [`Self::recv_vectored_with_ancillary`],
+	/// but it also returns the address of the sender, if the kernel provides one.
+	/// For connected sockets, this is normally an unnamed address.
+	///
+	/// This function is safe to call concurrently from different tasks.
+	/// All calling tasks will try to complete the asynchronous action,
+	/// although the order in which they complete is not guaranteed.
+	pub async fn recv_vectored_with_ancillary_from(
+		&self,
+		buffer: &mut [IoSliceMut<'_>],
+		ancillary: &mut SocketAncillary<'_>,
+	) -> std::io::Result<(usize, SocketAddr)> {
 		loop {
 			let mut ready_guard = self.io.readable().await?;
 
-			match ready_guard.try_io(|inner| sys::recv_msg(inner.get_ref(), buffer, ancillary)) {
-				Ok(result) => return result,
+			match ready_guard.try_io(|inner| {
+				self.check_payload(inner.get_ref())
+					.and_then(|()| sys::recv_msg_from(inner.get_ref(), buffer, ancillary))
+			}) {
+				Ok(result) => return result.map(|(len, address)| (self.after_recv(len), address)),
 				Err(_would_block) => continue,
 			}
 		}
 	}
 
+	/// Try to receive data with ancillary data and count the received file descriptors without blocking.
+	///
+	/// This is the same as [`Self::poll_recv_vectored_with_ancillary`],
+	/// but it also returns the total number of file descriptors in the received `SCM_RIGHTS` control messages.
+	///
+	/// Note that unlike [`Self::recv_vectored_with_fd_count`], only the last task calling this function will be woken up.
+	/// For that reason, it is preferable to use the async functions rather than polling functions when possible.
+	pub fn poll_recv_vectored_with_fd_count(
+		&self,
+		cx: &mut Context,
+		buffer: &mut [IoSliceMut],
+		ancillary: &mut SocketAncillary,
+	) -> Poll<std::io::Result<(usize, usize)>> {
+		let len = ready!(self.poll_recv_vectored_with_ancillary(cx, buffer, ancillary))?;
+		Poll::Ready(Ok((len, ancillary.fd_count())))
+	}
+
+	/// Receive data with ancillary data and count the received file descriptors.
+	///
+	/// This is the same as [`Self::recv_vectored_with_ancillary`],
+	/// but it also returns the total number of file descriptors in the received `SCM_RIGHTS` control messages,
+	/// as reported by [`SocketAncillary::fd_count()`].
+	/// This can be used to decide how to handle the file descriptors without iterating over the control messages.
+	///
+	/// Returns the number of bytes received and the number of file descriptors, in that order.
+	///
+	/// This function is safe to call concurrently from different tasks.
+	/// All calling tasks will try to complete the asynchronous action,
+	/// although the order in which they complete is not guaranteed.
+	pub async fn recv_vectored_with_fd_count(
+		&self,
+		buffer: &mut [IoSliceMut<'_>],
+		ancillary: &mut SocketAncillary<'_>,
+	) -> std::io::Result<(usize, usize)> {
+		let len = self.recv_vectored_with_ancillary(buffer, ancillary).await?;
+		Ok((len, ancillary.fd_count()))
+	}
+
+	/// Receive multiple messages with the credentials of the sender in a single system call.
+	///
+	/// Each buffer in `buffers` receives one message, so at most `buffers.len()` messages are received.
+	/// This waits until at least one message is available,
+	/// and then receives all messages that are available without waiting further.
+	///
+	/// Returns the length and credentials of each received message.
+	/// If the kernel did not attach credentials to a message, the credentials are `None`.
+	///
+	/// This function enables `SO_PASSCRED` on the socket, which is required to receive credentials.
+	/// However, messages that were sent before `SO_PASSCRED` was enabled only have credentials
+	/// if the sender attached them explicitly.
+	///
+	/// The control buffer for each message only has room for the credentials.
+	/// If a message also holds file descriptors, they are closed by the kernel.
+	///
+	/// This function is only available on Linux and Android.
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	pub async fn recv_many_credentialed(
+		&self,
+		buffers: &mut [&mut [u8]],
+	) -> std::io::Result<Vec<(usize, Option<SocketCred>)>> {
+		sys::set_passcred(self.io.get_ref(), true)?;
+		loop {
+			let mut ready_guard = self.io.readable().await?;
+
+			match ready_guard.try_io(|inner| {
+				self.check_idle()
+					.and_then(|()| sys::recv_many_credentialed(inner.get_ref(), buffers))
+			}) {
+				Ok(result) => {
+					return result.inspect(|messages| {
+						self.after_recv(messages.iter().map(|(len, _)| len).sum());
+					})
+				},
+				Err(_would_block) => continue,
+			}
+		}
+	}
+
+	/// Send a request datagram and wait for a single reply datagram.
+	///
+	/// Seqpacket connections preserve the order of datagrams, so on a connection that is dedicated to request-reply calls,
+	/// the next datagram received after sending a request is the reply to that request.
+	/// The reply is received into `response`, and the number of bytes received is returned.
+	/// Like with [`Self::recv()`], a reply that does not fit in `response` is truncated,
+	/// and `0` bytes are received if the peer closed the connection.
+	///
+	/// If the request can not be sent and the reply received within `timeout`, an error of kind [`std::io::ErrorKind::TimedOut`] is returned.
+	/// In that case, a late reply may still arrive, and it would be mistaken for the reply to the next call.
+	/// For that reason, the connection should not be used for more calls after a timeout.
+	///
+	/// You should not use this function on a connection where other tasks are sending or receiving at the same time.
+	pub async fn call(&self, request: &[u8], response: &mut [u8], timeout: Duration) -> std::io::Result<usize> {
+		let exchange = async {
+			self.send(request).await?;
+			self.recv(response).await
+		};
+
+		match tokio::time::timeout(timeout, exchange).await {
+			Ok(result) => result,
+			Err(_elapsed) => Err(std::io::Error::new(
+				std::io::ErrorKind::TimedOut,
+				"timed out waiting for reply",
+			)),
+		}
+	}
+
+	/// Measure the round-trip time to a peer that echoes all datagrams back.
+	///
+	/// This sends a datagram consisting of an 8 byte big-endian sequence number followed by `payload`,
+	/// and waits for the peer to send back the exact same datagram.
+	/// Any other datagrams received in the meantime are discarded.
+	///
+	/// If no matching reply is received within `timeout`, an error of kind [`std::io::ErrorKind::TimedOut`] is returned.
+	///
+	/// This is meant for diagnostics and health checks.
+	/// You should not use it on a connection where other tasks are receiving at the same time.
+	pub async fn ping(&self, payload: &[u8], timeout: Duration) -> std::io::Result<Duration> {
+		static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+		let sequence = SEQUENCE.fetch_add(1, Ordering::Relaxed);
+
+		let mut request = Vec::with_capacity(8 + payload.len());
+		request.extend_from_slice(&sequence.to_be_bytes());
+		request.extend_from_slice(payload);
+		// One extra byte to detect replies that are too long.
+		let mut reply = vec![0; request.len() + 1];
+
+		let start = std::time::Instant::now();
+		let round_trip = async {
+			self.send(&request).await?;
+			loop {
+				let len = self.recv(&mut reply).await?;
+				if reply[..len] == request[..] {
+					return Ok(start.elapsed());
+				}
+			}
+		};
+
+		match tokio::time::timeout(timeout, round_trip).await {
+			Ok(result) => result,
+			Err(_elapsed) => Err(std::io::Error::new(
+				std::io::ErrorKind::TimedOut,
+				"timed out waiting for ping reply",
+			)),
+		}
+	}
+
+	/// Receive a datagram with a fixed-size header and a variable sized body.
+	///
+	/// The first bytes of the datagram are received into `header`,
+	/// and the remaining bytes are received into a newly allocated buffer that is sized to fit the body exactly.
+	/// Both parts are received with a single `recvmsg` call, after querying the size of the datagram.
+	///
+	/// Returns the number of bytes received into `header` and the body.
+	/// If the datagram is shorter than the header, the body is empty.
+	///
+	/// On Linux and Android, the size of the next datagram is queried directly.
+	/// On other platforms, the total size of the receive queue is used instead,
+	/// which may cause a larger allocation than necessary.
+	/// If another task receives from the same socket concurrently, the body may still be truncated.
+	pub async fn recv_header_body(&self, header: &mut [u8]) -> std::io::Result<(usize, Vec<u8>)> {
+		loop {
+			let mut ready_guard = self.io.readable().await?;
+
+			let result = ready_guard.try_io(|inner| {
+				self.check_idle()?;
+				let size = sys::peek_size(inner.get_ref())?;
+				Self::check_payload_size(inner.get_ref(), size, self.max_payload.load(Ordering::Relaxed))?;
+				let mut body = vec![0; size.saturating_sub(header.len())];
+				let header_len = header.len();
+				let received = sys::recv_msg(
+					inner.get_ref(),
+					&mut [IoSliceMut::new(header), IoSliceMut::new(&mut body)],
+					&mut SocketAncillary::new(&mut []),
+				)?;
+				body.truncate(received.saturating_sub(header_len));
+				Ok((received.min(header_len), body))
+			});
+
+			match result {
+				Ok(result) => {
+					return result.map(|(header_len, body)| {
+						self.after_recv(header_len + body.len());
+						(header_len, body)
+					})
+				},
+				Err(_would_block) => continue,
+			}
+		}
+	}
+
+	/// Receive a datagram with file descriptors and forward it to another socket.
+	///
+	/// The payload is received into `buffer` and sent to `destination` together with all received file descriptors.
+	/// Afterwards, the local copies of the file descriptors are closed, even if sending the datagram failed.
+	/// Other ancillary data, such as credentials, is not forwarded.
+	///
+	/// If the ancillary data of the received datagram was truncated, an error wrapping [`ControlTruncated`] is returned and nothing is forwarded.
+	/// The size of the control buffer can be limited with [`Self::set_max_control_bytes()`].
+	///
+	/// Returns the number of bytes sent to `destination`.
+	pub async fn relay_fds_to(&self, destination: &UnixSeqpacket, buffer: &mut [u8]) -> std::io::Result<usize> {
+		let capacity = ancillary::fds_space(ancillary::MAX_FDS_PER_MESSAGE).min(self.max_control_bytes());
+		let mut control = vec![0; capacity];
+		let mut received = SocketAncillary::new(&mut control);
+		let len = self
+			.recv_vectored_with_ancillary(&mut [IoSliceMut::new(buffer)], &mut received)
+			.await?;
+
+		// Take ownership of the file descriptors right away, so they are closed on every exit path.
+		let fds: Vec<OwnedFd> = received.take_fds().collect();
+
+		if received.truncated() {
+			return Err(ControlTruncated { capacity }.into());
+		}
+
+		let raw_fds: Vec<RawFd> = fds.iter().map(|fd| fd.as_raw_fd()).collect();
+		let mut control = vec![0; ancillary::fds_space(raw_fds.len())];
+		let mut forward = SocketAncillary::new(&mut control);
+		if !raw_fds.is_empty() {
+			forward.add_fds(&raw_fds);
+		}
+		destination
+			.send_vectored_with_ancillary(&[IoSlice::new(&buffer[..len])], &mut forward)
+			.await
+	}
+
+	/// Receive a datagram and take ownership of its file descriptors, classified by type.
+	///
+	/// The payload is received into `buffer`, which limits the size of the payload, and returned as a new vector.
+	/// The type of each received file descriptor is determined with [`ancillary::fd_type()`].
+	///
+	/// If the ancillary data of the received datagram was truncated, an error wrapping [`ControlTruncated`] is returned.
+	/// If a file descriptor can not be classified, that error is returned.
+	/// In both cases, all received file descriptors are closed.
+	/// The size of the control buffer can be limited with [`Self::set_max_control_bytes()`].
+	///
+	/// Returns the payload and the file descriptors with their types, in the order they were received.
+	pub async fn recv_classified_fds(
+		&self,
+		buffer: &mut [u8],
+	) -> std::io::Result<(Vec<u8>, Vec<(OwnedFd, ancillary::FdType)>)> {
+		let capacity = ancillary::fds_space(ancillary::MAX_FDS_PER_MESSAGE).min(self.max_control_bytes());
+		let mut control = vec![0; capacity];
+		let mut received = SocketAncillary::new(&mut control);
+		let result = self
+			.recv_vectored_with_ancillary(&mut [IoSliceMut::new(buffer)], &mut received)
+			.await;
+
+		// Take ownership of the file descriptors even if receiving failed, so they are always closed.
+		let fds: Vec<OwnedFd> = received.take_fds().collect();
+		let len = result?;
+
+		if received.truncated() {
+			return Err(ControlTruncated { capacity }.into());
+		}
+
+		let fds = fds
+			.into_iter()
+			.map(|fd| {
+				let fd_type = ancillary::fd_type(fd.as_raw_fd())?;
+				Ok((fd, fd_type))
+			})
+			.collect::<std::io::Result<_>>()?;
+		Ok((buffer[..len].to_vec(), fds))
+	}
+
+	/// Send a datagram with a file descriptor, and close the local copy of the file descriptor if the send succeeds.
+	///
+	/// The kernel always duplicates file descriptors that are sent, so the local copy stays open after a normal send.
+	/// This function takes ownership of the file descriptor instead, to make the transfer explicit.
+	///
+	/// If the send fails, the returned error wraps a [`FdNotSent`] error that holds the file descriptor,
+	/// so it is not lost.
+	/// If the future is dropped before the send completes, the file descriptor is closed.
+	pub async fn send_and_close_fd(&self, buffer: &[u8], fd: OwnedFd) -> std::io::Result<usize> {
+		let mut ancillary = SocketAncillaryBuf::from_fds([fd.as_raw_fd()]);
+		match self
+			.send_vectored_with_ancillary(&[IoSlice::new(buffer)], &mut ancillary)
+			.await
+		{
+			Ok(len) => {
+				drop(fd);
+				Ok(len)
+			},
+			Err(error) => Err(FdNotSent { error, fd }.into()),
+		}
+	}
+
+	/// Send any number of file descriptors, split over as few datagrams as possible.
+	///
+	/// Linux accepts at most 253 file descriptors in a single datagram,
+	/// so the file descriptors are sent in chunks of 253, with one datagram per chunk.
+	/// Each datagram has a payload of a single byte: `1` if more datagrams follow, and `0` for the last datagram.
+	/// The receiver must expect multiple datagrams, and keep receiving until it gets a datagram with payload `0`.
+	/// If `fds` is empty, a single datagram without file descriptors is sent.
+	///
+	/// Returns the number of datagrams sent.
+	///
+	/// If sending a datagram fails, the error is returned, but the datagrams that were already sent are not recalled.
+	/// The caller remains the owner of the file descriptors, and is responsible for closing them.
+	pub async fn send_fds_chunked(&self, fds: &[RawFd]) -> std::io::Result<usize> {
+		let chunks = fds.len().div_ceil(ancillary::MAX_FDS_PER_MESSAGE).max(1);
+		for i in 0..chunks {
+			let start = i * ancillary::MAX_FDS_PER_MESSAGE;
+			let end = (start + ancillary::MAX_FDS_PER_MESSAGE).min(fds.len());
+			let more = [u8::from(i + 1 < chunks)];
+			let mut ancillary = SocketAncillaryBuf::from_fds(fds[start..end].iter().copied());
+			self.send_vectored_with_ancillary(&[IoSlice::new(&more)], &mut ancillary)
+				.await?;
+		}
+		Ok(chunks)
+	}
+
+	/// Receive a datagram and its file descriptors into caller-owned vectors.
+	///
+	/// Both vectors are cleared first, and their allocations are reused.
+	/// The size of the datagram is checked before it is received, and `payload` only grows if its capacity is too small.
+	/// Received file descriptors are appended to `fds`, which only allocates if it does not have enough capacity.
+	/// If the datagram has no file descriptors, `fds` is left empty.
+	/// Other control messages, like credentials received because of `SO_PASSCRED`, are ignored.
+	/// The control buffer for the file descriptors is kept on the stack.
+	///
+	/// Returns the number of bytes received and a flag that indicates if the datagram was truncated.
+	/// The flag is set if the ancillary data was truncated (`MSG_CTRUNC`), in which case some file descriptors may have been lost.
+	/// The control buffer is limited by [`Self::set_max_control_bytes()`].
+	/// The flag is also set if the payload was truncated (`MSG_TRUNC`),
+	/// which can only happen if another task received the checked datagram concurrently, and a larger datagram was received instead.
+	pub async fn recv_into(&self, payload: &mut Vec<u8>, fds: &mut Vec<OwnedFd>) -> std::io::Result<(usize, bool)> {
+		payload.clear();
+		fds.clear();
+
+		let (len, data_truncated, control_truncated) = self
+			.recv_with_stack_control(
+				|socket, ancillary| {
+					self.check_idle()?;
+					let size = sys::peek_size(socket)?;
+					Self::check_payload_size(socket, size, self.max_payload.load(Ordering::Relaxed))?;
+					payload.reserve(size);
+					let (len, truncated) =
+						sys::recv_msg_truncated_uninit(socket, payload.spare_capacity_mut(), ancillary)?;
+					// SAFETY: The kernel initialized the first `len` bytes of the spare capacity.
+					unsafe { payload.set_len(len) };
+					Ok((len, truncated))
+				},
+				|fd| fds.push(fd),
+			)
+			.await?;
+		Ok((len, data_truncated || control_truncated))
+	}
+
+	/// Receive a datagram and its file descriptors into a fixed array of slots, without allocating.
+	///
+	/// All slots are cleared first, closing any file descriptors they held.
+	/// Received file descriptors are stored in the slots in order.
+	/// If more file descriptors are received than there are slots, the excess file descriptors are closed.
+	/// The control buffer for the file descriptors is kept on the stack, like with [`Self::recv_into()`].
+	///
+	/// Returns the number of bytes received and the number of file descriptors received.
+	/// If the number of file descriptors is larger than `fds.len()`, some of them were closed.
+	///
+	/// If the ancillary data of the received datagram was truncated, an error wrapping [`ControlTruncated`] is returned,
+	/// since file descriptors may have been lost.
+	/// The file descriptors that were received are still stored in the slots.
+	/// The control buffer is limited by [`Self::set_max_control_bytes()`].
+	pub async fn recv_fds_into(
+		&self,
+		buffer: &mut [u8],
+		fds: &mut [Option<OwnedFd>],
+	) -> std::io::Result<(usize, usize)> {
+		fds.fill_with(|| None);
+
+		let mut count = 0;
+		let (len, _data_truncated, control_truncated) = self
+			.recv_with_stack_control(
+				|socket, ancillary| {
+					self.check_payload(socket)?;
+					sys::recv_msg_truncated(socket, &mut [IoSliceMut::new(buffer)], ancillary)
+				},
+				|fd| {
+					if let Some(slot) = fds.get_mut(count) {
+						*slot = Some(fd);
+					}
+					count += 1;
+				},
+			)
+			.await?;
+		if control_truncated {
+			return Err(ControlTruncated {
+				capacity: self.stack_control_capacity(),
+			}
+			.into());
+		}
+		Ok((len, count))
+	}
+
+	/// Get the size of the control buffer used by [`Self::recv_with_stack_control()`].
+	fn stack_control_capacity(&self) -> usize {
+		ancillary::fds_space(ancillary::MAX_FDS_PER_MESSAGE)
+			.min(self.max_control_bytes())
+			.min(RECV_INTO_CONTROL_WORDS * std::mem::size_of::<u64>())
+	}
+
+	/// Receive a datagram with a control buffer on the stack, passing each received file descriptor to `on_fd`.
+	///
+	/// The datagram is received by `recv`, which returns the number of bytes received and whether the data was truncated.
+	/// It is called again if the socket was not readable after all.
+	///
+	/// Returns the number of bytes received, and flags that indicate if the data and the ancillary data were truncated.
+	async fn recv_with_stack_control(
+		&self,
+		mut recv: impl FnMut(&FileDesc, &mut SocketAncillary) -> std::io::Result<(usize, bool)>,
+		on_fd: impl FnMut(OwnedFd),
+	) -> std::io::Result<(usize, bool, bool)> {
+		let mut control = [0u64; RECV_INTO_CONTROL_WORDS];
+		// SAFETY: Any byte pattern is a valid u64, and the slice covers exactly the array.
+		let control = unsafe {
+			std::slice::from_raw_parts_mut(control.as_mut_ptr().cast::<u8>(), std::mem::size_of_val(&control))
+		};
+		let mut received = SocketAncillary::new(&mut control[..self.stack_control_capacity()]);
+		let result = async {
+			loop {
+				let mut ready_guard = self.io.readable().await?;
+				match ready_guard.try_io(|inner| recv(inner.get_ref(), &mut received)) {
+					Ok(result) => return result.map(|(len, truncated)| (self.after_recv(len), truncated)),
+					Err(_would_block) => continue,
+				}
+			}
+		}
+		.await;
+		let result = self.with_label("recv", result);
+
+		// Take ownership of the file descriptors even if receiving failed, so they are always closed.
+		received.take_fds().for_each(on_fd);
+
+		let (len, data_truncated) = result?;
+		Ok((len, data_truncated, received.truncated()))
+	}
+
 	/// Shuts down the read, write, or both halves of this connection.
 	///
 	/// This function will cause all pending and future I/O calls on the
 	/// specified portions to immediately return with an appropriate value
 	/// (see the documentation of `Shutdown`).
+	///
+	/// Shutting down the write half signals the end of the connection to the peer without closing the socket:
+	/// further sends fail with [`std::io::ErrorKind::BrokenPipe`] and the peer receives `0` bytes,
+	/// but this socket can still receive the replies of the peer.
 	pub fn shutdown(&self, how: std::net::Shutdown) -> std::io::Result<()> {
 		sys::shutdown(self.io.get_ref(), how)
 	}
+
+	/// Shut down the write half of the connection, wait for the peer to receive all queued datagrams, and close the socket.
+	///
+	/// The write half of the connection is shut down first, and then this function waits until the send queue is empty (`SIOCOUTQ`),
+	/// which means that the peer has received all datagrams, or has closed the connection.
+	/// There is no notification when the send queue drains, so the queue is checked periodically.
+	///
+	/// If the queue did not drain before `deadline`, an error of kind [`std::io::ErrorKind::TimedOut`] is returned.
+	/// The socket is always closed, even if an error is returned.
+	///
+	/// This function is only available on Linux and Android.
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	pub async fn close_gracefully(self, deadline: std::time::Instant) -> std::io::Result<()> {
+		let deadline = tokio::time::Instant::from_std(deadline);
+		self.shutdown(std::net::Shutdown::Write)?;
+
+		let mut delay = Duration::from_millis(1);
+		loop {
+			if sys::send_queue_len(self.io.get_ref())? == 0 {
+				return Ok(());
+			}
+			let now = tokio::time::Instant::now();
+			if now >= deadline {
+				return Err(drain_timed_out());
+			}
+			tokio::time::sleep_until((now + delay).min(deadline)).await;
+			delay = (delay * 2).min(Duration::from_millis(100));
+		}
+	}
+}
+
+/// The error returned by [`UnixSeqpacket::close_gracefully()`] if the send queue did not drain in time.
+#[cfg(any(target_os = "android", target_os = "linux"))]
+fn drain_timed_out() -> std::io::Error {
+	std::io::Error::new(
+		std::io::ErrorKind::TimedOut,
+		"timed out waiting for the send queue to drain",
+	)
 }
 
 impl AsRawFd for UnixSeqpacket {
@@ -351,3 +1902,21 @@ impl IntoRawFd for UnixSeqpacket {
 		self.into_raw_fd()
 	}
 }
+
+impl FromRawFd for UnixSeqpacket {
+	/// Wrap a raw file descriptor as [`UnixSeqpacket`].
+	///
+	/// # Panics
+	/// This function panics if the file descriptor is not a `SOCK_SEQPACKET` socket
+	/// or if it can not be registered with the tokio runtime.
+	/// Use the inherent [`UnixSeqpacket::from_raw_fd()`] to handle these errors.
+	///
+	/// # Safety
+	/// See [`UnixSeqpacket::from_raw_fd()`].
+	unsafe fn from_raw_fd(fd: std::os::unix::io::RawFd) -> Self {
+		match UnixSeqpacket::from_raw_fd(fd) {
+			Ok(socket) => socket,
+			Err(e) => panic!("failed to adopt file descriptor {fd} as UnixSeqpacket: {e}"),
+		}
+	}
+}
@@ -0,0 +1,204 @@
+use std::io::{IoSlice, IoSliceMut};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use crate::ancillary::SocketAncillary;
+use crate::{ReuniteError, UnixSeqpacket};
+
+/// A handle to a shared [`UnixSeqpacket`] that can only be used to send data.
+///
+/// Create one with [`UnixSeqpacket::sender()`] or [`UnixSeqpacket::into_split()`].
+/// Cloning the handle is cheap: all clones refer to the same socket.
+///
+/// The handle does not give access to the receiving half of the socket:
+/// ```compile_fail
+/// # async fn foo(socket: std::sync::Arc<tokio_seqpacket::UnixSeqpacket>) {
+/// let sender = socket.sender();
+/// let mut buffer = [0u8; 128];
+/// sender.recv(&mut buffer).await;
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Sender {
+	inner: Arc<UnixSeqpacket>,
+}
+
+/// A handle to a shared [`UnixSeqpacket`] that can only be used to receive data.
+///
+/// Create one with [`UnixSeqpacket::receiver()`] or [`UnixSeqpacket::into_split()`].
+/// Cloning the handle is cheap: all clones refer to the same socket.
+///
+/// The handle does not give access to the sending half of the socket:
+/// ```compile_fail
+/// # async fn foo(socket: std::sync::Arc<tokio_seqpacket::UnixSeqpacket>) {
+/// let receiver = socket.receiver();
+/// receiver.send(b"Hello!").await;
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Receiver {
+	inner: Arc<UnixSeqpacket>,
+}
+
+impl UnixSeqpacket {
+	/// Create a handle to this socket that can only be used to send data.
+	///
+	/// This can be used to give a subsystem the ability to send data without allowing it to receive data from the socket.
+	pub fn sender(self: &Arc<Self>) -> Sender {
+		Sender { inner: self.clone() }
+	}
+
+	/// Create a handle to this socket that can only be used to receive data.
+	///
+	/// This can be used to give a subsystem the ability to receive data without allowing it to send data on the socket.
+	pub fn receiver(self: &Arc<Self>) -> Receiver {
+		Receiver { inner: self.clone() }
+	}
+
+	/// Split the socket into a handle that can only send and a handle that can only receive.
+	///
+	/// The handles can be moved to different tasks.
+	/// The socket is closed when all handles are dropped.
+	/// Use [`Sender::reunite()`] or [`Receiver::reunite()`] to get the socket back.
+	///
+	/// There is no borrowing variant of this function:
+	/// all I/O functions take `&self`, so a shared reference to the socket can already be used to send and receive at the same time.
+	pub fn into_split(self) -> (Sender, Receiver) {
+		let inner = Arc::new(self);
+		(Sender { inner: inner.clone() }, Receiver { inner })
+	}
+}
+
+/// Combine a sender and receiver back into a socket.
+fn reunite(sender: Sender, receiver: Receiver) -> Result<UnixSeqpacket, ReuniteError> {
+	if !Arc::ptr_eq(&sender.inner, &receiver.inner) {
+		return Err(ReuniteError { sender, receiver });
+	}
+	drop(receiver);
+	Arc::try_unwrap(sender.inner).map_err(|inner| ReuniteError {
+		sender: Sender { inner: inner.clone() },
+		receiver: Receiver { inner },
+	})
+}
+
+impl Sender {
+	/// Combine this sender with a receiver for the same socket to get the socket back.
+	///
+	/// This fails if the receiver belongs to a different socket,
+	/// or if other handles to the socket still exist.
+	/// The returned error holds both handles, so they are not lost.
+	pub fn reunite(self, receiver: Receiver) -> Result<UnixSeqpacket, ReuniteError> {
+		reunite(self, receiver)
+	}
+
+	/// Try to send data on the socket to the connected peer without blocking.
+	///
+	/// See [`UnixSeqpacket::poll_send`].
+	pub fn poll_send(&self, cx: &mut Context, buffer: &[u8]) -> Poll<std::io::Result<usize>> {
+		self.inner.poll_send(cx, buffer)
+	}
+
+	/// Try to send data on the socket to the connected peer without blocking.
+	///
+	/// See [`UnixSeqpacket::poll_send_vectored`].
+	pub fn poll_send_vectored(&self, cx: &mut Context, buffer: &[IoSlice]) -> Poll<std::io::Result<usize>> {
+		self.inner.poll_send_vectored(cx, buffer)
+	}
+
+	/// Try to send data with ancillary data on the socket to the connected peer without blocking.
+	///
+	/// See [`UnixSeqpacket::poll_send_vectored_with_ancillary`].
+	pub fn poll_send_vectored_with_ancillary(
+		&self,
+		cx: &mut Context,
+		buffer: &[IoSlice],
+		ancillary: &mut SocketAncillary,
+	) -> Poll<std::io::Result<usize>> {
+		self.inner.poll_send_vectored_with_ancillary(cx, buffer, ancillary)
+	}
+
+	/// Send data on the socket to the connected peer.
+	///
+	/// See [`UnixSeqpacket::send`].
+	pub async fn send(&self, buffer: &[u8]) -> std::io::Result<usize> {
+		self.inner.send(buffer).await
+	}
+
+	/// Send data on the socket to the connected peer.
+	///
+	/// See [`UnixSeqpacket::send_vectored`].
+	pub async fn send_vectored(&self, buffer: &[IoSlice<'_>]) -> std::io::Result<usize> {
+		self.inner.send_vectored(buffer).await
+	}
+
+	/// Send data with ancillary data on the socket to the connected peer.
+	///
+	/// See [`UnixSeqpacket::send_vectored_with_ancillary`].
+	pub async fn send_vectored_with_ancillary(
+		&self,
+		buffer: &[IoSlice<'_>],
+		ancillary: &mut SocketAncillary<'_>,
+	) -> std::io::Result<usize> {
+		self.inner.send_vectored_with_ancillary(buffer, ancillary).await
+	}
+}
+
+impl Receiver {
+	/// Combine this receiver with a sender for the same socket to get the socket back.
+	///
+	/// See [`Sender::reunite()`].
+	pub fn reunite(self, sender: Sender) -> Result<UnixSeqpacket, ReuniteError> {
+		reunite(sender, self)
+	}
+
+	/// Try to receive data on the socket from the connected peer without blocking.
+	///
+	/// See [`UnixSeqpacket::poll_recv`].
+	pub fn poll_recv(&self, cx: &mut Context, buffer: &mut [u8]) -> Poll<std::io::Result<usize>> {
+		self.inner.poll_recv(cx, buffer)
+	}
+
+	/// Try to receive data on the socket from the connected peer without blocking.
+	///
+	/// See [`UnixSeqpacket::poll_recv_vectored`].
+	pub fn poll_recv_vectored(&self, cx: &mut Context, buffer: &mut [IoSliceMut]) -> Poll<std::io::Result<usize>> {
+		self.inner.poll_recv_vectored(cx, buffer)
+	}
+
+	/// Try to receive data with ancillary data on the socket from the connected peer without blocking.
+	///
+	/// See [`UnixSeqpacket::poll_recv_vectored_with_ancillary`].
+	pub fn poll_recv_vectored_with_ancillary(
+		&self,
+		cx: &mut Context,
+		buffer: &mut [IoSliceMut],
+		ancillary: &mut SocketAncillary,
+	) -> Poll<std::io::Result<usize>> {
+		self.inner.poll_recv_vectored_with_ancillary(cx, buffer, ancillary)
+	}
+
+	/// Receive data on the socket from the connected peer.
+	///
+	/// See [`UnixSeqpacket::recv`].
+	pub async fn recv(&self, buffer: &mut [u8]) -> std::io::Result<usize> {
+		self.inner.recv(buffer).await
+	}
+
+	/// Receive data on the socket from the connected peer.
+	///
+	/// See [`UnixSeqpacket::recv_vectored`].
+	pub async fn recv_vectored(&self, buffer: &mut [IoSliceMut<'_>]) -> std::io::Result<usize> {
+		self.inner.recv_vectored(buffer).await
+	}
+
+	/// Receive data with ancillary data on the socket from the connected peer.
+	///
+	/// See [`UnixSeqpacket::recv_vectored_with_ancillary`].
+	pub async fn recv_vectored_with_ancillary(
+		&self,
+		buffer: &mut [IoSliceMut<'_>],
+		ancillary: &mut SocketAncillary<'_>,
+	) -> std::io::Result<usize> {
+		self.inner.recv_vectored_with_ancillary(buffer, ancillary).await
+	}
+}
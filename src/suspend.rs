@@ -0,0 +1,75 @@
+use filedesc::FileDesc;
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, RawFd};
+
+use crate::socket::Settings;
+use crate::{sys, UnixSeqpacket};
+
+/// A seqpacket socket that is temporarily not registered with the tokio runtime.
+///
+/// Create one with [`UnixSeqpacket::suspend()`].
+/// While suspended, the file descriptor can be used by blocking code,
+/// for example by passing it to a C library.
+/// Use [`Self::set_nonblocking()`] to put the socket in blocking mode if that code requires it.
+///
+/// Use [`Self::resume()`] to register the socket with the tokio runtime again.
+/// Dropping a suspended socket closes it.
+pub struct SuspendedSocket {
+	pub(crate) fd: FileDesc,
+	pub(crate) settings: Settings,
+}
+
+impl std::fmt::Debug for SuspendedSocket {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		f.debug_struct("SuspendedSocket")
+			.field("fd", &self.fd.as_raw_fd())
+			.finish()
+	}
+}
+
+impl SuspendedSocket {
+	/// Put the socket in blocking or non-blocking mode.
+	///
+	/// Regardless of the mode set with this function, the socket is put back in non-blocking mode by [`Self::resume()`].
+	pub fn set_nonblocking(&self, nonblocking: bool) -> std::io::Result<()> {
+		sys::set_nonblocking(&self.fd, nonblocking)
+	}
+
+	/// Send data on the socket to the connected peer.
+	///
+	/// If the socket is in blocking mode, this blocks the current thread until the data can be sent.
+	/// Otherwise, an error of kind [`std::io::ErrorKind::WouldBlock`] is returned if the data can not be sent right away.
+	pub fn send(&self, buffer: &[u8]) -> std::io::Result<usize> {
+		sys::send(&self.fd, buffer)
+	}
+
+	/// Receive data on the socket from the connected peer.
+	///
+	/// If the socket is in blocking mode, this blocks the current thread until a datagram is received.
+	/// Otherwise, an error of kind [`std::io::ErrorKind::WouldBlock`] is returned if no datagram is available right away.
+	pub fn recv(&self, buffer: &mut [u8]) -> std::io::Result<usize> {
+		sys::recv(&self.fd, buffer)
+	}
+
+	/// Put the socket back in non-blocking mode and register it with the tokio runtime again.
+	///
+	/// Settings that are not stored by the kernel, like the receive watermark, are preserved.
+	///
+	/// Registration of the file descriptor with the tokio runtime may fail.
+	/// In that case, the socket is closed and an error is returned.
+	pub fn resume(self) -> std::io::Result<UnixSeqpacket> {
+		sys::set_nonblocking(&self.fd, true)?;
+		UnixSeqpacket::with_settings(self.fd, self.settings)
+	}
+}
+
+impl AsFd for SuspendedSocket {
+	fn as_fd(&self) -> BorrowedFd<'_> {
+		self.fd.as_fd()
+	}
+}
+
+impl AsRawFd for SuspendedSocket {
+	fn as_raw_fd(&self) -> RawFd {
+		self.fd.as_raw_fd()
+	}
+}
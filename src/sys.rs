@@ -1,15 +1,26 @@
 use filedesc::FileDesc;
 use std::convert::TryInto;
 use std::io::{IoSlice, IoSliceMut};
+use std::mem::MaybeUninit;
 use std::os::raw::{c_int, c_void};
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
 
 use crate::ancillary::SocketAncillary;
+#[cfg(any(target_os = "android", target_os = "linux"))]
+use crate::ancillary::{AncillaryData, SocketCred};
+use crate::SocketAddr;
 
 const SOCKET_FLAGS: c_int = libc::SOCK_CLOEXEC | libc::SOCK_NONBLOCK;
 const SOCKET_TYPE: c_int = libc::SOCK_SEQPACKET | SOCKET_FLAGS;
 const SEND_MSG_DEFAULT_FLAGS: c_int = libc::MSG_NOSIGNAL;
 
+/// Flags passed to every `recvmsg()` call.
+///
+/// Where supported, `MSG_CMSG_CLOEXEC` makes the kernel set the `close-on-exec` flag on received file descriptors atomically,
+/// so they can not leak into a child process that is spawned concurrently.
+/// On Illumos and Solaris, the flag is set in a separate step after receiving the message.
 #[cfg(any(target_os = "illumos", target_os = "solaris"))]
 const RECV_MSG_DEFAULT_FLAGS: c_int = libc::MSG_NOSIGNAL;
 #[cfg(not(any(target_os = "illumos", target_os = "solaris")))]
@@ -42,6 +53,24 @@ pub fn connect<P: AsRef<Path>>(socket: &FileDesc, address: P) -> std::io::Result
 	}
 }
 
+/// Connect a socket to a socket address, which may be a pathname or an abstract address.
+pub fn connect_addr(socket: &FileDesc, address: &SocketAddr) -> std::io::Result<()> {
+	if address.is_unnamed() {
+		return Err(std::io::Error::new(
+			std::io::ErrorKind::InvalidInput,
+			"can not connect to an unnamed address",
+		));
+	}
+	unsafe {
+		check(libc::connect(
+			socket.as_raw_fd(),
+			&address.address as *const _ as *const libc::sockaddr,
+			address.len,
+		))?;
+		Ok(())
+	}
+}
+
 pub fn bind<P: AsRef<Path>>(socket: &FileDesc, address: P) -> std::io::Result<()> {
 	let (address, addr_len) = path_to_sockaddr(address.as_ref())?;
 	unsafe {
@@ -54,6 +83,19 @@ pub fn bind<P: AsRef<Path>>(socket: &FileDesc, address: P) -> std::io::Result<()
 	}
 }
 
+/// Bind a socket to a socket address, which may be a pathname or an abstract address.
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub fn bind_addr(socket: &FileDesc, address: &SocketAddr) -> std::io::Result<()> {
+	unsafe {
+		check(libc::bind(
+			socket.as_raw_fd(),
+			&address.address as *const _ as *const libc::sockaddr,
+			address.len,
+		))?;
+		Ok(())
+	}
+}
+
 pub fn listen(socket: &FileDesc, backlog: c_int) -> std::io::Result<()> {
 	unsafe {
 		check(libc::listen(socket.as_raw_fd(), backlog))?;
@@ -75,6 +117,32 @@ pub fn accept(socket: &FileDesc) -> std::io::Result<FileDesc> {
 	}
 }
 
+/// Accept up to `max` pending connections together with the address of each peer.
+///
+/// An error is only returned if no connection could be accepted at all.
+/// Otherwise, the connections accepted so far are returned and the error is left for the next call.
+pub fn accept_many(socket: &FileDesc, max: usize) -> std::io::Result<Vec<(FileDesc, SocketAddr)>> {
+	let mut accepted = Vec::new();
+	while accepted.len() < max {
+		let mut address = SocketAddr::new_unnamed();
+		address.len = core::mem::size_of::<libc::sockaddr_un>() as libc::socklen_t;
+		let fd = unsafe {
+			check(libc::accept4(
+				socket.as_raw_fd(),
+				&mut address.address as *mut _ as *mut _,
+				&mut address.len,
+				SOCKET_FLAGS,
+			))
+		};
+		match fd {
+			Ok(fd) => accepted.push((unsafe { FileDesc::from_raw_fd(fd) }, address)),
+			Err(e) if accepted.is_empty() => return Err(e),
+			Err(_) => break,
+		}
+	}
+	Ok(accepted)
+}
+
 pub fn shutdown(socket: &FileDesc, how: std::net::Shutdown) -> std::io::Result<()> {
 	let how = match how {
 		std::net::Shutdown::Read => libc::SHUT_RD,
@@ -87,6 +155,59 @@ pub fn shutdown(socket: &FileDesc, how: std::net::Shutdown) -> std::io::Result<(
 	}
 }
 
+pub fn set_nonblocking(socket: &FileDesc, nonblocking: bool) -> std::io::Result<()> {
+	unsafe {
+		let flags = check(libc::fcntl(socket.as_raw_fd(), libc::F_GETFL))?;
+		let new_flags = if nonblocking {
+			flags | libc::O_NONBLOCK
+		} else {
+			flags & !libc::O_NONBLOCK
+		};
+		if new_flags != flags {
+			check(libc::fcntl(socket.as_raw_fd(), libc::F_SETFL, new_flags))?;
+		}
+		Ok(())
+	}
+}
+
+/// Check if the peer has shut down the connection, without blocking.
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub fn peer_hung_up(socket: &FileDesc) -> std::io::Result<bool> {
+	let mut poll_fd = libc::pollfd {
+		fd: socket.as_raw_fd(),
+		events: libc::POLLRDHUP,
+		revents: 0,
+	};
+	unsafe {
+		check(libc::poll(&mut poll_fd, 1, 0))?;
+	}
+	Ok(poll_fd.revents & (libc::POLLRDHUP | libc::POLLHUP) != 0)
+}
+
+/// Get the inode number of a file descriptor.
+pub fn get_inode(socket: &FileDesc) -> std::io::Result<u64> {
+	unsafe {
+		let mut stat: libc::stat = std::mem::zeroed();
+		check(libc::fstat(socket.as_raw_fd(), &mut stat))?;
+		// This is not a no-op on all platforms.
+		#[allow(clippy::unnecessary_cast)]
+		Ok(stat.st_ino as u64)
+	}
+}
+
+/// Check if the socket is readable right now, without blocking.
+pub fn is_readable(socket: &FileDesc) -> std::io::Result<bool> {
+	let mut poll_fd = libc::pollfd {
+		fd: socket.as_raw_fd(),
+		events: libc::POLLIN,
+		revents: 0,
+	};
+	unsafe {
+		check(libc::poll(&mut poll_fd, 1, 0))?;
+	}
+	Ok(poll_fd.revents != 0)
+}
+
 pub fn take_socket_error(socket: &FileDesc) -> std::io::Result<Option<std::io::Error>> {
 	unsafe {
 		let mut error: c_int = 0;
@@ -106,6 +227,227 @@ pub fn take_socket_error(socket: &FileDesc) -> std::io::Result<Option<std::io::E
 	}
 }
 
+/// Return an error if the file descriptor is not a `SOCK_SEQPACKET` socket.
+pub fn ensure_seqpacket(socket: &FileDesc) -> std::io::Result<()> {
+	let kind: c_int = get_socket_option(socket, libc::SOL_SOCKET, libc::SO_TYPE)?;
+	if kind == libc::SOCK_SEQPACKET {
+		Ok(())
+	} else {
+		Err(std::io::Error::new(
+			std::io::ErrorKind::InvalidInput,
+			"file descriptor is not a SOCK_SEQPACKET socket",
+		))
+	}
+}
+
+/// Get the value of a socket option.
+fn get_socket_option<T: Copy>(socket: &FileDesc, level: c_int, name: c_int) -> std::io::Result<T> {
+	unsafe {
+		let mut value: T = core::mem::zeroed();
+		let mut len = core::mem::size_of::<T>() as libc::socklen_t;
+		check(libc::getsockopt(
+			socket.as_raw_fd(),
+			level,
+			name,
+			&mut value as *mut T as *mut c_void,
+			&mut len,
+		))?;
+		Ok(value)
+	}
+}
+
+/// Set the value of a socket option.
+fn set_socket_option<T>(socket: &FileDesc, level: c_int, name: c_int, value: &T) -> std::io::Result<()> {
+	unsafe {
+		check(libc::setsockopt(
+			socket.as_raw_fd(),
+			level,
+			name,
+			value as *const T as *const c_void,
+			core::mem::size_of::<T>() as libc::socklen_t,
+		))?;
+		Ok(())
+	}
+}
+
+pub fn get_send_buffer_size(socket: &FileDesc) -> std::io::Result<usize> {
+	let size: c_int = get_socket_option(socket, libc::SOL_SOCKET, libc::SO_SNDBUF)?;
+	Ok(size as usize)
+}
+
+pub fn set_send_buffer_size(socket: &FileDesc, size: usize) -> std::io::Result<()> {
+	let size: c_int = size.try_into().map_err(|_| std::io::ErrorKind::InvalidInput)?;
+	set_socket_option(socket, libc::SOL_SOCKET, libc::SO_SNDBUF, &size)
+}
+
+pub fn get_recv_buffer_size(socket: &FileDesc) -> std::io::Result<usize> {
+	let size: c_int = get_socket_option(socket, libc::SOL_SOCKET, libc::SO_RCVBUF)?;
+	Ok(size as usize)
+}
+
+pub fn set_recv_buffer_size(socket: &FileDesc, size: usize) -> std::io::Result<()> {
+	let size: c_int = size.try_into().map_err(|_| std::io::ErrorKind::InvalidInput)?;
+	set_socket_option(socket, libc::SOL_SOCKET, libc::SO_RCVBUF, &size)
+}
+
+/// Set a buffer size option that requires `CAP_NET_ADMIN`, with a clear error if the process lacks it.
+#[cfg(any(target_os = "android", target_os = "linux"))]
+fn set_forced_buffer_size(socket: &FileDesc, name: c_int, option: &str, size: usize) -> std::io::Result<()> {
+	let size: c_int = size.try_into().map_err(|_| std::io::ErrorKind::InvalidInput)?;
+	set_socket_option(socket, libc::SOL_SOCKET, name, &size).map_err(|e| requires_net_admin(e, option))
+}
+
+/// Replace an `EPERM` error with an error that explains that `option` requires `CAP_NET_ADMIN`.
+#[cfg(any(target_os = "android", target_os = "linux"))]
+fn requires_net_admin(error: std::io::Error, option: &str) -> std::io::Error {
+	if error.raw_os_error() == Some(libc::EPERM) {
+		std::io::Error::new(
+			std::io::ErrorKind::PermissionDenied,
+			format!("setting {} requires the CAP_NET_ADMIN capability", option),
+		)
+	} else {
+		error
+	}
+}
+
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub fn set_send_buffer_size_force(socket: &FileDesc, size: usize) -> std::io::Result<()> {
+	set_forced_buffer_size(socket, libc::SO_SNDBUFFORCE, "SO_SNDBUFFORCE", size)
+}
+
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub fn set_recv_buffer_size_force(socket: &FileDesc, size: usize) -> std::io::Result<()> {
+	set_forced_buffer_size(socket, libc::SO_RCVBUFFORCE, "SO_RCVBUFFORCE", size)
+}
+
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub fn get_priority(socket: &FileDesc) -> std::io::Result<u32> {
+	let priority: c_int = get_socket_option(socket, libc::SOL_SOCKET, libc::SO_PRIORITY)?;
+	Ok(priority as u32)
+}
+
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub fn set_priority(socket: &FileDesc, priority: u32) -> std::io::Result<()> {
+	let priority: c_int = priority.try_into().map_err(|_| std::io::ErrorKind::InvalidInput)?;
+	set_socket_option(socket, libc::SOL_SOCKET, libc::SO_PRIORITY, &priority)
+		.map_err(|e| requires_net_admin(e, "SO_PRIORITY above 6"))
+}
+
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub fn get_passcred(socket: &FileDesc) -> std::io::Result<bool> {
+	let enabled: c_int = get_socket_option(socket, libc::SOL_SOCKET, libc::SO_PASSCRED)?;
+	Ok(enabled != 0)
+}
+
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub fn set_passcred(socket: &FileDesc, enable: bool) -> std::io::Result<()> {
+	set_socket_option(socket, libc::SOL_SOCKET, libc::SO_PASSCRED, &c_int::from(enable))
+}
+
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub fn get_timestampns(socket: &FileDesc) -> std::io::Result<bool> {
+	let enabled: c_int = get_socket_option(socket, libc::SOL_SOCKET, libc::SO_TIMESTAMPNS)?;
+	Ok(enabled != 0)
+}
+
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub fn set_timestampns(socket: &FileDesc, enable: bool) -> std::io::Result<()> {
+	set_socket_option(socket, libc::SOL_SOCKET, libc::SO_TIMESTAMPNS, &c_int::from(enable))
+}
+
+fn get_timeout(socket: &FileDesc, name: c_int) -> std::io::Result<Option<Duration>> {
+	let timeout: libc::timeval = get_socket_option(socket, libc::SOL_SOCKET, name)?;
+	if timeout.tv_sec == 0 && timeout.tv_usec == 0 {
+		Ok(None)
+	} else {
+		Ok(Some(
+			Duration::from_secs(timeout.tv_sec as u64) + Duration::from_micros(timeout.tv_usec as u64),
+		))
+	}
+}
+
+fn set_timeout(socket: &FileDesc, name: c_int, timeout: Option<Duration>) -> std::io::Result<()> {
+	let timeout = match timeout {
+		None => libc::timeval { tv_sec: 0, tv_usec: 0 },
+		Some(timeout) if timeout.is_zero() => {
+			return Err(std::io::Error::new(
+				std::io::ErrorKind::InvalidInput,
+				"cannot set a zero duration timeout",
+			));
+		},
+		Some(timeout) => libc::timeval {
+			tv_sec: timeout.as_secs().try_into().unwrap_or(libc::time_t::MAX),
+			// Round up, so that a timeout of less than one microsecond does not disable the timeout.
+			tv_usec: timeout.subsec_micros().max(u32::from(timeout.as_secs() == 0)) as _,
+		},
+	};
+	set_socket_option(socket, libc::SOL_SOCKET, name, &timeout)
+}
+
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub fn get_peek_offset(socket: &FileDesc) -> std::io::Result<Option<usize>> {
+	let offset: c_int = get_socket_option(socket, libc::SOL_SOCKET, libc::SO_PEEK_OFF)?;
+	Ok(usize::try_from(offset).ok())
+}
+
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub fn set_peek_offset(socket: &FileDesc, offset: Option<usize>) -> std::io::Result<()> {
+	let offset: c_int = match offset {
+		Some(offset) => offset.try_into().map_err(|_| std::io::ErrorKind::InvalidInput)?,
+		None => -1,
+	};
+	set_socket_option(socket, libc::SOL_SOCKET, libc::SO_PEEK_OFF, &offset)
+}
+
+/// Get the sizes of the datagrams in the receive queue, without removing them.
+///
+/// This peeks at every datagram with `MSG_PEEK | MSG_TRUNC`, using `SO_PEEK_OFF` to skip the datagrams that were already seen.
+/// The peek offset is restored afterwards.
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub fn queued_datagram_sizes(socket: &FileDesc, max: usize) -> std::io::Result<Vec<usize>> {
+	let total = recv_queue_len(socket)?;
+	let previous = get_peek_offset(socket)?;
+
+	let mut sizes = Vec::new();
+	let mut offset = 0;
+	let result = (|| {
+		while sizes.len() < max && offset < total {
+			set_peek_offset(socket, Some(offset))?;
+			let size = match peek_size(socket) {
+				Ok(size) => size,
+				Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => break,
+				Err(e) => return Err(e),
+			};
+			if size == 0 {
+				// This is an empty datagram, and the offset can not move past it.
+				break;
+			}
+			sizes.push(size);
+			offset += size;
+		}
+		Ok(())
+	})();
+
+	set_peek_offset(socket, previous)?;
+	result.map(|()| sizes)
+}
+
+pub fn get_read_timeout(socket: &FileDesc) -> std::io::Result<Option<Duration>> {
+	get_timeout(socket, libc::SO_RCVTIMEO)
+}
+
+pub fn set_read_timeout(socket: &FileDesc, timeout: Option<Duration>) -> std::io::Result<()> {
+	set_timeout(socket, libc::SO_RCVTIMEO, timeout)
+}
+
+pub fn get_write_timeout(socket: &FileDesc) -> std::io::Result<Option<Duration>> {
+	get_timeout(socket, libc::SO_SNDTIMEO)
+}
+
+pub fn set_write_timeout(socket: &FileDesc, timeout: Option<Duration>) -> std::io::Result<()> {
+	set_timeout(socket, libc::SO_SNDTIMEO, timeout)
+}
+
 pub fn get_local_address(socket: &FileDesc) -> std::io::Result<PathBuf> {
 	unsafe {
 		let mut addr: libc::sockaddr_un = core::mem::zeroed();
@@ -119,20 +461,110 @@ pub fn get_local_address(socket: &FileDesc) -> std::io::Result<PathBuf> {
 	}
 }
 
-pub fn send(socket: &FileDesc, buffer: &[u8]) -> std::io::Result<usize> {
+/// Get the local address of a socket, which may be unnamed.
+pub fn get_local_socket_addr(socket: &FileDesc) -> std::io::Result<SocketAddr> {
+	let mut address = SocketAddr::new_unnamed();
+	address.len = core::mem::size_of::<libc::sockaddr_un>() as libc::socklen_t;
 	unsafe {
-		check_size(libc::send(
+		check(libc::getsockname(
 			socket.as_raw_fd(),
-			buffer.as_ptr() as *const c_void,
-			buffer.len(),
-			SEND_MSG_DEFAULT_FLAGS,
-		))
+			&mut address.address as *mut _ as *mut _,
+			&mut address.len,
+		))?;
 	}
+	Ok(address)
 }
 
-pub fn send_msg(socket: &FileDesc, buffer: &[IoSlice], ancillary: &mut SocketAncillary) -> std::io::Result<usize> {
-	ancillary.truncated = false;
+/// Get the address of the peer of a socket, which may be unnamed.
+pub fn get_peer_socket_addr(socket: &FileDesc) -> std::io::Result<SocketAddr> {
+	let mut address = SocketAddr::new_unnamed();
+	address.len = core::mem::size_of::<libc::sockaddr_un>() as libc::socklen_t;
+	unsafe {
+		check(libc::getpeername(
+			socket.as_raw_fd(),
+			&mut address.address as *mut _ as *mut _,
+			&mut address.len,
+		))?;
+	}
+	Ok(address)
+}
+
+/// Get the number of bytes in the send queue of the socket, including kernel bookkeeping overhead.
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub fn send_queue_len(socket: &FileDesc) -> std::io::Result<usize> {
+	unsafe {
+		let mut len: c_int = 0;
+		// SIOCOUTQ has the same value as TIOCOUTQ.
+		check(libc::ioctl(socket.as_raw_fd(), libc::TIOCOUTQ, &mut len))?;
+		Ok(len as usize)
+	}
+}
+
+pub fn recv_queue_len(socket: &FileDesc) -> std::io::Result<usize> {
+	unsafe {
+		let mut len: c_int = 0;
+		check(libc::ioctl(socket.as_raw_fd(), libc::FIONREAD, &mut len))?;
+		Ok(len as usize)
+	}
+}
+
+/// Get the size of the next datagram in the receive queue without removing it.
+///
+/// On platforms where the size of the next datagram can not be queried directly,
+/// this returns the total size of the receive queue, which is an upper bound.
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub fn peek_size(socket: &FileDesc) -> std::io::Result<usize> {
+	unsafe {
+		retry_interrupted(|| {
+			check_size(libc::recv(
+				socket.as_raw_fd(),
+				std::ptr::null_mut(),
+				0,
+				RECV_MSG_DEFAULT_FLAGS | libc::MSG_PEEK | libc::MSG_TRUNC,
+			))
+		})
+	}
+}
+
+/// Get the size of the next datagram in the receive queue without removing it.
+///
+/// On platforms where the size of the next datagram can not be queried directly,
+/// this returns the total size of the receive queue, which is an upper bound.
+#[cfg(not(any(target_os = "android", target_os = "linux")))]
+pub fn peek_size(socket: &FileDesc) -> std::io::Result<usize> {
+	let len = recv_queue_len(socket)?;
+	if len == 0 {
+		// Let the caller wait for the socket to become readable again.
+		// This may also be an empty datagram, but then we still report a size of zero when the real receive succeeds.
+		unsafe {
+			retry_interrupted(|| {
+				check_size(libc::recv(
+					socket.as_raw_fd(),
+					std::ptr::null_mut(),
+					0,
+					RECV_MSG_DEFAULT_FLAGS | libc::MSG_PEEK,
+				))
+			})
+		}
+	} else {
+		Ok(len)
+	}
+}
 
+pub fn send(socket: &FileDesc, buffer: &[u8]) -> std::io::Result<usize> {
+	unsafe {
+		retry_interrupted(|| {
+			check_size(libc::send(
+				socket.as_raw_fd(),
+				buffer.as_ptr() as *const c_void,
+				buffer.len(),
+				SEND_MSG_DEFAULT_FLAGS,
+			))
+		})
+	}
+}
+
+pub fn send_msg(socket: &FileDesc, buffer: &[IoSlice], ancillary: &mut SocketAncillary) -> std::io::Result<usize> {
 	let control_data = match ancillary.len() {
 		0 => std::ptr::null_mut(),
 		_ => ancillary.buffer.as_mut_ptr() as *mut std::os::raw::c_void,
@@ -141,6 +573,7 @@ pub fn send_msg(socket: &FileDesc, buffer: &[IoSlice], ancillary: &mut SocketAnc
 	let mut header: libc::msghdr = unsafe { std::mem::zeroed() };
 	header.msg_name = std::ptr::null_mut();
 	header.msg_namelen = 0;
+	check_iov_len(buffer.len())?;
 	header.msg_iov = buffer.as_ptr() as *mut libc::iovec;
 	// This is not a no-op on all platforms.
 	#[allow(clippy::useless_conversion)]
@@ -159,24 +592,72 @@ pub fn send_msg(socket: &FileDesc, buffer: &[IoSlice], ancillary: &mut SocketAnc
 	}
 
 	unsafe {
-		check_size(libc::sendmsg(
-			socket.as_raw_fd(),
-			&header as *const _,
-			SEND_MSG_DEFAULT_FLAGS,
-		))
+		retry_interrupted(|| {
+			check_size(libc::sendmsg(
+				socket.as_raw_fd(),
+				&header as *const _,
+				SEND_MSG_DEFAULT_FLAGS,
+			))
+		})
 	}
 }
 
-pub fn recv(socket: &FileDesc, buffer: &mut [u8]) -> std::io::Result<usize> {
+/// Send multiple datagrams with a single `sendmmsg()` call.
+///
+/// Returns the number of datagrams that were sent.
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub fn send_many(socket: &FileDesc, datagrams: &[Vec<u8>]) -> std::io::Result<usize> {
+	let mut iovecs: Vec<libc::iovec> = datagrams
+		.iter()
+		.map(|datagram| libc::iovec {
+			iov_base: datagram.as_ptr() as *mut c_void,
+			iov_len: datagram.len(),
+		})
+		.collect();
+	let mut headers: Vec<libc::mmsghdr> = iovecs
+		.iter_mut()
+		.map(|iovec| {
+			let mut header: libc::mmsghdr = unsafe { std::mem::zeroed() };
+			header.msg_hdr.msg_iov = iovec;
+			header.msg_hdr.msg_iovlen = 1;
+			header
+		})
+		.collect();
+
+	let vlen = headers.len().try_into().map_err(|_| std::io::ErrorKind::InvalidInput)?;
 	unsafe {
-		let read = check_size(libc::recv(
-			socket.as_raw_fd(),
-			buffer.as_mut_ptr() as *mut c_void,
-			buffer.len(),
-			RECV_MSG_DEFAULT_FLAGS,
-		))?;
-		Ok(read)
+		let count = retry_interrupted(|| {
+			check(libc::sendmmsg(
+				socket.as_raw_fd(),
+				headers.as_mut_ptr(),
+				vlen,
+				SEND_MSG_DEFAULT_FLAGS,
+			))
+		})?;
+		Ok(count as usize)
+	}
+}
+
+/// Send multiple datagrams one by one.
+///
+/// Returns the number of datagrams that were sent.
+#[cfg(not(any(target_os = "android", target_os = "linux")))]
+pub fn send_many(socket: &FileDesc, datagrams: &[Vec<u8>]) -> std::io::Result<usize> {
+	for (i, datagram) in datagrams.iter().enumerate() {
+		if let Err(e) = send(socket, datagram) {
+			return if i == 0 { Err(e) } else { Ok(i) };
+		}
 	}
+	Ok(datagrams.len())
+}
+
+pub fn recv(socket: &FileDesc, buffer: &mut [u8]) -> std::io::Result<usize> {
+	// Use `recvmsg()` so that we can see if the message was truncated.
+	recv_msg(
+		socket,
+		&mut [IoSliceMut::new(buffer)],
+		&mut SocketAncillary::new(&mut []),
+	)
 }
 
 pub fn recv_msg(
@@ -184,15 +665,107 @@ pub fn recv_msg(
 	buffer: &mut [IoSliceMut],
 	ancillary: &mut SocketAncillary,
 ) -> std::io::Result<usize> {
+	recv_msg_impl(socket, buffer, ancillary, None, 0).map(|(size, _flags)| size)
+}
+
+/// Receive a message without removing it from the receive queue.
+pub fn peek_msg(
+	socket: &FileDesc,
+	buffer: &mut [IoSliceMut],
+	ancillary: &mut SocketAncillary,
+) -> std::io::Result<usize> {
+	recv_msg_impl(socket, buffer, ancillary, None, libc::MSG_PEEK).map(|(size, _flags)| size)
+}
+
+/// Receive a message and report if the data was truncated because it did not fit in the buffer (`MSG_TRUNC`).
+pub fn recv_msg_truncated(
+	socket: &FileDesc,
+	buffer: &mut [IoSliceMut],
+	ancillary: &mut SocketAncillary,
+) -> std::io::Result<(usize, bool)> {
+	let (size, flags) = recv_msg_impl(socket, buffer, ancillary, None, 0)?;
+	Ok((size, flags & libc::MSG_TRUNC != 0))
+}
+
+/// Receive a message and return the full size of the datagram, even if it did not fit in the buffer.
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub fn recv_msg_full_len(
+	socket: &FileDesc,
+	buffer: &mut [IoSliceMut],
+	ancillary: &mut SocketAncillary,
+) -> std::io::Result<usize> {
+	recv_msg_impl(socket, buffer, ancillary, None, libc::MSG_TRUNC).map(|(size, _flags)| size)
+}
+
+pub fn recv_msg_from(
+	socket: &FileDesc,
+	buffer: &mut [IoSliceMut],
+	ancillary: &mut SocketAncillary,
+) -> std::io::Result<(usize, SocketAddr)> {
+	let mut address = SocketAddr::new_unnamed();
+	let (size, _flags) = recv_msg_impl(socket, buffer, ancillary, Some(&mut address), 0)?;
+	Ok((size, address))
+}
+
+/// Receive a message into uninitialized memory and report if the data was truncated (`MSG_TRUNC`).
+///
+/// The first bytes of `buffer`, up to the returned size, are initialized when this function returns successfully.
+pub fn recv_msg_truncated_uninit(
+	socket: &FileDesc,
+	buffer: &mut [MaybeUninit<u8>],
+	ancillary: &mut SocketAncillary,
+) -> std::io::Result<(usize, bool)> {
+	let mut iovec = libc::iovec {
+		iov_base: buffer.as_mut_ptr().cast(),
+		iov_len: buffer.len(),
+	};
+	let (size, flags) = recv_msg_iovecs(socket, std::slice::from_mut(&mut iovec), ancillary, None, 0)?;
+	Ok((size, flags & libc::MSG_TRUNC != 0))
+}
+
+/// Receive a message, and return its size together with the flags reported by the kernel (`msg_flags`).
+fn recv_msg_impl(
+	socket: &FileDesc,
+	buffer: &mut [IoSliceMut],
+	ancillary: &mut SocketAncillary,
+	address: Option<&mut SocketAddr>,
+	flags: c_int,
+) -> std::io::Result<(usize, c_int)> {
+	// SAFETY: `IoSliceMut` is guaranteed to be ABI compatible with `iovec` on Unix.
+	let buffer = unsafe { std::slice::from_raw_parts_mut(buffer.as_mut_ptr().cast::<libc::iovec>(), buffer.len()) };
+	recv_msg_iovecs(socket, buffer, ancillary, address, flags)
+}
+
+/// Receive a message into the memory described by `buffer`.
+///
+/// The `iovec` structs must point to valid memory, but that memory does not need to be initialized.
+fn recv_msg_iovecs(
+	socket: &FileDesc,
+	buffer: &mut [libc::iovec],
+	ancillary: &mut SocketAncillary,
+	address: Option<&mut SocketAddr>,
+	flags: c_int,
+) -> std::io::Result<(usize, c_int)> {
 	let control_data = match ancillary.capacity() {
 		0 => std::ptr::null_mut(),
 		_ => ancillary.buffer.as_mut_ptr() as *mut std::os::raw::c_void,
 	};
 
 	let mut header: libc::msghdr = unsafe { std::mem::zeroed() };
-	header.msg_name = std::ptr::null_mut();
-	header.msg_namelen = 0;
-	header.msg_iov = buffer.as_ptr() as *mut libc::iovec;
+	let address = match address {
+		Some(address) => {
+			header.msg_name = &mut address.address as *mut libc::sockaddr_un as *mut c_void;
+			header.msg_namelen = core::mem::size_of::<libc::sockaddr_un>() as libc::socklen_t;
+			Some(address)
+		},
+		None => {
+			header.msg_name = std::ptr::null_mut();
+			header.msg_namelen = 0;
+			None
+		},
+	};
+	check_iov_len(buffer.len())?;
+	header.msg_iov = buffer.as_mut_ptr();
 	// This is not a no-op on all platforms.
 	#[allow(clippy::useless_conversion)]
 	{
@@ -210,21 +783,123 @@ pub fn recv_msg(
 	}
 
 	let size = unsafe {
-		check_size(libc::recvmsg(
-			socket.as_raw_fd(),
-			&mut header as *mut _,
-			RECV_MSG_DEFAULT_FLAGS,
-		))?
+		retry_interrupted(|| {
+			check_size(libc::recvmsg(
+				socket.as_raw_fd(),
+				&mut header as *mut _,
+				RECV_MSG_DEFAULT_FLAGS | flags,
+			))
+		})?
 	};
 	ancillary.truncated = header.msg_flags & libc::MSG_CTRUNC != 0;
 	ancillary.length = header.msg_controllen as usize;
+	ancillary.fds_owned = true;
+	if let Some(address) = address {
+		address.len = header.msg_namelen;
+	}
+
+	#[cfg(feature = "tracing")]
+	trace_truncation(header.msg_flags, size, buffer, ancillary);
 
 	// Illumos and solaris do not support MSG_CMSG_CLOEXEC,
 	// so we fix-up all received file descriptors manually.
 	#[cfg(any(target_os = "illumos", target_os = "solaris"))]
 	fixup_cloexec(&ancillary);
 
-	Ok(size)
+	Ok((size, header.msg_flags))
+}
+
+/// Receive multiple messages with their credentials in a single `recvmmsg()` call.
+///
+/// Each message gets its own control buffer that is large enough to hold a single `SCM_CREDENTIALS` message.
+#[cfg(any(target_os = "android", target_os = "linux"))]
+pub fn recv_many_credentialed(
+	socket: &FileDesc,
+	buffers: &mut [&mut [u8]],
+) -> std::io::Result<Vec<(usize, Option<SocketCred>)>> {
+	let control_space = unsafe { libc::CMSG_SPACE(core::mem::size_of::<libc::ucred>() as u32) as usize };
+	let mut control = vec![0u8; control_space * buffers.len()];
+	let mut iovecs: Vec<libc::iovec> = buffers
+		.iter_mut()
+		.map(|buffer| libc::iovec {
+			iov_base: buffer.as_mut_ptr().cast(),
+			iov_len: buffer.len(),
+		})
+		.collect();
+	let mut headers: Vec<libc::mmsghdr> = iovecs
+		.iter_mut()
+		.zip(control.chunks_mut(control_space))
+		.map(|(iovec, control)| {
+			let mut header: libc::mmsghdr = unsafe { std::mem::zeroed() };
+			header.msg_hdr.msg_iov = iovec;
+			header.msg_hdr.msg_iovlen = 1;
+			header.msg_hdr.msg_control = control.as_mut_ptr().cast();
+			header.msg_hdr.msg_controllen = control_space as _;
+			header
+		})
+		.collect();
+
+	let vlen = headers.len().try_into().map_err(|_| std::io::ErrorKind::InvalidInput)?;
+	let count = unsafe {
+		retry_interrupted(|| {
+			check(libc::recvmmsg(
+				socket.as_raw_fd(),
+				headers.as_mut_ptr(),
+				vlen,
+				RECV_MSG_DEFAULT_FLAGS,
+				std::ptr::null_mut(),
+			))
+		})?
+	};
+
+	let mut messages = Vec::with_capacity(count as usize);
+	for (header, control) in headers
+		.iter()
+		.zip(control.chunks_mut(control_space))
+		.take(count as usize)
+	{
+		// This is not a no-op on all platforms.
+		#[allow(clippy::unnecessary_cast)]
+		let control_len = header.msg_hdr.msg_controllen as usize;
+		let mut ancillary = SocketAncillary::new(control);
+		ancillary.length = control_len;
+		let cred = ancillary.messages().find_map(|message| match message {
+			Ok(AncillaryData::ScmCredentials(mut creds)) => creds.next(),
+			_ => None,
+		});
+		messages.push((header.msg_len as usize, cred));
+	}
+	Ok(messages)
+}
+
+/// Emit a warning if a received message was truncated.
+#[cfg(feature = "tracing")]
+fn trace_truncation(flags: c_int, size: usize, buffer: &[libc::iovec], ancillary: &SocketAncillary) {
+	if flags & (libc::MSG_TRUNC | libc::MSG_CTRUNC) == 0 {
+		return;
+	}
+
+	let buffer_len: usize = buffer.iter().map(|buffer| buffer.iov_len).sum();
+	let fds: usize = ancillary
+		.messages()
+		.filter_map(Result::ok)
+		.map(|message| match message {
+			crate::ancillary::AncillaryData::ScmRights(fds) => fds.count(),
+			#[allow(unreachable_patterns)]
+			_ => 0,
+		})
+		.sum();
+
+	tracing::warn!(
+		data_truncated = flags & libc::MSG_TRUNC != 0,
+		ancillary_truncated = flags & libc::MSG_CTRUNC != 0,
+		received_bytes = size,
+		buffer_len,
+		ancillary_len = ancillary.len(),
+		ancillary_capacity = ancillary.capacity(),
+		received_fds = fds,
+		"received message was truncated",
+	);
 }
 
 #[cfg(any(target_os = "illumos", target_os = "solaris"))]
@@ -292,6 +967,31 @@ fn sockaddr_to_path(address: &libc::sockaddr_un, len: libc::socklen_t) -> std::i
 	}
 }
 
+/// Get the maximum number of I/O slices for a single vectored send or receive.
+///
+/// The limit can not change while the process runs, so it is queried only once.
+pub fn iov_max() -> usize {
+	static IOV_MAX: OnceLock<usize> = OnceLock::new();
+	*IOV_MAX.get_or_init(|| match unsafe { libc::sysconf(libc::_SC_IOV_MAX) } {
+		// POSIX requires at least 16, but all supported platforms allow 1024.
+		-1 => 1024,
+		max => max as usize,
+	})
+}
+
+/// Check that the number of I/O slices does not exceed [`iov_max()`].
+fn check_iov_len(len: usize) -> std::io::Result<()> {
+	let max = iov_max();
+	if len > max {
+		Err(std::io::Error::new(
+			std::io::ErrorKind::InvalidInput,
+			format!("too many I/O slices: got {len}, but the maximum is {max}"),
+		))
+	} else {
+		Ok(())
+	}
+}
+
 /// Check the return value of a syscall that returns a size.
 fn check_size(ret: isize) -> std::io::Result<usize> {
 	if ret < 0 {
@@ -301,6 +1001,20 @@ fn check_size(ret: isize) -> std::io::Result<usize> {
 	}
 }
 
+/// Run a send or receive syscall again if it was interrupted by a signal (`EINTR`).
+///
+/// An interrupted call did not transfer any data, so retrying it can not merge or split datagrams.
+/// This does not busy-loop: a call is only interrupted when a signal arrives.
+/// Other errors, including `EAGAIN` and `EWOULDBLOCK`, are returned so the caller can wait for the reactor.
+fn retry_interrupted<T>(mut syscall: impl FnMut() -> std::io::Result<T>) -> std::io::Result<T> {
+	loop {
+		match syscall() {
+			Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+			result => return result,
+		}
+	}
+}
+
 /// Check the return value of a syscall.
 fn check(value: std::os::raw::c_int) -> std::io::Result<std::os::raw::c_int> {
 	if value == -1 {
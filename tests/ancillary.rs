@@ -0,0 +1,526 @@
+use assert2::{assert, let_assert};
+use tokio_seqpacket::ancillary::SocketAncillary;
+
+/// Test that adding anything but exactly one credential is rejected.
+#[test]
+#[cfg(any(target_os = "android", target_os = "linux"))]
+fn add_multiple_creds() {
+	use tokio_seqpacket::ancillary::SocketCred;
+
+	let mut buffer = [0; 128];
+	let mut ancillary = SocketAncillary::new(&mut buffer);
+	let cred = SocketCred::new();
+
+	assert!(ancillary.can_add_creds(1));
+	assert!(!ancillary.can_add_creds(0));
+	assert!(!ancillary.can_add_creds(2));
+	assert!(!ancillary.add_creds(&[]));
+	assert!(!ancillary.add_creds(&[cred.clone(), cred.clone()]));
+	assert!(ancillary.is_empty());
+
+	assert!(ancillary.add_creds(&[cred]));
+	assert!(!ancillary.is_empty());
+
+	let mut buffer = [0; 4];
+	let ancillary = SocketAncillary::new(&mut buffer);
+	assert!(!ancillary.can_add_creds(1));
+}
+
+/// Test receiving multiple messages with credentials in one call.
+#[tokio::test]
+#[cfg(any(target_os = "android", target_os = "linux"))]
+async fn recv_many_credentialed() {
+	use std::io::IoSlice;
+	use tokio_seqpacket::ancillary::SocketCred;
+	use tokio_seqpacket::UnixSeqpacket;
+
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+
+	let mut cred = SocketCred::new();
+	cred.set_pid(std::process::id() as libc::pid_t);
+	cred.set_uid(unsafe { libc::getuid() });
+	cred.set_gid(unsafe { libc::getgid() });
+
+	for i in 0..4u8 {
+		let mut buffer = [0; 64];
+		let mut ancillary = SocketAncillary::new(&mut buffer);
+		assert!(ancillary.add_creds(&[cred.clone()]));
+		assert!(let Ok(1) = a.send_vectored_with_ancillary(&[IoSlice::new(&[i])], &mut ancillary).await);
+	}
+
+	let mut buffers = [[0u8; 16]; 5];
+	let mut slices: Vec<&mut [u8]> = buffers.iter_mut().map(|buffer| &mut buffer[..]).collect();
+	let_assert!(Ok(messages) = b.recv_many_credentialed(&mut slices).await);
+	assert!(messages.len() == 4);
+	for (i, (len, received)) in messages.into_iter().enumerate() {
+		assert!(len == 1);
+		assert!(buffers[i][0] == i as u8);
+		let_assert!(Some(received) = received);
+		assert!(received.get_pid() == cred.get_pid());
+		assert!(received.get_uid() == cred.get_uid());
+		assert!(received.get_gid() == cred.get_gid());
+	}
+}
+
+/// Test listing the types of the control messages in a buffer.
+#[test]
+#[cfg(any(target_os = "android", target_os = "linux"))]
+fn message_types() {
+	use tokio_seqpacket::ancillary::SocketCred;
+
+	let mut buffer = [0; 128];
+	let mut ancillary = SocketAncillary::new(&mut buffer);
+	assert!(ancillary.message_types().is_empty());
+
+	assert!(ancillary.add_fds(&[0, 1]));
+	assert!(ancillary.add_creds(&[SocketCred::new()]));
+	assert!(
+		ancillary.message_types()
+			== [
+				(libc::SOL_SOCKET, libc::SCM_RIGHTS),
+				(libc::SOL_SOCKET, libc::SCM_CREDENTIALS)
+			]
+	);
+}
+
+/// Test comparing selected fields of credentials.
+#[test]
+#[cfg(any(target_os = "android", target_os = "linux"))]
+fn cred_matches() {
+	use tokio_seqpacket::ancillary::{CredFields, SocketCred};
+
+	let mut a = SocketCred::new();
+	a.set_pid(10);
+	a.set_uid(1000);
+	a.set_gid(100);
+	let mut b = a.clone();
+	b.set_pid(20);
+
+	assert!(a.matches(&b, CredFields::UID | CredFields::GID));
+	assert!(!a.matches(&b, CredFields::ALL));
+	assert!(!a.matches(&b, CredFields::PID));
+	assert!(a.matches(&b, CredFields::NONE));
+	assert!(a.matches(&a, CredFields::ALL));
+
+	b.set_gid(101);
+	assert!(a.matches(&b, CredFields::UID));
+	assert!(!a.matches(&b, CredFields::UID | CredFields::GID));
+}
+
+/// Test the Debug, equality and construction helpers of credentials.
+#[test]
+#[cfg(any(target_os = "android", target_os = "linux"))]
+fn cred_debug_eq() {
+	use std::collections::HashSet;
+	use tokio_seqpacket::ancillary::SocketCred;
+
+	let a = SocketCred::from_parts(10, 1000, 100);
+	assert!(a.get_pid() == 10);
+	assert!(a.get_uid() == 1000);
+	assert!(a.get_gid() == 100);
+	assert!(format!("{:?}", a) == "SocketCred { pid: 10, uid: 1000, gid: 100 }");
+
+	let b = SocketCred::from(libc::ucred {
+		pid: 10,
+		uid: 1000,
+		gid: 100,
+	});
+	assert!(a == b);
+	assert!(a != SocketCred::from_parts(11, 1000, 100));
+
+	let set: HashSet<_> = [a.clone(), b, SocketCred::new()].into_iter().collect();
+	assert!(set.len() == 2);
+	assert!(set.contains(&a));
+}
+
+/// Test iterating over control messages together with their raw bytes.
+#[test]
+fn messages_with_raw() {
+	use tokio_seqpacket::ancillary::AncillaryData;
+
+	let mut buffer = [0; 128];
+	let mut ancillary = SocketAncillary::new(&mut buffer);
+	assert!(ancillary.add_fds(&[7, 9]));
+
+	// Build the expected control message by hand: a header followed by the file descriptors.
+	let data_len = 2 * std::mem::size_of::<std::os::raw::c_int>();
+	let mut header: libc::cmsghdr = unsafe { std::mem::zeroed() };
+	header.cmsg_len = unsafe { libc::CMSG_LEN(data_len as u32) } as _;
+	header.cmsg_level = libc::SOL_SOCKET;
+	header.cmsg_type = libc::SCM_RIGHTS;
+	let header_len = unsafe { libc::CMSG_LEN(0) } as usize;
+	let mut expected = vec![0u8; header_len];
+	let header_bytes =
+		unsafe { std::slice::from_raw_parts(&header as *const _ as *const u8, std::mem::size_of::<libc::cmsghdr>()) };
+	expected[..header_bytes.len()].copy_from_slice(header_bytes);
+	expected.extend_from_slice(&7i32.to_ne_bytes());
+	expected.extend_from_slice(&9i32.to_ne_bytes());
+
+	let mut messages = ancillary.messages_with_raw();
+	let_assert!(Some((Ok(AncillaryData::ScmRights(fds)), raw)) = messages.next());
+	assert!(fds.collect::<Vec<_>>() == [7, 9]);
+	assert!(raw == expected.as_slice());
+	assert!(let None = messages.next());
+}
+
+/// Build the bytes of a control message with the given header fields and data.
+#[cfg(feature = "test-util")]
+fn raw_cmsg(cmsg_len: usize, cmsg_level: i32, cmsg_type: i32, data: &[u8]) -> Vec<u8> {
+	let mut header: libc::cmsghdr = unsafe { std::mem::zeroed() };
+	header.cmsg_len = cmsg_len as _;
+	header.cmsg_level = cmsg_level;
+	header.cmsg_type = cmsg_type;
+	let header_bytes =
+		unsafe { std::slice::from_raw_parts(&header as *const _ as *const u8, std::mem::size_of::<libc::cmsghdr>()) };
+	let mut bytes = vec![0u8; unsafe { libc::CMSG_LEN(0) } as usize];
+	bytes[..header_bytes.len()].copy_from_slice(header_bytes);
+	bytes.extend_from_slice(data);
+	bytes
+}
+
+/// Test parsing a hand-crafted control message of an unknown type.
+#[test]
+#[cfg(feature = "test-util")]
+fn parse_unknown_cmsg() {
+	use tokio_seqpacket::ancillary::AncillaryError;
+
+	let len = unsafe { libc::CMSG_LEN(4) } as usize;
+	let raw = raw_cmsg(len, libc::SOL_SOCKET, 12345, &[1, 2, 3, 4]);
+	let mut buffer = [0u64; 16];
+	let buffer = unsafe { std::slice::from_raw_parts_mut(buffer.as_mut_ptr().cast::<u8>(), 128) };
+	buffer[..raw.len()].copy_from_slice(&raw);
+
+	let space = unsafe { libc::CMSG_SPACE(4) } as usize;
+	let ancillary = SocketAncillary::from_raw_received(buffer, space, false);
+	assert!(!ancillary.truncated());
+	let mut messages = ancillary.messages();
+	let_assert!(Some(Err(AncillaryError::Unknown { cmsg_level, cmsg_type })) = messages.next());
+	assert!(cmsg_level == libc::SOL_SOCKET);
+	assert!(cmsg_type == 12345);
+	assert!(let None = messages.next());
+}
+
+/// Test that file descriptors in hand-crafted control messages are never taken or closed.
+#[test]
+#[cfg(feature = "test-util")]
+fn raw_received_fds_not_owned() {
+	use std::os::unix::io::AsRawFd;
+	use tokio_seqpacket::ancillary::AncillaryData;
+
+	let_assert!(Ok(file) = std::fs::File::open("/dev/null"));
+	let fd = file.as_raw_fd();
+	let len = unsafe { libc::CMSG_LEN(4) } as usize;
+	let raw = raw_cmsg(len, libc::SOL_SOCKET, libc::SCM_RIGHTS, &fd.to_ne_bytes());
+	let mut buffer = [0u64; 16];
+	let buffer = unsafe { std::slice::from_raw_parts_mut(buffer.as_mut_ptr().cast::<u8>(), 128) };
+	buffer[..raw.len()].copy_from_slice(&raw);
+
+	let mut ancillary = SocketAncillary::from_raw_received(buffer, raw.len(), false);
+	let_assert!(Some(Ok(AncillaryData::ScmRights(mut fds))) = ancillary.messages().next());
+	assert!(fds.next() == Some(fd));
+	assert!(ancillary.take_fds().count() == 0);
+	assert!(unsafe { libc::fcntl(fd, libc::F_GETFD) } != -1);
+}
+
+/// Test parsing a hand-crafted control message that extends beyond the received data.
+#[test]
+#[cfg(feature = "test-util")]
+fn parse_truncated_cmsg() {
+	use tokio_seqpacket::ancillary::AncillaryError;
+
+	// The header claims room for four file descriptors, but only one was received.
+	let len = unsafe { libc::CMSG_LEN(16) } as usize;
+	let raw = raw_cmsg(len, libc::SOL_SOCKET, libc::SCM_RIGHTS, &5i32.to_ne_bytes());
+	let mut buffer = [0u64; 16];
+	let buffer = unsafe { std::slice::from_raw_parts_mut(buffer.as_mut_ptr().cast::<u8>(), 128) };
+	buffer[..raw.len()].copy_from_slice(&raw);
+
+	let ancillary = SocketAncillary::from_raw_received(buffer, raw.len(), true);
+	assert!(ancillary.truncated());
+	let mut messages = ancillary.messages();
+	let_assert!(Some(Err(AncillaryError::Truncated { cmsg_level, cmsg_type })) = messages.next());
+	assert!(cmsg_level == libc::SOL_SOCKET);
+	assert!(cmsg_type == libc::SCM_RIGHTS);
+	assert!(let None = messages.next());
+	assert!(ancillary.fd_count() == 0);
+}
+
+/// Test parsing a control message with a registered custom parser.
+#[test]
+#[cfg(feature = "test-util")]
+fn custom_parser() {
+	use tokio_seqpacket::ancillary::{register_parser, unregister_parser, AncillaryData, AncillaryError};
+
+	#[derive(Debug, PartialEq)]
+	struct Counter(u32);
+
+	// Use a level that no other test uses, since the registry is global.
+	const LEVEL: i32 = 0x5eb;
+	const TYPE: i32 = 7;
+	register_parser(LEVEL, TYPE, |data| {
+		Box::new(Counter(u32::from_ne_bytes(data[..4].try_into().unwrap())))
+	});
+
+	let len = unsafe { libc::CMSG_LEN(4) } as usize;
+	let raw = raw_cmsg(len, LEVEL, TYPE, &42u32.to_ne_bytes());
+	let mut buffer = [0u64; 16];
+	let buffer = unsafe { std::slice::from_raw_parts_mut(buffer.as_mut_ptr().cast::<u8>(), 128) };
+	buffer[..raw.len()].copy_from_slice(&raw);
+	let ancillary = SocketAncillary::from_raw_received(buffer, raw.len(), false);
+
+	let_assert!(
+		Some(Ok(AncillaryData::Custom {
+			cmsg_level,
+			cmsg_type,
+			value
+		})) = ancillary.messages().next()
+	);
+	assert!(cmsg_level == LEVEL);
+	assert!(cmsg_type == TYPE);
+	assert!(value.downcast_ref::<Counter>() == Some(&Counter(42)));
+
+	// Without the parser, the message is unknown again.
+	assert!(unregister_parser(LEVEL, TYPE));
+	assert!(!unregister_parser(LEVEL, TYPE));
+	assert!(let Some(Err(AncillaryError::Unknown { .. })) = ancillary.messages().next());
+}
+
+/// Test that the computed control message sizes fit exactly.
+#[test]
+fn cmsg_space_helpers() {
+	use tokio_seqpacket::ancillary::{cmsg_space, cmsg_space_for_fds, CmsgKind};
+
+	let mut buffer = vec![0u8; cmsg_space_for_fds(4)];
+	let mut ancillary = SocketAncillary::new(&mut buffer);
+	assert!(ancillary.add_fds(&[0, 1, 2, 3]));
+	assert!(ancillary.len() == ancillary.capacity());
+	assert!(!ancillary.add_fds(&[0]));
+
+	assert!(cmsg_space(&[]) == 0);
+	assert!(cmsg_space(&[CmsgKind::Fds(4), CmsgKind::Fds(1)]) == cmsg_space_for_fds(4) + cmsg_space_for_fds(1));
+	assert!(let Err(_) = std::panic::catch_unwind(|| cmsg_space_for_fds(usize::MAX)));
+	assert!(let Err(_) = std::panic::catch_unwind(|| cmsg_space(&[CmsgKind::Fds(usize::MAX / 8)])));
+}
+
+/// Test that the computed size for credentials fits exactly.
+#[test]
+#[cfg(any(target_os = "android", target_os = "linux"))]
+fn cmsg_space_for_creds() {
+	use tokio_seqpacket::ancillary::{cmsg_space, cmsg_space_for_creds, cmsg_space_for_fds, CmsgKind, SocketCred};
+
+	let mut buffer = vec![0u8; cmsg_space(&[CmsgKind::Fds(2), CmsgKind::Creds(2)])];
+	let mut ancillary = SocketAncillary::new(&mut buffer);
+	assert!(ancillary.add_fds(&[0, 1]));
+	assert!(ancillary.add_creds(&[SocketCred::new()]));
+	assert!(ancillary.add_creds(&[SocketCred::new()]));
+	assert!(ancillary.len() == ancillary.capacity());
+	assert!(cmsg_space_for_creds(2) + cmsg_space_for_fds(2) == ancillary.capacity());
+}
+
+/// Test that try_add_fds() reports why file descriptors could not be added.
+#[test]
+fn try_add_fds_errors() {
+	use tokio_seqpacket::ancillary::{cmsg_space_for_fds, AncillaryAddError};
+
+	let mut buffer = vec![0u8; cmsg_space_for_fds(1)];
+	let mut ancillary = SocketAncillary::new(&mut buffer);
+	assert!(let Ok(()) = ancillary.try_add_fds(&[0]));
+	let_assert!(Err(AncillaryAddError::BufferFull { needed, available }) = ancillary.try_add_fds(&[1]));
+	assert!(needed == cmsg_space_for_fds(1));
+	assert!(available == 0);
+	assert!(!ancillary.add_fds(&[1]));
+	assert!(ancillary.len() == cmsg_space_for_fds(1));
+}
+
+/// Test that try_add_fds() detects a corrupt chain of control messages.
+#[test]
+#[cfg(feature = "test-util")]
+fn try_add_fds_corrupt_chain() {
+	use tokio_seqpacket::ancillary::AncillaryAddError;
+
+	// The message claims to be longer than the used part of the buffer.
+	let len = unsafe { libc::CMSG_LEN(4) } as usize;
+	let raw = raw_cmsg(len, libc::SOL_SOCKET, libc::SCM_RIGHTS, &5i32.to_ne_bytes());
+	let mut buffer = [0u64; 16];
+	let buffer = unsafe { std::slice::from_raw_parts_mut(buffer.as_mut_ptr().cast::<u8>(), 128) };
+	buffer[..raw.len()].copy_from_slice(&raw);
+
+	let mut ancillary = SocketAncillary::from_raw_received(buffer, 8, false);
+	assert!(let Err(AncillaryAddError::CmsgChainCorrupt) = ancillary.try_add_fds(&[0]));
+	assert!(ancillary.len() == 8);
+}
+
+/// Test that enabling SO_PASSCRED attaches the credentials of the sender to every message.
+#[tokio::test]
+#[cfg(any(target_os = "android", target_os = "linux"))]
+async fn passcred() {
+	use std::io::IoSliceMut;
+	use tokio_seqpacket::ancillary::AncillaryData;
+	use tokio_seqpacket::UnixSeqpacket;
+
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	assert!(let Ok(false) = b.passcred());
+	assert!(let Ok(()) = b.set_passcred(true));
+	assert!(let Ok(true) = b.passcred());
+
+	// The sender does not add credentials, but the kernel attaches them anyway.
+	assert!(let Ok(1) = a.send(b"x").await);
+	let mut buffer = [0; 64];
+	let mut ancillary = SocketAncillary::new(&mut buffer);
+	let mut read_buf = [0u8; 4];
+	assert!(let Ok(1) = b.recv_vectored_with_ancillary(&mut [IoSliceMut::new(&mut read_buf)], &mut ancillary).await);
+	let_assert!(Some(Ok(AncillaryData::ScmCredentials(mut creds))) = ancillary.messages().next());
+	let_assert!(Some(cred) = creds.next());
+	assert!(cred.get_pid() == std::process::id() as libc::pid_t);
+	assert!(cred.get_uid() == unsafe { libc::getuid() });
+
+	assert!(let Ok(()) = b.set_passcred(false));
+	assert!(let Ok(false) = b.passcred());
+}
+
+/// Test converting the credentials of a connected peer to a `SocketCred`.
+#[tokio::test]
+#[cfg(any(target_os = "android", target_os = "linux"))]
+async fn peer_cred_to_socket_cred() {
+	use tokio_seqpacket::ancillary::SocketCred;
+	use tokio_seqpacket::UnixSeqpacket;
+
+	let_assert!(Ok((a, _b)) = UnixSeqpacket::pair());
+	let_assert!(Ok(ucred) = a.peer_cred());
+	let cred = SocketCred::from(ucred);
+	assert!(cred.get_pid() == std::process::id() as libc::pid_t);
+	assert!(cred.get_uid() == unsafe { libc::getuid() });
+	assert!(cred.get_gid() == unsafe { libc::getgid() });
+}
+
+/// Build a buffer with an unknown control message between two `SCM_RIGHTS` messages.
+#[cfg(feature = "test-util")]
+fn raw_cmsgs_with_unknown() -> Vec<u64> {
+	let mut raw = Vec::new();
+	for (cmsg_type, value) in [(libc::SCM_RIGHTS, 5i32), (12345, 6), (libc::SCM_RIGHTS, 7)] {
+		let len = unsafe { libc::CMSG_LEN(4) } as usize;
+		let mut cmsg = raw_cmsg(len, libc::SOL_SOCKET, cmsg_type, &value.to_ne_bytes());
+		cmsg.resize(unsafe { libc::CMSG_SPACE(4) } as usize, 0);
+		raw.extend_from_slice(&cmsg);
+	}
+	let mut buffer = vec![0u64; raw.len() / 8];
+	let bytes = unsafe { std::slice::from_raw_parts_mut(buffer.as_mut_ptr().cast::<u8>(), raw.len()) };
+	bytes.copy_from_slice(&raw);
+	buffer
+}
+
+/// Test the different policies for unknown control messages.
+#[test]
+#[cfg(feature = "test-util")]
+fn unknown_policy() {
+	use tokio_seqpacket::ancillary::{AncillaryData, AncillaryError, UnknownPolicy};
+
+	fn fds(message: Option<Result<AncillaryData, AncillaryError>>) -> Vec<i32> {
+		let_assert!(Some(Ok(AncillaryData::ScmRights(fds))) = message);
+		fds.collect()
+	}
+
+	let mut buffer = raw_cmsgs_with_unknown();
+	let len = buffer.len() * 8;
+	let buffer = unsafe { std::slice::from_raw_parts_mut(buffer.as_mut_ptr().cast::<u8>(), len) };
+	let ancillary = SocketAncillary::from_raw_received(buffer, len, false);
+
+	// The default policy reports an error.
+	let mut messages = ancillary.messages();
+	assert!(fds(messages.next()) == [5]);
+	let_assert!(Some(Err(AncillaryError::Unknown { cmsg_level, cmsg_type })) = messages.next());
+	assert!((cmsg_level, cmsg_type) == (libc::SOL_SOCKET, 12345));
+	assert!(fds(messages.next()) == [7]);
+	assert!(let None = messages.next());
+
+	let mut messages = ancillary.messages().unknown_policy(UnknownPolicy::Error);
+	assert!(fds(messages.next()) == [5]);
+	assert!(let Some(Err(AncillaryError::Unknown { .. })) = messages.next());
+
+	// Skipping continues with the next known message.
+	let mut messages = ancillary.messages().unknown_policy(UnknownPolicy::Skip);
+	assert!(fds(messages.next()) == [5]);
+	assert!(fds(messages.next()) == [7]);
+	assert!(let None = messages.next());
+	assert!(messages.unknown().is_empty());
+
+	// Collecting skips the message, but keeps it.
+	let mut messages = ancillary.messages().unknown_policy(UnknownPolicy::Collect);
+	assert!(fds(messages.next()) == [5]);
+	assert!(messages.unknown().is_empty());
+	assert!(fds(messages.next()) == [7]);
+	assert!(let None = messages.next());
+	let unknown = messages.into_unknown();
+	assert!(unknown.len() == 1);
+	assert!(unknown[0].cmsg_level == libc::SOL_SOCKET);
+	assert!(unknown[0].cmsg_type == 12345);
+	assert!(unknown[0].data == 6i32.to_ne_bytes());
+
+	// Yielding reports the raw data in place.
+	let mut messages = ancillary.messages().unknown_policy(UnknownPolicy::Yield);
+	assert!(fds(messages.next()) == [5]);
+	let_assert!(
+		Some(Ok(AncillaryData::Unknown {
+			cmsg_level,
+			cmsg_type,
+			data
+		})) = messages.next()
+	);
+	assert!((cmsg_level, cmsg_type) == (libc::SOL_SOCKET, 12345));
+	assert!(data == 6i32.to_ne_bytes());
+	assert!(fds(messages.next()) == [7]);
+	assert!(let None = messages.next());
+}
+
+/// Test that malformed `SCM_RIGHTS` messages are rejected instead of yielding bogus file descriptors.
+#[test]
+#[cfg(feature = "test-util")]
+fn parse_malformed_fds() {
+	use tokio_seqpacket::ancillary::{AncillaryData, AncillaryError};
+
+	let mut raw = Vec::new();
+	// A length that is not a multiple of the size of a file descriptor.
+	let mut cmsg = raw_cmsg(
+		unsafe { libc::CMSG_LEN(6) } as usize,
+		libc::SOL_SOCKET,
+		libc::SCM_RIGHTS,
+		&[1, 0, 0, 0, 2, 0],
+	);
+	cmsg.resize(unsafe { libc::CMSG_SPACE(6) } as usize, 0);
+	raw.extend_from_slice(&cmsg);
+	// A negative file descriptor.
+	let data = [(-1i32).to_ne_bytes(), 9i32.to_ne_bytes()].concat();
+	let mut cmsg = raw_cmsg(
+		unsafe { libc::CMSG_LEN(8) } as usize,
+		libc::SOL_SOCKET,
+		libc::SCM_RIGHTS,
+		&data,
+	);
+	cmsg.resize(unsafe { libc::CMSG_SPACE(8) } as usize, 0);
+	raw.extend_from_slice(&cmsg);
+	// A valid message after the malformed ones is still parsed.
+	raw.extend_from_slice(&raw_cmsg(
+		unsafe { libc::CMSG_LEN(4) } as usize,
+		libc::SOL_SOCKET,
+		libc::SCM_RIGHTS,
+		&5i32.to_ne_bytes(),
+	));
+
+	let mut buffer = [0u64; 16];
+	let buffer = unsafe { std::slice::from_raw_parts_mut(buffer.as_mut_ptr().cast::<u8>(), 128) };
+	buffer[..raw.len()].copy_from_slice(&raw);
+	let ancillary = SocketAncillary::from_raw_received(buffer, raw.len(), false);
+
+	let mut messages = ancillary.messages();
+	for _ in 0..2 {
+		let_assert!(Some(Err(AncillaryError::Malformed { cmsg_level, cmsg_type })) = messages.next());
+		assert!(cmsg_level == libc::SOL_SOCKET);
+		assert!(cmsg_type == libc::SCM_RIGHTS);
+	}
+	let_assert!(Some(Ok(AncillaryData::ScmRights(fds))) = messages.next());
+	assert!(fds.collect::<Vec<_>>() == [5]);
+	assert!(let None = messages.next());
+
+	// Only the file descriptor of the valid message is counted.
+	assert!(ancillary.fd_count() == 1);
+}
@@ -38,3 +38,1005 @@ async fn pass_fd() {
 	assert!(let Ok(_) = file.read_to_end(&mut contents));
 	assert!(contents == b"Wie dit leest is gek.");
 }
+
+#[tokio::test]
+async fn relay_fd() {
+	let_assert!(Ok(mut file) = tempfile());
+	assert!(let Ok(_) = file.write_all(b"Doorgeven maar."));
+	assert!(let Ok(0) = file.seek(std::io::SeekFrom::Start(0)));
+
+	let_assert!(Ok((a, relay_in)) = UnixSeqpacket::pair());
+	let_assert!(Ok((relay_out, c)) = UnixSeqpacket::pair());
+
+	let mut cmsg = [0; 64];
+	let mut cmsg = SocketAncillary::new(&mut cmsg);
+	cmsg.add_fds(&[file.as_raw_fd()]);
+	assert!(let Ok(6) = a.send_vectored_with_ancillary(&[IoSlice::new(b"Relay!")], &mut cmsg).await);
+	drop(file);
+
+	let mut relay_buf = [0u8; 64];
+	assert!(let Ok(6) = relay_in.relay_fds_to(&relay_out, &mut relay_buf).await);
+
+	let mut cmsg = [0; 64];
+	let mut cmsg = SocketAncillary::new(&mut cmsg);
+	let mut read_buf = [0u8; 64];
+	assert!(let Ok(6) = c.recv_vectored_with_ancillary(&mut [IoSliceMut::new(&mut read_buf)], &mut cmsg).await);
+	assert!(&read_buf[..6] == b"Relay!");
+
+	let mut cmsgs = cmsg.messages();
+	let_assert!(Some(Ok(AncillaryData::ScmRights(mut fds))) = cmsgs.next());
+	let_assert!(Some(fd) = fds.next());
+	assert!(let None = fds.next());
+
+	let mut file = unsafe { std::fs::File::from_raw_fd(fd) };
+	let mut contents = Vec::new();
+	assert!(let Ok(_) = file.read_to_end(&mut contents));
+	assert!(contents == b"Doorgeven maar.");
+}
+
+#[tokio::test]
+async fn fd_count() {
+	use std::os::unix::io::OwnedFd;
+
+	let_assert!(Ok(file) = tempfile());
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+
+	let mut cmsg = [0; 64];
+	let mut cmsg = SocketAncillary::new(&mut cmsg);
+	assert!(cmsg.fd_count() == 0);
+	assert!(cmsg.add_fds(&[file.as_raw_fd(), file.as_raw_fd()]));
+	assert!(cmsg.add_fds(&[file.as_raw_fd()]));
+	assert!(cmsg.fd_count() == 3);
+	assert!(let Ok(5) = a.send_vectored_with_ancillary(&[IoSlice::new(b"Three")], &mut cmsg).await);
+
+	let mut cmsg = [0; 64];
+	let mut cmsg = SocketAncillary::new(&mut cmsg);
+	let mut read_buf = [0u8; 64];
+	assert!(let Ok(5) = b.recv_vectored_with_ancillary(&mut [IoSliceMut::new(&mut read_buf)], &mut cmsg).await);
+
+	// Counting does not consume anything.
+	assert!(cmsg.fd_count() == 3);
+	assert!(cmsg.fd_count() == 3);
+
+	let mut fds = Vec::new();
+	for message in cmsg.messages() {
+		let_assert!(Ok(AncillaryData::ScmRights(message)) = message);
+		fds.extend(message.map(|fd| unsafe { OwnedFd::from_raw_fd(fd) }));
+	}
+	assert!(fds.len() == 3);
+	for fd in &fds {
+		let_assert!(Ok(_) = std::fs::File::from(fd.try_clone().unwrap()).metadata());
+	}
+}
+
+#[tokio::test]
+async fn max_control_bytes() {
+	use tokio_seqpacket::ControlTruncated;
+
+	let_assert!(Ok(file) = tempfile());
+	let_assert!(Ok((a, relay_in)) = UnixSeqpacket::pair());
+	let_assert!(Ok((relay_out, _c)) = UnixSeqpacket::pair());
+
+	assert!(relay_in.max_control_bytes() == usize::MAX);
+	relay_in.set_max_control_bytes(32);
+	assert!(relay_in.max_control_bytes() == 32);
+
+	let mut cmsg = [0; 256];
+	let mut cmsg = SocketAncillary::new(&mut cmsg);
+	assert!(cmsg.add_fds(&[file.as_raw_fd(); 20]));
+	assert!(let Ok(6) = a.send_vectored_with_ancillary(&[IoSlice::new(b"Relay!")], &mut cmsg).await);
+
+	let mut relay_buf = [0u8; 64];
+	let_assert!(Err(e) = relay_in.relay_fds_to(&relay_out, &mut relay_buf).await);
+	assert!(e.kind() == std::io::ErrorKind::InvalidData);
+	let_assert!(Some(inner) = e.get_ref());
+	let_assert!(Some(truncated) = inner.downcast_ref::<ControlTruncated>());
+	assert!(truncated.capacity() == 32);
+}
+
+#[tokio::test]
+async fn ancillary_from_fds() {
+	use tokio_seqpacket::ancillary::SocketAncillaryBuf;
+
+	let files: Vec<_> = (0..5).map(|_| tempfile().unwrap()).collect();
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+
+	let mut cmsg: SocketAncillaryBuf = files.iter().map(|file| file.as_raw_fd()).collect();
+	assert!(cmsg.fd_count() == 5);
+	assert!(let Ok(4) = a.send_vectored_with_ancillary(&[IoSlice::new(b"Five")], &mut cmsg).await);
+
+	let mut cmsg = SocketAncillaryBuf::with_capacity(128);
+	let mut read_buf = [0u8; 64];
+	assert!(let Ok(4) = b.recv_vectored_with_ancillary(&mut [IoSliceMut::new(&mut read_buf)], &mut cmsg).await);
+	assert!(!cmsg.truncated());
+	assert!(cmsg.fd_count() == 5);
+
+	for message in cmsg.messages() {
+		let_assert!(Ok(AncillaryData::ScmRights(fds)) = message);
+		for fd in fds {
+			drop(unsafe { std::fs::File::from_raw_fd(fd) });
+		}
+	}
+
+	// Large lists are split over multiple control messages.
+	let cmsg = SocketAncillaryBuf::from_fds(std::iter::repeat_n(files[0].as_raw_fd(), 300));
+	assert!(cmsg.fd_count() == 300);
+	assert!(cmsg.messages().count() == 2);
+}
+
+#[tokio::test]
+async fn truncated_flag() {
+	let_assert!(Ok(file) = tempfile());
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+
+	let mut cmsg = [0; 128];
+	let mut cmsg = SocketAncillary::new(&mut cmsg);
+	assert!(cmsg.truncated() == false);
+	assert!(cmsg.add_fds(&[file.as_raw_fd(); 8]));
+	assert!(let Ok(5) = a.send_vectored_with_ancillary(&[IoSlice::new(b"Eight")], &mut cmsg).await);
+	assert!(cmsg.truncated() == false);
+
+	// Receive into a control buffer that is too small.
+	let mut cmsg = [0; 128];
+	let (small, _) = cmsg.split_at_mut(ancillary_space(1));
+	let mut cmsg = SocketAncillary::new(small);
+	let mut read_buf = [0u8; 64];
+	assert!(let Ok(5) = b.recv_vectored_with_ancillary(&mut [IoSliceMut::new(&mut read_buf)], &mut cmsg).await);
+	assert!(cmsg.truncated() == true);
+	for message in cmsg.messages() {
+		let_assert!(Ok(AncillaryData::ScmRights(fds)) = message);
+		fds.for_each(|fd| drop(unsafe { std::fs::File::from_raw_fd(fd) }));
+	}
+
+	// Building a new ancillary for sending starts out not truncated.
+	let mut send_cmsg = [0; 64];
+	let mut send_cmsg = SocketAncillary::new(&mut send_cmsg);
+	assert!(send_cmsg.add_fds(&[file.as_raw_fd()]));
+	assert!(send_cmsg.truncated() == false);
+
+	// Only clear() resets the flag of the received ancillary data.
+	assert!(cmsg.truncated() == true);
+	cmsg.clear();
+	assert!(cmsg.truncated() == false);
+}
+
+fn ancillary_space(fds: usize) -> usize {
+	unsafe { libc::CMSG_SPACE((fds * std::mem::size_of::<std::os::unix::io::RawFd>()) as u32) as usize }
+}
+
+#[tokio::test]
+#[cfg(any(target_os = "android", target_os = "linux"))]
+async fn check_fd_type() {
+	use tokio_seqpacket::ancillary::{fd_type, FdType};
+
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	let_assert!(Ok((socket, _other)) = UnixSeqpacket::pair());
+	let memfd = unsafe { libc::memfd_create(c"test".as_ptr(), libc::MFD_CLOEXEC) };
+	assert!(memfd >= 0);
+	let memfd = unsafe { std::os::unix::io::OwnedFd::from_raw_fd(memfd) };
+	let_assert!(Ok(file) = tempfile());
+
+	assert!(let Ok(FdType::Socket) = fd_type(socket.as_raw_fd()));
+	assert!(let Ok(FdType::MemFd) = fd_type(memfd.as_raw_fd()));
+	assert!(let Ok(FdType::RegularFile) = fd_type(file.as_raw_fd()));
+
+	// The kernel merges all file descriptors of a datagram into one message, so send two datagrams.
+	for fds in [&[socket.as_raw_fd()][..], &[memfd.as_raw_fd(), memfd.as_raw_fd()]] {
+		let mut cmsg = [0; 64];
+		let mut cmsg = SocketAncillary::new(&mut cmsg);
+		assert!(cmsg.add_fds(fds));
+		assert!(let Ok(3) = a.send_vectored_with_ancillary(&[IoSlice::new(b"fds")], &mut cmsg).await);
+	}
+
+	let mut fds = Vec::new();
+	for expected in [FdType::Socket, FdType::MemFd] {
+		let mut cmsg = [0; 64];
+		let mut cmsg = SocketAncillary::new(&mut cmsg);
+		let mut read_buf = [0u8; 64];
+		assert!(let Ok(3) = b.recv_vectored_with_ancillary(&mut [IoSliceMut::new(&mut read_buf)], &mut cmsg).await);
+
+		let mut messages = cmsg.messages();
+		let_assert!(Some(Ok(AncillaryData::ScmRights(received))) = messages.next());
+		assert!(let Ok(()) = received.check_type(expected));
+		let_assert!(Err(e) = received.check_type(FdType::RegularFile));
+		assert!(e.kind() == std::io::ErrorKind::InvalidData);
+
+		// Checking the type did not consume the file descriptors.
+		fds.extend(received.map(|fd| unsafe { std::os::unix::io::OwnedFd::from_raw_fd(fd) }));
+	}
+	assert!(fds.len() == 3);
+}
+
+#[tokio::test]
+async fn push_fds_grows() {
+	use tokio_seqpacket::ancillary::SocketAncillaryBuf;
+
+	let files: Vec<_> = (0..21).map(|_| tempfile().unwrap()).collect();
+	let fds: Vec<_> = files.iter().map(|file| file.as_raw_fd()).collect();
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+
+	let mut cmsg = SocketAncillaryBuf::with_capacity(ancillary_space(1));
+	let_assert!(Ok(_) = cmsg.push_fds(&fds[..1]));
+	let initial_capacity = cmsg.capacity();
+
+	// Pushing more file descriptors reallocates the buffer.
+	let_assert!(Ok(_) = cmsg.push_fds(&fds[1..11]).and_then(|cmsg| cmsg.push_fds(&fds[11..])));
+	assert!(cmsg.capacity() > initial_capacity);
+	assert!(cmsg.fd_count() == 21);
+	assert!(let Ok(4) = a.send_vectored_with_ancillary(&[IoSlice::new(b"Many")], &mut cmsg).await);
+
+	let mut cmsg = SocketAncillaryBuf::with_capacity(ancillary_space(21));
+	let mut read_buf = [0u8; 64];
+	assert!(let Ok(4) = b.recv_vectored_with_ancillary(&mut [IoSliceMut::new(&mut read_buf)], &mut cmsg).await);
+	assert!(!cmsg.truncated());
+
+	let mut received = Vec::new();
+	for message in cmsg.messages() {
+		let_assert!(Ok(AncillaryData::ScmRights(fds)) = message);
+		received.extend(fds.map(|fd| unsafe { std::fs::File::from_raw_fd(fd) }));
+	}
+	assert!(received.len() == 21);
+
+	// The received file descriptors refer to the same files, in the same order.
+	let_assert!(Ok(mut file) = files[20].try_clone());
+	assert!(let Ok(_) = file.write_all(b"last"));
+	let mut contents = String::new();
+	assert!(let Ok(0) = received[20].seek(std::io::SeekFrom::Start(0)));
+	assert!(let Ok(4) = received[20].read_to_string(&mut contents));
+	assert!(contents == "last");
+}
+
+#[test]
+#[cfg(any(target_os = "android", target_os = "linux"))]
+fn push_fds_and_creds() {
+	use tokio_seqpacket::ancillary::{SocketAncillaryBuf, SocketCred};
+
+	let_assert!(Ok(file) = tempfile());
+	let mut cmsg = SocketAncillaryBuf::new();
+	let_assert!(
+		Ok(_) = cmsg
+			.push_fds(&[file.as_raw_fd()])
+			.and_then(|cmsg| cmsg.push_creds(SocketCred::new()))
+	);
+	let mut messages = cmsg.messages();
+	assert!(let Some(Ok(AncillaryData::ScmRights(_))) = messages.next());
+	assert!(let Some(Ok(AncillaryData::ScmCredentials(_))) = messages.next());
+	assert!(let None = messages.next());
+}
+
+#[tokio::test]
+async fn recv_into_reuses_vectors() {
+	use std::os::unix::fs::MetadataExt;
+
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+
+	let mut payload = Vec::with_capacity(64);
+	let mut fds = Vec::with_capacity(4);
+	let payload_ptr = payload.as_ptr();
+	let fds_ptr = fds.as_ptr();
+
+	for i in 1..=3 {
+		let files: Vec<_> = (0..i).map(|_| tempfile().unwrap()).collect();
+		let raw_fds: Vec<_> = files.iter().map(|file| file.as_raw_fd()).collect();
+		let mut control = [0; 128];
+		let mut cmsg = SocketAncillary::new(&mut control);
+		assert!(cmsg.add_fds(&raw_fds));
+		let message = format!("message {}", i);
+		assert!(let Ok(9) = a.send_vectored_with_ancillary(&[IoSlice::new(message.as_bytes())], &mut cmsg).await);
+
+		assert!(let Ok((9, false)) = b.recv_into(&mut payload, &mut fds).await);
+		assert!(payload == message.as_bytes());
+		assert!(fds.len() == i);
+
+		// The vectors were not reallocated.
+		assert!(payload.as_ptr() == payload_ptr);
+		assert!(fds.as_ptr() == fds_ptr);
+
+		// The received file descriptors refer to the sent files.
+		for (file, fd) in files.iter().zip(&fds) {
+			let_assert!(Ok(sent) = file.metadata());
+			let_assert!(Ok(received) = std::fs::File::from(fd.try_clone().unwrap()).metadata());
+			assert!(sent.ino() == received.ino());
+		}
+	}
+}
+
+/// Test that recv_into() grows the vector if the datagram does not fit in its capacity.
+#[tokio::test]
+async fn recv_into_grows_payload() {
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	let mut payload = Vec::with_capacity(4);
+	let mut fds = Vec::new();
+
+	assert!(let Ok(12) = a.send(b"Hello world!").await);
+	assert!(let Ok((12, false)) = b.recv_into(&mut payload, &mut fds).await);
+	assert!(payload == b"Hello world!");
+
+	// A vector without capacity receives the whole datagram too.
+	let mut payload = Vec::new();
+	assert!(let Ok(5) = a.send(b"Hello").await);
+	assert!(let Ok((5, false)) = b.recv_into(&mut payload, &mut fds).await);
+	assert!(payload == b"Hello");
+
+	// A large enough vector is not reallocated.
+	let payload_ptr = payload.as_ptr();
+	assert!(let Ok(3) = a.send(b"Bye").await);
+	assert!(let Ok((3, false)) = b.recv_into(&mut payload, &mut fds).await);
+	assert!(payload == b"Bye");
+	assert!(payload.as_ptr() == payload_ptr);
+}
+
+#[tokio::test]
+async fn max_fds_in_flight() {
+	use std::time::Duration;
+
+	let_assert!(Ok(file) = tempfile());
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	a.set_max_fds_in_flight(2);
+
+	async fn send_fd(socket: &UnixSeqpacket, fd: std::os::unix::io::RawFd) -> std::io::Result<usize> {
+		let mut control = [0; 64];
+		let mut cmsg = SocketAncillary::new(&mut control);
+		assert!(cmsg.add_fds(&[fd]));
+		socket
+			.send_vectored_with_ancillary(&[IoSlice::new(b"fd")], &mut cmsg)
+			.await
+	}
+
+	assert!(let Ok(2) = send_fd(&a, file.as_raw_fd()).await);
+	assert!(let Ok(2) = send_fd(&a, file.as_raw_fd()).await);
+	assert!(a.fds_in_flight() == 2);
+
+	// The third file descriptor has to wait for an acknowledgement.
+	let third = send_fd(&a, file.as_raw_fd());
+	tokio::pin!(third);
+	assert!(let Err(_) = tokio::time::timeout(Duration::from_millis(20), &mut third).await);
+
+	// The peer consumes a file descriptor and sends an acknowledgement.
+	let mut payload = Vec::with_capacity(16);
+	let mut fds = Vec::new();
+	assert!(let Ok((2, false)) = b.recv_into(&mut payload, &mut fds).await);
+	assert!(fds.len() == 1);
+	assert!(let Ok(3) = b.send(b"ack").await);
+
+	let mut buffer = [0; 16];
+	assert!(let Ok(3) = a.recv(&mut buffer).await);
+	a.acknowledge_fds(1);
+	assert!(let Ok(Ok(2)) = tokio::time::timeout(Duration::from_secs(1), third).await);
+	assert!(a.fds_in_flight() == 2);
+
+	// Sending more file descriptors than the limit at once is an error.
+	let mut control = [0; 64];
+	let mut cmsg = SocketAncillary::new(&mut control);
+	assert!(cmsg.add_fds(&[file.as_raw_fd(); 3]));
+	let_assert!(Err(e) = a.send_vectored_with_ancillary(&[IoSlice::new(b"fds")], &mut cmsg).await);
+	assert!(e.kind() == std::io::ErrorKind::InvalidInput);
+}
+
+#[tokio::test]
+#[cfg(any(target_os = "android", target_os = "linux"))]
+async fn control_builder() {
+	use tokio_seqpacket::ancillary::{AncillaryAddError, ControlBuilder, SocketCred};
+
+	let_assert!(Ok(file) = tempfile());
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	let_assert!(Ok(mut config) = b.config());
+	config.pass_credentials = true;
+	assert!(let Ok(()) = b.apply_config(&config));
+
+	let mut cred = SocketCred::new();
+	cred.set_pid(std::process::id() as libc::pid_t);
+	cred.set_uid(unsafe { libc::getuid() });
+	cred.set_gid(unsafe { libc::getgid() });
+
+	let fds = [file.as_raw_fd(); 2];
+	let builder = ControlBuilder::new().fds(&fds).creds(cred.clone());
+
+	// If the control messages do not fit, nothing is added.
+	let mut control = [0; 32];
+	let mut cmsg = SocketAncillary::new(&mut control);
+	let_assert!(Err(AncillaryAddError::BufferFull { needed, available: 32 }) = builder.build_into(&mut cmsg));
+	assert!(builder.space() == Ok(needed));
+	assert!(needed > 32);
+	assert!(cmsg.is_empty());
+
+	let mut control = [0; 128];
+	let mut cmsg = SocketAncillary::new(&mut control);
+	assert!(let Ok(()) = builder.build_into(&mut cmsg));
+	assert!(let Ok(4) = a.send_vectored_with_ancillary(&[IoSlice::new(b"both")], &mut cmsg).await);
+
+	let mut control = [0; 128];
+	let mut cmsg = SocketAncillary::new(&mut control);
+	let mut read_buf = [0u8; 16];
+	assert!(let Ok(4) = b.recv_vectored_with_ancillary(&mut [IoSliceMut::new(&mut read_buf)], &mut cmsg).await);
+
+	let mut fd_count = 0;
+	let mut received_cred = None;
+	for message in cmsg.messages() {
+		match message {
+			Ok(AncillaryData::ScmRights(fds)) => {
+				for fd in fds {
+					drop(unsafe { std::fs::File::from_raw_fd(fd) });
+					fd_count += 1;
+				}
+			},
+			Ok(AncillaryData::ScmCredentials(mut creds)) => received_cred = creds.next(),
+			Ok(_) => panic!("unexpected control message"),
+			Err(_) => panic!("failed to parse control message"),
+		}
+	}
+	assert!(fd_count == 2);
+	let_assert!(Some(received_cred) = received_cred);
+	assert!(received_cred.get_pid() == cred.get_pid());
+}
+
+#[tokio::test]
+async fn received_fds_stay_open() {
+	let_assert!(Ok(file) = tempfile());
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+
+	let mut control = [0; 64];
+	let mut cmsg = SocketAncillary::new(&mut control);
+	assert!(cmsg.add_fds(&[file.as_raw_fd()]));
+	assert!(let Ok(2) = a.send_vectored_with_ancillary(&[IoSlice::new(b"fd")], &mut cmsg).await);
+
+	let mut control = [0; 64];
+	let mut cmsg = SocketAncillary::new(&mut control);
+	let mut read_buf = [0u8; 16];
+	assert!(let Ok(2) = b.recv_vectored_with_ancillary(&mut [IoSliceMut::new(&mut read_buf)], &mut cmsg).await);
+	let mut raw_fds = Vec::new();
+	for message in cmsg.messages() {
+		let_assert!(Ok(AncillaryData::ScmRights(fds)) = message);
+		raw_fds.extend(fds);
+	}
+	drop(cmsg);
+
+	// The crate never closes received file descriptors: that is up to the caller.
+	assert!(raw_fds.len() == 1);
+	for fd in raw_fds {
+		assert!(unsafe { libc::fcntl(fd, libc::F_GETFD) } != -1);
+		drop(unsafe { std::fs::File::from_raw_fd(fd) });
+	}
+}
+
+#[tokio::test]
+async fn send_and_close_fd() {
+	use std::os::unix::io::OwnedFd;
+	use tokio_seqpacket::FdNotSent;
+
+	fn pipe() -> (std::fs::File, OwnedFd) {
+		let mut fds = [0; 2];
+		assert!(unsafe { libc::pipe(fds.as_mut_ptr()) } == 0);
+		unsafe { (std::fs::File::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) }
+	}
+
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	let (mut read_end, write_end) = pipe();
+	assert!(let Ok(4) = a.send_and_close_fd(b"pipe", write_end).await);
+
+	let mut payload = Vec::with_capacity(16);
+	let mut fds = Vec::new();
+	assert!(let Ok((4, false)) = b.recv_into(&mut payload, &mut fds).await);
+	assert!(fds.len() == 1);
+	drop(fds);
+
+	// The only remaining write end was closed, so the read end sees the end of file.
+	let mut contents = Vec::new();
+	assert!(let Ok(0) = read_end.read_to_end(&mut contents));
+
+	// If sending fails, the file descriptor is returned in the error.
+	drop(b);
+	let (mut read_end, write_end) = pipe();
+	let_assert!(Err(e) = a.send_and_close_fd(b"pipe", write_end).await);
+	assert!(e.kind() == std::io::ErrorKind::BrokenPipe);
+	let_assert!(Some(inner) = e.into_inner());
+	let_assert!(Ok(not_sent) = inner.downcast::<FdNotSent>());
+	let mut write_end = std::fs::File::from(not_sent.into_fd());
+	assert!(let Ok(()) = write_end.write_all(b"still open"));
+	drop(write_end);
+	assert!(let Ok(10) = read_end.read_to_end(&mut contents));
+}
+
+/// Test that `take_fds()` does not take file descriptors that were added for sending.
+#[test]
+fn take_fds_ignores_added_fds() {
+	use tokio_seqpacket::ancillary::SocketAncillaryBuf;
+
+	let_assert!(Ok(file) = tempfile());
+	let mut control = [0; 64];
+	let mut cmsg = SocketAncillary::new(&mut control);
+	assert!(cmsg.add_fds(&[file.as_raw_fd()]));
+	assert!(cmsg.take_fds().count() == 0);
+
+	let mut cmsg = SocketAncillaryBuf::from_fds([file.as_raw_fd()]);
+	assert!(cmsg.take_fds().count() == 0);
+
+	// The file descriptor is still open.
+	assert!(unsafe { libc::fcntl(file.as_raw_fd(), libc::F_GETFD) } != -1);
+}
+
+#[tokio::test]
+async fn take_fds_closes_unconsumed() {
+	use std::os::unix::io::OwnedFd;
+
+	fn pipe() -> (std::fs::File, OwnedFd) {
+		let mut fds = [0; 2];
+		assert!(unsafe { libc::pipe(fds.as_mut_ptr()) } == 0);
+		unsafe { (std::fs::File::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) }
+	}
+
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	let pipes = [pipe(), pipe(), pipe()];
+	let write_ends: Vec<_> = pipes.iter().map(|(_, write_end)| write_end.as_raw_fd()).collect();
+
+	let mut control = [0; 64];
+	let mut cmsg = SocketAncillary::new(&mut control);
+	assert!(cmsg.add_fds(&write_ends));
+	assert!(let Ok(5) = a.send_vectored_with_ancillary(&[IoSlice::new(b"pipes")], &mut cmsg).await);
+	let mut read_ends: Vec<_> = pipes.into_iter().map(|(read_end, _)| read_end).collect();
+
+	let mut control = [0; 64];
+	let mut cmsg = SocketAncillary::new(&mut control);
+	let mut read_buf = [0u8; 16];
+	assert!(let Ok(5) = b.recv_vectored_with_ancillary(&mut [IoSliceMut::new(&mut read_buf)], &mut cmsg).await);
+
+	// Take only the first two file descriptors: the third must be closed when the iterator is dropped.
+	let taken: Vec<OwnedFd> = cmsg.take_fds().take(2).collect();
+	assert!(taken.len() == 2);
+
+	// Taking the file descriptors again must not yield (and later close) them a second time.
+	assert!(cmsg.take_fds().count() == 0);
+
+	for (fd, read_end) in taken.into_iter().zip(&mut read_ends) {
+		let mut write_end = std::fs::File::from(fd);
+		assert!(let Ok(()) = write_end.write_all(b"x"));
+		drop(write_end);
+		let mut data = Vec::new();
+		assert!(let Ok(1) = read_end.read_to_end(&mut data));
+	}
+
+	// All write ends of the last pipe are closed, so reading gives EOF instead of blocking.
+	let mut data = Vec::new();
+	assert!(let Ok(0) = read_ends[2].read_to_end(&mut data));
+}
+
+#[tokio::test]
+async fn scatter_recv_with_ancillary() {
+	let_assert!(Ok(file) = tempfile());
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+
+	let mut cmsg = [0; 64];
+	let mut cmsg = SocketAncillary::new(&mut cmsg);
+	assert!(cmsg.add_fds(&[file.as_raw_fd()]));
+	assert!(let Ok(11) = a.send_vectored_with_ancillary(&[IoSlice::new(b"HEAD"), IoSlice::new(b"payload")], &mut cmsg).await);
+
+	// Scatter a fixed size header and a variable payload into separate buffers.
+	let mut header = [0u8; 4];
+	let mut payload = [0u8; 16];
+	let mut cmsg = [0; 64];
+	let mut cmsg = SocketAncillary::new(&mut cmsg);
+	let mut buffers = [IoSliceMut::new(&mut header), IoSliceMut::new(&mut payload)];
+	assert!(let Ok(11) = b.recv_vectored_with_ancillary(&mut buffers, &mut cmsg).await);
+	assert!(!cmsg.truncated());
+	assert!(cmsg.take_fds().count() == 1);
+	assert!(&header == b"HEAD");
+	assert!(&payload[..7] == b"payload");
+}
+
+#[tokio::test]
+async fn gather_send_with_ancillary() {
+	let_assert!(Ok(file) = tempfile());
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+
+	// Header and body go out as a single datagram together with the file descriptor.
+	let mut cmsg = [0; 64];
+	let mut cmsg = SocketAncillary::new(&mut cmsg);
+	assert!(cmsg.add_fds(&[file.as_raw_fd()]));
+	assert!(let Ok(9) = a.send_vectored_with_ancillary(&[IoSlice::new(b"HDR"), IoSlice::new(b"body!!")], &mut cmsg).await);
+
+	let mut read_buf = [0u8; 64];
+	let mut cmsg = [0; 64];
+	let mut cmsg = SocketAncillary::new(&mut cmsg);
+	assert!(let Ok(9) = b.recv_vectored_with_ancillary(&mut [IoSliceMut::new(&mut read_buf)], &mut cmsg).await);
+	assert!(&read_buf[..9] == b"HDRbody!!");
+	assert!(cmsg.take_fds().count() == 1);
+
+	// A datagram larger than the send buffer is rejected instead of truncated.
+	let_assert!(Ok(send_buffer_size) = a.config().map(|config| config.send_buffer_size));
+	let large = vec![0u8; send_buffer_size];
+	let mut cmsg = [0; 64];
+	let mut cmsg = SocketAncillary::new(&mut cmsg);
+	assert!(cmsg.add_fds(&[file.as_raw_fd()]));
+	let_assert!(
+		Err(e) = a
+			.send_vectored_with_ancillary(&[IoSlice::new(b"HDR"), IoSlice::new(&large)], &mut cmsg)
+			.await
+	);
+	assert!(e.raw_os_error() == Some(libc::EMSGSIZE));
+}
+
+#[tokio::test]
+async fn recv_vectored_with_fd_count() {
+	let files: Vec<_> = (0..3).map(|_| tempfile().unwrap()).collect();
+	let fds: Vec<_> = files.iter().map(|file| file.as_raw_fd()).collect();
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+
+	let mut cmsg = [0; 64];
+	let mut cmsg = SocketAncillary::new(&mut cmsg);
+	assert!(cmsg.add_fds(&fds));
+	assert!(let Ok(11) = a.send_vectored_with_ancillary(&[IoSlice::new(b"HEADpayload")], &mut cmsg).await);
+
+	let mut header = [0u8; 4];
+	let mut payload = [0u8; 16];
+	let mut cmsg = [0; 64];
+	let mut cmsg = SocketAncillary::new(&mut cmsg);
+	let mut buffers = [IoSliceMut::new(&mut header), IoSliceMut::new(&mut payload)];
+	assert!(let Ok((11, 3)) = b.recv_vectored_with_fd_count(&mut buffers, &mut cmsg).await);
+	assert!(cmsg.take_fds().count() == 3);
+	assert!(&header == b"HEAD");
+	assert!(&payload[..7] == b"payload");
+}
+
+#[tokio::test]
+async fn reserve_fds() {
+	use tokio_seqpacket::ancillary::SocketAncillaryBuf;
+
+	let files: Vec<_> = (0..7).map(|_| tempfile().unwrap()).collect();
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+
+	let mut cmsg: SocketAncillaryBuf = files.iter().map(|file| file.as_raw_fd()).collect();
+	assert!(let Ok(5) = a.send_vectored_with_ancillary(&[IoSlice::new(b"batch")], &mut cmsg).await);
+
+	// Grow an owned buffer to exactly fit the announced number of file descriptors.
+	let mut cmsg = SocketAncillaryBuf::new();
+	cmsg.reserve_fds(7);
+	assert!(cmsg.capacity() == ancillary_space(7));
+	cmsg.reserve_fds(1);
+	assert!(cmsg.capacity() == ancillary_space(7));
+
+	let mut read_buf = [0u8; 16];
+	assert!(let Ok(5) = b.recv_vectored_with_ancillary(&mut [IoSliceMut::new(&mut read_buf)], &mut cmsg).await);
+	assert!(!cmsg.truncated());
+	assert!(cmsg.take_fds().count() == 7);
+}
+
+#[test]
+#[cfg(any(target_os = "android", target_os = "linux"))]
+fn owned_add_creds() {
+	use tokio_seqpacket::ancillary::{SocketAncillaryBuf, SocketCred};
+
+	let mut cmsg = SocketAncillaryBuf::new();
+	assert!(cmsg.add_creds(&[SocketCred::new()]));
+	assert!(!cmsg.add_creds(&[SocketCred::new(), SocketCred::new()]));
+	let mut messages = cmsg.messages();
+	assert!(let Some(Ok(AncillaryData::ScmCredentials(_))) = messages.next());
+	assert!(let None = messages.next());
+}
+
+#[tokio::test]
+async fn recv_classified_fds() {
+	use tokio_seqpacket::ancillary::FdType;
+
+	let mut pipe = [0; 2];
+	assert!(unsafe { libc::pipe(pipe.as_mut_ptr()) } == 0);
+	let (read_end, write_end) = unsafe { (std::fs::File::from_raw_fd(pipe[0]), std::fs::File::from_raw_fd(pipe[1])) };
+	let_assert!(Ok((c, _d)) = UnixSeqpacket::pair());
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+
+	let mut cmsg = [0; 64];
+	let mut cmsg = SocketAncillary::new(&mut cmsg);
+	assert!(cmsg.add_fds(&[read_end.as_raw_fd(), c.as_raw_fd()]));
+	assert!(let Ok(5) = a.send_vectored_with_ancillary(&[IoSlice::new(b"mixed")], &mut cmsg).await);
+	drop(write_end);
+
+	let mut buffer = [0u8; 16];
+	let_assert!(Ok((payload, fds)) = b.recv_classified_fds(&mut buffer).await);
+	assert!(payload == b"mixed");
+	let types: Vec<FdType> = fds.iter().map(|(_, fd_type)| *fd_type).collect();
+	assert!(types == [FdType::Fifo, FdType::Socket]);
+}
+
+/// Test that truncation of the data and of the ancillary data are reported independently.
+#[tokio::test]
+#[cfg(any(target_os = "android", target_os = "linux"))]
+async fn recv_sized_with_ancillary() {
+	use tokio_seqpacket::ancillary::cmsg_space_for_fds;
+
+	let_assert!(Ok(file) = tempfile());
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+
+	let mut cmsg = [0; 64];
+	let mut cmsg = SocketAncillary::new(&mut cmsg);
+	cmsg.add_fds(&[file.as_raw_fd(); 4]);
+	for _ in 0..3 {
+		assert!(let Ok(20) = a.send_vectored_with_ancillary(&[IoSlice::new(&[1; 20])], &mut cmsg).await);
+	}
+
+	// Only the data is truncated.
+	let mut read_buf = [0u8; 10];
+	let mut control = [0; 64];
+	let mut received = SocketAncillary::new(&mut control);
+	let_assert!(
+		Ok(sized) = b
+			.recv_vectored_with_ancillary_sized(&mut [IoSliceMut::new(&mut read_buf)], &mut received)
+			.await
+	);
+	assert!((sized.copied, sized.full_len) == (10, 20));
+	assert!(sized.is_truncated());
+	assert!(!received.truncated());
+	assert!(received.take_fds().count() == 4);
+
+	// Only the ancillary data is truncated.
+	let mut read_buf = [0u8; 32];
+	let mut control = vec![0; cmsg_space_for_fds(1)];
+	let mut received = SocketAncillary::new(&mut control);
+	let_assert!(
+		Ok(sized) = b
+			.recv_vectored_with_ancillary_sized(&mut [IoSliceMut::new(&mut read_buf)], &mut received)
+			.await
+	);
+	assert!((sized.copied, sized.full_len) == (20, 20));
+	assert!(!sized.is_truncated());
+	assert!(received.truncated());
+	drop(received.take_fds());
+
+	// Both are truncated.
+	let mut read_buf = [0u8; 5];
+	let mut received = SocketAncillary::new(&mut []);
+	let_assert!(
+		Ok(sized) = b
+			.recv_vectored_with_ancillary_sized(&mut [IoSliceMut::new(&mut read_buf)], &mut received)
+			.await
+	);
+	assert!((sized.copied, sized.full_len) == (5, 20));
+	assert!(received.truncated());
+}
+
+/// Test peeking a datagram with file descriptors.
+#[tokio::test]
+async fn peek_with_ancillary() {
+	let_assert!(Ok(file) = tempfile());
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+
+	let mut cmsg = [0; 64];
+	let mut cmsg = SocketAncillary::new(&mut cmsg);
+	cmsg.add_fds(&[file.as_raw_fd()]);
+	assert!(let Ok(5) = a.send_vectored_with_ancillary(&[IoSlice::new(b"Peek!")], &mut cmsg).await);
+
+	// Every peek and the final receive get their own copy of the file descriptor.
+	for _ in 0..3 {
+		let mut read_buf = [0u8; 64];
+		let mut control = [0; 64];
+		let mut received = SocketAncillary::new(&mut control);
+		assert!(let Ok(5) = b.peek_vectored_with_ancillary(&mut [IoSliceMut::new(&mut read_buf)], &mut received).await);
+		assert!(&read_buf[..5] == b"Peek!");
+		assert!(received.take_fds().count() == 1);
+	}
+
+	let mut read_buf = [0u8; 64];
+	let mut control = [0; 64];
+	let mut received = SocketAncillary::new(&mut control);
+	assert!(let Ok(5) = b.recv_vectored_with_ancillary(&mut [IoSliceMut::new(&mut read_buf)], &mut received).await);
+	assert!(received.take_fds().count() == 1);
+}
+
+/// Test that received file descriptors have the `close-on-exec` flag set.
+#[tokio::test]
+async fn received_fd_is_cloexec() {
+	let mut pipe = [-1; 2];
+	assert!(unsafe { libc::pipe(pipe.as_mut_ptr()) } == 0);
+	let read_end = unsafe { std::os::unix::io::OwnedFd::from_raw_fd(pipe[0]) };
+	let _write_end = unsafe { std::os::unix::io::OwnedFd::from_raw_fd(pipe[1]) };
+	assert!(unsafe { libc::fcntl(read_end.as_raw_fd(), libc::F_GETFD) } & libc::FD_CLOEXEC == 0);
+
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	let mut cmsg = [0; 64];
+	let mut cmsg = SocketAncillary::new(&mut cmsg);
+	cmsg.add_fds(&[read_end.as_raw_fd()]);
+	assert!(let Ok(4) = a.send_vectored_with_ancillary(&[IoSlice::new(b"pipe")], &mut cmsg).await);
+
+	let mut cmsg = [0; 64];
+	let mut cmsg = SocketAncillary::new(&mut cmsg);
+	let mut read_buf = [0u8; 64];
+	assert!(let Ok(4) = b.recv_vectored_with_ancillary(&mut [IoSliceMut::new(&mut read_buf)], &mut cmsg).await);
+
+	let mut cmsgs = cmsg.messages();
+	let_assert!(Some(Ok(AncillaryData::ScmRights(mut fds))) = cmsgs.next());
+	let_assert!(Some(fd) = fds.next());
+	let fd = unsafe { std::os::unix::io::OwnedFd::from_raw_fd(fd) };
+	assert!(unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_GETFD) } & libc::FD_CLOEXEC != 0);
+}
+
+/// Test sending more file descriptors than fit in a single datagram.
+#[tokio::test]
+async fn send_fds_chunked() {
+	use tokio_seqpacket::ancillary::SocketAncillaryBuf;
+
+	let_assert!(Ok(file) = tempfile());
+	let fds = vec![file.as_raw_fd(); 300];
+
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	assert!(let Ok(2) = a.send_fds_chunked(&fds).await);
+
+	let mut received = Vec::new();
+	loop {
+		let mut ancillary = SocketAncillaryBuf::new();
+		ancillary.reserve_fds(253);
+		let mut more = [0u8; 1];
+		assert!(let Ok(1) = b.recv_vectored_with_ancillary(&mut [IoSliceMut::new(&mut more)], &mut ancillary).await);
+		assert!(!ancillary.truncated());
+		for message in ancillary.messages() {
+			let_assert!(Ok(AncillaryData::ScmRights(fds)) = message);
+			received.extend(fds.map(|fd| unsafe { std::os::unix::io::OwnedFd::from_raw_fd(fd) }));
+		}
+		if more[0] == 0 {
+			break;
+		}
+	}
+	assert!(received.len() == 300);
+
+	// Without file descriptors, a single datagram is sent.
+	assert!(let Ok(1) = a.send_fds_chunked(&[]).await);
+	let mut more = [0u8; 1];
+	assert!(let Ok(1) = b.recv(&mut more).await);
+	assert!(more[0] == 0);
+}
+
+/// Test receiving file descriptors into a fixed array of slots.
+#[tokio::test]
+async fn recv_fds_into_slots() {
+	let_assert!(Ok(file) = tempfile());
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+
+	async fn send_fds(socket: &UnixSeqpacket, fds: &[std::os::unix::io::RawFd]) {
+		let mut control = [0; 128];
+		let mut cmsg = SocketAncillary::new(&mut control);
+		assert!(cmsg.add_fds(fds));
+		assert!(let Ok(3) = socket.send_vectored_with_ancillary(&[IoSlice::new(b"fds")], &mut cmsg).await);
+	}
+
+	let mut buffer = [0u8; 16];
+	let mut slots: [Option<std::os::unix::io::OwnedFd>; 4] = Default::default();
+
+	send_fds(&a, &[file.as_raw_fd(); 2]).await;
+	assert!(let Ok((3, 2)) = b.recv_fds_into(&mut buffer, &mut slots).await);
+	assert!(&buffer[..3] == b"fds");
+	assert!(slots[0].is_some());
+	assert!(slots[1].is_some());
+	assert!(slots[2].is_none());
+	assert!(slots[3].is_none());
+
+	// Excess file descriptors are closed, but they are included in the count.
+	send_fds(&a, &[file.as_raw_fd(); 5]).await;
+	assert!(let Ok((3, 5)) = b.recv_fds_into(&mut buffer, &mut slots).await);
+	assert!(slots.iter().all(|slot| slot.is_some()));
+
+	// Slots are cleared before receiving.
+	assert!(let Ok(3) = a.send(b"fds").await);
+	assert!(let Ok((3, 0)) = b.recv_fds_into(&mut buffer, &mut slots).await);
+	assert!(slots.iter().all(|slot| slot.is_none()));
+}
+
+/// Test that recv_fds_into() reports file descriptors lost to a small control buffer.
+#[tokio::test]
+async fn recv_fds_into_control_truncated() {
+	use tokio_seqpacket::ControlTruncated;
+
+	let_assert!(Ok(file) = tempfile());
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	b.set_max_control_bytes(32);
+
+	let mut control = [0; 256];
+	let mut cmsg = SocketAncillary::new(&mut control);
+	assert!(cmsg.add_fds(&[file.as_raw_fd(); 20]));
+	assert!(let Ok(3) = a.send_vectored_with_ancillary(&[IoSlice::new(b"fds")], &mut cmsg).await);
+
+	let mut buffer = [0u8; 16];
+	let mut slots: [Option<std::os::unix::io::OwnedFd>; 4] = Default::default();
+	let_assert!(Err(e) = b.recv_fds_into(&mut buffer, &mut slots).await);
+	assert!(e.kind() == std::io::ErrorKind::InvalidData);
+	let_assert!(Some(truncated) = e.get_ref().and_then(|e| e.downcast_ref::<ControlTruncated>()));
+	assert!(truncated.capacity() == 32);
+}
+
+/// Test that the file descriptor helpers handle datagrams without file descriptors.
+#[tokio::test]
+async fn fd_helpers_without_fds() {
+	for passcred in [false, true] {
+		let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+		#[cfg(any(target_os = "android", target_os = "linux"))]
+		assert!(let Ok(()) = b.set_passcred(passcred));
+		#[cfg(not(any(target_os = "android", target_os = "linux")))]
+		let _ = passcred;
+
+		assert!(let Ok(5) = a.send(b"plain").await);
+		let mut payload = Vec::with_capacity(16);
+		let mut fds = Vec::new();
+		assert!(let Ok((5, false)) = b.recv_into(&mut payload, &mut fds).await);
+		assert!(payload == b"plain");
+		assert!(fds.is_empty());
+
+		assert!(let Ok(5) = a.send(b"plain").await);
+		let mut buffer = [0u8; 16];
+		let mut slots: [Option<std::os::unix::io::OwnedFd>; 2] = Default::default();
+		assert!(let Ok((5, 0)) = b.recv_fds_into(&mut buffer, &mut slots).await);
+		assert!(&buffer[..5] == b"plain");
+		assert!(slots.iter().all(|slot| slot.is_none()));
+
+		assert!(let Ok(5) = a.send(b"plain").await);
+		let_assert!(Ok((payload, classified)) = b.recv_classified_fds(&mut buffer).await);
+		assert!(payload == b"plain");
+		assert!(classified.is_empty());
+	}
+}
+
+/// Test receiving kernel timestamps together with file descriptors.
+#[tokio::test]
+#[cfg(any(target_os = "android", target_os = "linux"))]
+async fn receive_timestamp() {
+	let_assert!(Ok(file) = tempfile());
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	assert!(let Ok(false) = b.receive_timestamp());
+	assert!(let Ok(()) = b.set_receive_timestamp(true));
+	assert!(let Ok(true) = b.receive_timestamp());
+
+	let before = std::time::SystemTime::now()
+		.duration_since(std::time::UNIX_EPOCH)
+		.unwrap();
+	let mut control = [0; 64];
+	let mut cmsg = SocketAncillary::new(&mut control);
+	assert!(cmsg.add_fds(&[file.as_raw_fd()]));
+	assert!(let Ok(4) = a.send_vectored_with_ancillary(&[IoSlice::new(b"time")], &mut cmsg).await);
+	let after = std::time::SystemTime::now()
+		.duration_since(std::time::UNIX_EPOCH)
+		.unwrap();
+
+	let mut control = [0; 128];
+	let mut cmsg = SocketAncillary::new(&mut control);
+	let mut read_buf = [0u8; 16];
+	assert!(let Ok(4) = b.recv_vectored_with_ancillary(&mut [IoSliceMut::new(&mut read_buf)], &mut cmsg).await);
+	assert!(!cmsg.truncated());
+
+	let mut timestamp = None;
+	let mut fd_count = 0;
+	for message in cmsg.messages() {
+		match message {
+			Ok(AncillaryData::Timestamp(time)) => timestamp = Some(time),
+			Ok(AncillaryData::ScmRights(fds)) => fd_count += fds.count(),
+			_ => panic!("unexpected control message"),
+		}
+	}
+	drop(cmsg.take_fds());
+
+	assert!(fd_count == 1);
+	let_assert!(Some(timestamp) = timestamp);
+	assert!(timestamp >= before);
+	assert!(timestamp <= after);
+}
+
+/// Test that `SCM_CREDS` messages carry the credentials of the sender, filled in by the kernel.
+#[tokio::test]
+#[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
+async fn pass_creds_bsd() {
+	use tokio_seqpacket::ancillary::SocketCred;
+
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+
+	// The kernel replaces whatever credentials we put in the message.
+	let mut control = [0; 256];
+	let mut cmsg = SocketAncillary::new(&mut control);
+	assert!(cmsg.add_creds(&[SocketCred::from_parts(1, 2, 3)]));
+	assert!(let Ok(5) = a.send_vectored_with_ancillary(&[IoSlice::new(b"creds")], &mut cmsg).await);
+
+	let mut control = [0; 256];
+	let mut cmsg = SocketAncillary::new(&mut control);
+	let mut read_buf = [0u8; 16];
+	assert!(let Ok(5) = b.recv_vectored_with_ancillary(&mut [IoSliceMut::new(&mut read_buf)], &mut cmsg).await);
+
+	let mut messages = cmsg.messages();
+	let_assert!(Some(Ok(AncillaryData::ScmCredentials(mut creds))) = messages.next());
+	let_assert!(Some(cred) = creds.next());
+	assert!(cred.get_pid() == unsafe { libc::getpid() });
+	assert!(cred.get_uid() == unsafe { libc::geteuid() });
+	assert!(cred.get_gid() == unsafe { libc::getegid() });
+}
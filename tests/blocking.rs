@@ -0,0 +1,109 @@
+use assert2::{assert, let_assert};
+use std::io::{IoSlice, IoSliceMut};
+use std::time::Duration;
+use tokio_seqpacket::blocking::UnixSeqpacket;
+
+/// Test a simple send and recv call on blocking sockets.
+#[test]
+fn send_recv() {
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	assert!(let Ok(12) = a.send(b"Hello world!"));
+
+	let mut buffer = [0u8; 128];
+	assert!(let Ok(12) = b.recv(&mut buffer));
+	assert!(&buffer[..12] == b"Hello world!");
+
+	assert!(let Ok(10) = b.send_vectored(&[IoSlice::new(b"Hello"), IoSlice::new(b" back")]));
+	let (first, second) = buffer.split_at_mut(5);
+	assert!(let Ok(10) = a.recv_vectored(&mut [IoSliceMut::new(first), IoSliceMut::new(second)]));
+	assert!(&buffer[..10] == b"Hello back");
+}
+
+/// Test the addresses and credentials of blocking sockets.
+#[test]
+fn addresses() {
+	let dir = tempfile::tempdir().unwrap();
+	let path = dir.path().join("missing.sock");
+
+	let_assert!(Ok((a, _b)) = UnixSeqpacket::pair());
+	let_assert!(Ok(local) = a.local_addr());
+	assert!(local.is_unnamed());
+	let_assert!(Ok(peer) = a.peer_addr());
+	assert!(peer.is_unnamed());
+	let_assert!(Ok(cred) = a.peer_cred());
+	assert!(cred.uid() == unsafe { libc::getuid() });
+
+	// Connecting to a missing socket fails.
+	let_assert!(Err(e) = UnixSeqpacket::connect(&path));
+	assert!(e.kind() == std::io::ErrorKind::NotFound);
+}
+
+/// Test timeouts, non-blocking mode and cloning of blocking sockets.
+#[test]
+fn timeouts_and_clone() {
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+
+	assert!(let Ok(None) = b.read_timeout());
+	assert!(let Ok(()) = b.set_read_timeout(Some(Duration::from_millis(10))));
+	assert!(let Ok(Some(_)) = b.read_timeout());
+	let mut buffer = [0u8; 16];
+	let_assert!(Err(e) = b.recv(&mut buffer));
+	assert!(matches!(
+		e.kind(),
+		std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+	));
+
+	assert!(let Ok(()) = b.set_read_timeout(None));
+	assert!(let Ok(()) = b.set_nonblocking(true));
+	let_assert!(Err(e) = b.recv(&mut buffer));
+	assert!(e.kind() == std::io::ErrorKind::WouldBlock);
+
+	let_assert!(Ok(c) = a.try_clone());
+	assert!(let Ok(5) = c.send(b"clone"));
+	assert!(let Ok(5) = b.recv(&mut buffer));
+	assert!(&buffer[..5] == b"clone");
+	assert!(let Ok(None) = a.take_error());
+}
+
+/// Test converting a blocking socket to an async socket.
+#[tokio::test]
+async fn into_async() {
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	let_assert!(Ok(b) = b.into_async());
+
+	let task = std::thread::spawn(move || a.send(b"Hello async!"));
+	let mut buffer = [0u8; 128];
+	assert!(let Ok(12) = b.recv(&mut buffer).await);
+	assert!(let Ok(Ok(12)) = task.join());
+}
+
+/// Test that a receive that is interrupted by a signal is retried.
+#[test]
+fn recv_retries_on_eintr() {
+	use std::os::unix::thread::JoinHandleExt;
+
+	extern "C" fn ignore_signal(_signal: libc::c_int) {}
+
+	// Install a handler without `SA_RESTART`, so the blocking `recvmsg()` fails with `EINTR`.
+	unsafe {
+		let mut action: libc::sigaction = std::mem::zeroed();
+		action.sa_sigaction = ignore_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+		action.sa_flags = 0;
+		assert!(libc::sigaction(libc::SIGUSR2, &action, std::ptr::null_mut()) == 0);
+	}
+
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	let receiver = std::thread::spawn(move || {
+		let mut buffer = [0u8; 128];
+		let len = b.recv(&mut buffer)?;
+		Ok::<_, std::io::Error>(buffer[..len].to_vec())
+	});
+
+	std::thread::sleep(Duration::from_millis(50));
+	assert!(unsafe { libc::pthread_kill(receiver.as_pthread_t(), libc::SIGUSR2) } == 0);
+	std::thread::sleep(Duration::from_millis(50));
+	assert!(let Ok(12) = a.send(b"Hello world!"));
+
+	let_assert!(Ok(Ok(data)) = receiver.join());
+	assert!(data == b"Hello world!");
+}
@@ -0,0 +1,33 @@
+#![cfg(feature = "tokio-util")]
+
+use assert2::{assert, let_assert};
+use std::time::Duration;
+use tokio_seqpacket::UnixSeqpacket;
+use tokio_util::sync::CancellationToken;
+
+/// Test that recv_or_cancel() returns when the token is cancelled, without consuming a datagram.
+#[tokio::test]
+async fn recv_or_cancel() {
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	let token = CancellationToken::new();
+
+	tokio::spawn({
+		let token = token.clone();
+		async move {
+			tokio::time::sleep(Duration::from_millis(10)).await;
+			token.cancel();
+		}
+	});
+
+	let mut buffer = [0u8; 128];
+	assert!(let Ok(None) = b.recv_or_cancel(&mut buffer, &token).await);
+
+	// With the token cancelled, a queued datagram is not consumed.
+	assert!(let Ok(12) = a.send(b"Hello world!").await);
+	assert!(let Ok(None) = b.recv_or_cancel(&mut buffer, &token).await);
+
+	// But it can still be received normally.
+	let token = CancellationToken::new();
+	assert!(let Ok(Some(12)) = b.recv_or_cancel(&mut buffer, &token).await);
+	assert!(&buffer[..12] == b"Hello world!");
+}
@@ -0,0 +1,105 @@
+use assert2::{assert, let_assert};
+use std::time::Duration;
+use tokio_seqpacket::UnixSeqpacket;
+
+/// Test that the configuration of one socket can be applied to another.
+#[tokio::test]
+async fn copy_config() {
+	let_assert!(Ok((a, _b)) = UnixSeqpacket::pair());
+	let_assert!(Ok((c, _d)) = UnixSeqpacket::pair());
+
+	let_assert!(Ok(mut config) = a.config());
+	assert!(config.close_on_exec == true);
+	assert!(config.read_timeout == None);
+	assert!(config.write_timeout == None);
+
+	config.send_buffer_size = 32 * 1024;
+	config.recv_buffer_size = 48 * 1024;
+	config.read_timeout = Some(Duration::from_millis(1500));
+	config.write_timeout = Some(Duration::from_secs(3));
+	config.close_on_exec = false;
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	{
+		config.pass_credentials = true;
+	}
+
+	assert!(let Ok(()) = a.apply_config(&config));
+	let_assert!(Ok(config) = a.config());
+	assert!(let Ok(()) = c.apply_config(&config));
+	let_assert!(Ok(copied) = c.config());
+	assert!(copied == config);
+	assert!(config.send_buffer_size == 32 * 1024);
+	assert!(config.recv_buffer_size == 48 * 1024);
+	assert!(config.read_timeout == Some(Duration::from_millis(1500)));
+	assert!(config.write_timeout == Some(Duration::from_secs(3)));
+	assert!(config.close_on_exec == false);
+}
+
+/// Test forcing buffer sizes beyond the system-wide limit.
+#[tokio::test]
+#[cfg(any(target_os = "android", target_os = "linux"))]
+async fn force_buffer_sizes() {
+	fn read_limit(path: &str) -> usize {
+		let_assert!(Ok(limit) = std::fs::read_to_string(path));
+		let_assert!(Ok(limit) = limit.trim().parse());
+		limit
+	}
+
+	let_assert!(Ok((a, _b)) = UnixSeqpacket::pair());
+	let send_size = read_limit("/proc/sys/net/core/wmem_max") + 4096;
+	let recv_size = read_limit("/proc/sys/net/core/rmem_max") + 4096;
+
+	match a.set_send_buffer_size_force(send_size) {
+		Ok(()) => {
+			assert!(let Ok(()) = a.set_recv_buffer_size_force(recv_size));
+			let_assert!(Ok(config) = a.config());
+			assert!(config.send_buffer_size == 2 * send_size);
+			assert!(config.recv_buffer_size == 2 * recv_size);
+		},
+		Err(e) => {
+			// Without CAP_NET_ADMIN, both options are rejected with a clear error.
+			assert!(e.kind() == std::io::ErrorKind::PermissionDenied);
+			assert!(e.to_string().contains("CAP_NET_ADMIN"));
+			let_assert!(Err(e) = a.set_recv_buffer_size_force(recv_size));
+			assert!(e.kind() == std::io::ErrorKind::PermissionDenied);
+		},
+	}
+}
+
+/// Test setting and reading the priority of outgoing datagrams.
+#[tokio::test]
+#[cfg(any(target_os = "android", target_os = "linux"))]
+async fn priority() {
+	let_assert!(Ok((a, _b)) = UnixSeqpacket::pair());
+	assert!(let Ok(()) = a.set_priority(3));
+	assert!(let Ok(3) = a.priority());
+
+	// High priorities require CAP_NET_ADMIN, so only check them when that is available.
+	match a.set_priority(7) {
+		Ok(()) => assert!(let Ok(7) = a.priority()),
+		Err(e) => {
+			assert!(e.kind() == std::io::ErrorKind::PermissionDenied);
+			assert!(e.to_string().contains("CAP_NET_ADMIN"));
+			assert!(let Ok(3) = a.priority());
+		},
+	}
+}
+
+/// Test setting the buffer sizes directly.
+#[tokio::test]
+async fn buffer_sizes() {
+	let_assert!(Ok((a, _b)) = UnixSeqpacket::pair());
+
+	assert!(let Ok(()) = a.set_send_buffer_size(64 * 1024));
+	assert!(let Ok(()) = a.set_recv_buffer_size(96 * 1024));
+
+	// Linux doubles the requested size, so only check that the reported size is at least what we asked for.
+	let_assert!(Ok(send_size) = a.send_buffer_size());
+	let_assert!(Ok(recv_size) = a.recv_buffer_size());
+	assert!(send_size >= 64 * 1024);
+	assert!(recv_size >= 96 * 1024);
+
+	let_assert!(Ok(config) = a.config());
+	assert!(config.send_buffer_size == send_size);
+	assert!(config.recv_buffer_size == recv_size);
+}
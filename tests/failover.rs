@@ -0,0 +1,42 @@
+use assert2::{assert, let_assert};
+use tempfile::tempdir;
+use tokio_seqpacket::{FailoverSocket, UnixSeqpacketListener};
+
+/// Test that the socket fails over to the backup server when the primary dies.
+#[tokio::test]
+async fn failover_to_backup() {
+	let dir = tempdir().unwrap();
+	let primary_path = dir.path().join("primary.sock");
+	let backup_path = dir.path().join("backup.sock");
+	let_assert!(Ok(mut primary) = UnixSeqpacketListener::bind(&primary_path));
+	let_assert!(Ok(mut backup) = UnixSeqpacketListener::bind(&backup_path));
+
+	let_assert!(Ok(mut socket) = FailoverSocket::connect([&primary_path, &backup_path]).await);
+	assert!(socket.current_address() == primary_path);
+
+	let_assert!(Ok(server) = primary.accept().await);
+	assert!(let Ok(5) = socket.send(b"first").await);
+	let mut buf = [0u8; 16];
+	assert!(let Ok(5) = server.recv(&mut buf).await);
+
+	// Kill the primary server.
+	drop(server);
+	drop(primary);
+
+	assert!(let Ok(6) = socket.send(b"second").await);
+	assert!(socket.current_address() == backup_path);
+	let_assert!(Ok(server) = backup.accept().await);
+	assert!(let Ok(6) = server.recv(&mut buf).await);
+	assert!(&buf[..6] == b"second");
+}
+
+/// Test that connecting fails if no address is reachable.
+#[tokio::test]
+async fn no_reachable_address() {
+	let dir = tempdir().unwrap();
+	let_assert!(Err(e) = FailoverSocket::connect([dir.path().join("missing.sock")]).await);
+	assert!(e.kind() == std::io::ErrorKind::NotFound);
+
+	let_assert!(Err(e) = FailoverSocket::connect(Vec::<std::path::PathBuf>::new()).await);
+	assert!(e.kind() == std::io::ErrorKind::InvalidInput);
+}
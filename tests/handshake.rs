@@ -0,0 +1,42 @@
+#![cfg(any(target_os = "android", target_os = "linux"))]
+
+use assert2::{assert, let_assert};
+use std::time::Duration;
+use tokio_seqpacket::UnixSeqpacket;
+
+/// Test that both sides of a handshake agree on the common capabilities.
+#[tokio::test]
+async fn handshake() {
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	let timeout = Duration::from_secs(1);
+
+	let (result_a, result_b) = tokio::join!(a.handshake(0b0111, timeout), b.handshake(0b1101, timeout));
+	let_assert!(Ok(result_a) = result_a);
+	let_assert!(Ok(result_b) = result_b);
+
+	assert!(result_a.capabilities == 0b0101);
+	assert!(result_b.capabilities == 0b0101);
+	assert!(result_a.peer_capabilities == 0b1101);
+	assert!(result_b.peer_capabilities == 0b0111);
+	assert!(result_a.peer_cred.uid() == unsafe { libc::getuid() });
+
+	// SO_PASSCRED is restored afterwards.
+	assert!(let Ok(false) = a.passcred());
+}
+
+/// Test that a handshake times out if the peer does not respond.
+#[tokio::test]
+async fn handshake_timeout() {
+	let_assert!(Ok((a, _b)) = UnixSeqpacket::pair());
+	let_assert!(Err(e) = a.handshake(1, Duration::from_millis(10)).await);
+	assert!(e.kind() == std::io::ErrorKind::TimedOut);
+}
+
+/// Test that a malformed handshake is rejected.
+#[tokio::test]
+async fn handshake_malformed() {
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	assert!(let Ok(5) = b.send(b"Hello").await);
+	let_assert!(Err(e) = a.handshake(1, Duration::from_secs(1)).await);
+	assert!(e.kind() == std::io::ErrorKind::InvalidData);
+}
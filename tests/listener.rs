@@ -33,3 +33,440 @@ async fn unix_seqpacket_listener() {
 
 	assert!(let Ok(()) = server_task.await);
 }
+
+/// Test that we can wait for the credentials of a connected peer.
+#[tokio::test]
+async fn peer_cred_when_ready() {
+	use std::time::Duration;
+
+	let dir = tempdir().unwrap();
+	let path = dir.path().join("listener.sock");
+
+	let_assert!(Ok(mut listener) = UnixSeqpacketListener::bind(&path));
+	let_assert!(Ok(client) = UnixSeqpacket::connect(&path).await);
+	let_assert!(Ok(server) = listener.accept().await);
+
+	let_assert!(Ok(Ok(cred)) = tokio::time::timeout(Duration::from_secs(1), server.peer_cred_when_ready()).await);
+	assert!(cred.uid() == unsafe { libc::getuid() });
+	if let Some(pid) = cred.pid() {
+		assert!(pid != 0);
+		assert!(pid == std::process::id() as libc::pid_t);
+	}
+	drop(client);
+}
+
+/// Test that we can wait for a pending connection without accepting it.
+#[tokio::test]
+async fn readable() {
+	use std::time::Duration;
+
+	let dir = tempdir().unwrap();
+	let path = dir.path().join("listener.sock");
+
+	let_assert!(Ok(mut listener) = UnixSeqpacketListener::bind(&path));
+	assert!(let Err(_) = tokio::time::timeout(Duration::from_millis(10), listener.readable()).await);
+
+	let_assert!(Ok(client) = UnixSeqpacket::connect(&path).await);
+	assert!(let Ok(Ok(())) = tokio::time::timeout(Duration::from_secs(1), listener.readable()).await);
+	// Waiting again does not consume the connection.
+	assert!(let Ok(Ok(())) = tokio::time::timeout(Duration::from_secs(1), listener.readable()).await);
+
+	let_assert!(Ok(Ok(server)) = tokio::time::timeout(Duration::from_secs(1), listener.accept()).await);
+	assert!(let Ok(5) = client.send(b"Hello").await);
+	let mut buffer = [0u8; 16];
+	assert!(let Ok(5) = server.recv(&mut buffer).await);
+}
+
+/// Test binding a listener with a full configuration.
+#[tokio::test]
+async fn bind_configured() {
+	use std::os::unix::fs::PermissionsExt;
+	use tokio_seqpacket::ListenerConfig;
+
+	let dir = tempdir().unwrap();
+	let path = dir.path().join("listener.sock");
+
+	let config = ListenerConfig::new().backlog(4).mode(0o600).unlink_on_drop(true);
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	let config = config.pass_credentials(true);
+
+	let_assert!(Ok(mut listener) = UnixSeqpacketListener::bind_configured(&path, &config));
+	let_assert!(Ok(metadata) = std::fs::metadata(&path));
+	assert!(metadata.permissions().mode() & 0o777 == 0o600);
+
+	let_assert!(Ok(_client) = UnixSeqpacket::connect(&path).await);
+	let_assert!(Ok(server) = listener.accept().await);
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	{
+		let_assert!(Ok(server_config) = server.config());
+		assert!(server_config.pass_credentials == true);
+	}
+	drop(server);
+
+	drop(listener);
+	assert!(!path.exists());
+}
+
+/// Test that a failure after binding does not leave the socket file behind.
+#[test]
+fn bind_configured_cleanup() {
+	use tokio_seqpacket::ListenerConfig;
+
+	let dir = tempdir().unwrap();
+	let path = dir.path().join("listener.sock");
+
+	// Binding to an existing path fails, and must not remove the existing file.
+	let_assert!(Ok(_) = std::fs::write(&path, b""));
+	let_assert!(Err(_) = UnixSeqpacketListener::bind_configured(&path, &ListenerConfig::new()));
+	assert!(path.exists());
+	let_assert!(Ok(()) = std::fs::remove_file(&path));
+
+	// Registering with the tokio runtime panics without a runtime, after the socket was bound.
+	let result = std::panic::catch_unwind(|| UnixSeqpacketListener::bind_configured(&path, &ListenerConfig::new()));
+	assert!(let Err(_) = result);
+	assert!(!path.exists());
+}
+
+/// Test handing off a listener through an inheritable file descriptor.
+#[tokio::test]
+async fn inherit_listener() {
+	let dir = tempdir().unwrap();
+	let path = dir.path().join("listener.sock");
+
+	let_assert!(Ok(listener) = UnixSeqpacketListener::bind(&path));
+	let_assert!(Ok(fd) = listener.into_inheritable());
+	let flags = unsafe { libc::fcntl(fd, libc::F_GETFD) };
+	assert!(flags != -1);
+	assert!(flags & libc::FD_CLOEXEC == 0);
+
+	let_assert!(Ok(mut listener) = unsafe { UnixSeqpacketListener::from_inherited(fd) });
+	let flags = unsafe { libc::fcntl(fd, libc::F_GETFD) };
+	assert!(flags & libc::FD_CLOEXEC != 0);
+
+	let_assert!(Ok(client) = UnixSeqpacket::connect(&path).await);
+	let_assert!(Ok(server) = listener.accept().await);
+	assert!(let Ok(5) = client.send(b"Hello").await);
+	let mut buffer = [0u8; 16];
+	assert!(let Ok(5) = server.recv(&mut buffer).await);
+}
+
+/// Test that connecting to a stream socket returns a clear error.
+#[tokio::test]
+async fn connect_to_stream_socket() {
+	use tokio_seqpacket::SocketTypeMismatch;
+
+	let dir = tempdir().unwrap();
+	let path = dir.path().join("stream.sock");
+	let_assert!(Ok(_listener) = std::os::unix::net::UnixListener::bind(&path));
+
+	let_assert!(Err(e) = UnixSeqpacket::connect(&path).await);
+	assert!(e.kind() == std::io::ErrorKind::InvalidInput);
+	let_assert!(Some(mismatch) = e.get_ref().and_then(|e| e.downcast_ref::<SocketTypeMismatch>()));
+	assert!(mismatch.path() == path);
+	assert!(e.to_string().contains("is not a seqpacket socket"));
+}
+
+/// Test accepting connections together with the credentials of the peer.
+#[tokio::test]
+#[cfg(any(target_os = "android", target_os = "linux"))]
+async fn incoming_with_cred() {
+	let dir = tempdir().unwrap();
+	let path = dir.path().join("listener.sock");
+	let_assert!(Ok(mut listener) = UnixSeqpacketListener::bind(&path));
+
+	let_assert!(Ok(_a) = UnixSeqpacket::connect(&path).await);
+	let_assert!(Ok(_b) = UnixSeqpacket::connect(&path).await);
+
+	let mut incoming = listener.incoming_with_cred();
+	for _ in 0..2 {
+		let_assert!(Some(Ok((_socket, cred))) = incoming.next().await);
+		assert!(cred.get_uid() == unsafe { libc::getuid() });
+		assert!(cred.get_pid() == std::process::id() as libc::pid_t);
+	}
+}
+
+/// Test that accept_many accepts a burst of connections with the peer addresses.
+#[tokio::test]
+#[cfg(any(target_os = "android", target_os = "linux"))]
+async fn accept_many() {
+	use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd};
+
+	/// Connect a socket that is bound to an abstract address first.
+	fn connect_from_abstract(name: &[u8], path: &std::path::Path) -> OwnedFd {
+		fn sockaddr(bytes: &[u8]) -> (libc::sockaddr_un, libc::socklen_t) {
+			let mut address: libc::sockaddr_un = unsafe { std::mem::zeroed() };
+			address.sun_family = libc::AF_UNIX as libc::sa_family_t;
+			for (dest, &byte) in address.sun_path.iter_mut().zip(bytes) {
+				*dest = byte as libc::c_char;
+			}
+			let len = std::mem::size_of::<libc::sa_family_t>() + bytes.len();
+			(address, len as libc::socklen_t)
+		}
+
+		let fd = unsafe { libc::socket(libc::AF_UNIX, libc::SOCK_SEQPACKET | libc::SOCK_CLOEXEC, 0) };
+		assert!(fd >= 0);
+		let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+		let local = [b"\0".as_slice(), name].concat();
+		let (address, len) = sockaddr(&local);
+		assert!(unsafe { libc::bind(fd.as_raw_fd(), &address as *const _ as *const _, len) } == 0);
+		let (address, len) = sockaddr(std::os::unix::ffi::OsStrExt::as_bytes(path.as_os_str()));
+		assert!(unsafe { libc::connect(fd.as_raw_fd(), &address as *const _ as *const _, len) } == 0);
+		fd
+	}
+
+	let dir = tempdir().unwrap();
+	let path = dir.path().join("listener.sock");
+	let_assert!(Ok(mut listener) = UnixSeqpacketListener::bind(&path));
+
+	let names: Vec<Vec<u8>> = (0..3)
+		.map(|i| format!("tokio-seqpacket-accept-many-{}-{i}", std::process::id()).into_bytes())
+		.collect();
+	let clients: Vec<OwnedFd> = names.iter().map(|name| connect_from_abstract(name, &path)).collect();
+
+	let_assert!(Ok(accepted) = listener.accept_many(8).await);
+	assert!(accepted.len() == 3);
+	for ((socket, address), name) in accepted.iter().zip(&names) {
+		assert!(address.as_abstract_name() == Some(name.as_slice()));
+		assert!(let Ok(5) = socket.send(b"hello").await);
+	}
+
+	// Asking for zero connections returns right away.
+	let_assert!(Ok(none) = listener.accept_many(0).await);
+	assert!(none.is_empty());
+	drop(clients);
+}
+
+/// Test that accept_filtered closes rejected connections.
+#[tokio::test]
+#[cfg(any(target_os = "android", target_os = "linux"))]
+async fn accept_filtered() {
+	let dir = tempdir().unwrap();
+	let path = dir.path().join("listener.sock");
+	let_assert!(Ok(mut listener) = UnixSeqpacketListener::bind(&path));
+
+	let_assert!(Ok(rejected) = UnixSeqpacket::connect(&path).await);
+	let_assert!(Ok(allowed) = UnixSeqpacket::connect(&path).await);
+
+	// Simulate a disallowed UID for the first connection.
+	let mut seen = 0;
+	let uid = unsafe { libc::getuid() };
+	let_assert!(
+		Ok((socket, cred)) = listener
+			.accept_filtered(|cred| {
+				seen += 1;
+				let simulated_uid = if seen == 1 { uid + 1 } else { cred.get_uid() };
+				simulated_uid == uid
+			})
+			.await
+	);
+	assert!(seen == 2);
+	assert!(cred.get_uid() == uid);
+
+	// The rejected connection was closed.
+	let mut buf = [0u8; 16];
+	assert!(let Ok(0) = rejected.recv(&mut buf).await);
+
+	assert!(let Ok(5) = socket.send(b"hello").await);
+	assert!(let Ok(5) = allowed.recv(&mut buf).await);
+}
+
+/// Test that we can connect to the socket address of a listener.
+#[tokio::test]
+async fn connect_addr() {
+	let dir = tempdir().unwrap();
+	let path = dir.path().join("listener.sock");
+	let_assert!(Ok(mut listener) = UnixSeqpacketListener::bind(&path));
+	let_assert!(Ok(address) = listener.local_socket_addr());
+	assert!(address.as_pathname() == Some(path.as_path()));
+
+	let_assert!(Ok(client) = UnixSeqpacket::connect_addr(&address).await);
+	let_assert!(Ok(server) = listener.accept().await);
+	assert!(let Ok(5) = client.send(b"hello").await);
+	let mut buf = [0u8; 16];
+	assert!(let Ok(5) = server.recv(&mut buf).await);
+}
+
+/// Test that we can connect to an abstract socket address.
+#[tokio::test]
+#[cfg(any(target_os = "android", target_os = "linux"))]
+async fn connect_addr_abstract() {
+	use std::os::unix::io::{FromRawFd, OwnedFd};
+
+	let name = format!("tokio-seqpacket-connect-addr-{}", std::process::id());
+	let mut address: libc::sockaddr_un = unsafe { std::mem::zeroed() };
+	address.sun_family = libc::AF_UNIX as libc::sa_family_t;
+	for (dest, &byte) in address.sun_path[1..].iter_mut().zip(name.as_bytes()) {
+		*dest = byte as libc::c_char;
+	}
+	let len = std::mem::size_of::<libc::sa_family_t>() + 1 + name.len();
+
+	let fd = unsafe {
+		libc::socket(
+			libc::AF_UNIX,
+			libc::SOCK_SEQPACKET | libc::SOCK_CLOEXEC | libc::SOCK_NONBLOCK,
+			0,
+		)
+	};
+	assert!(fd >= 0);
+	assert!(unsafe { libc::bind(fd, &address as *const _ as *const _, len as libc::socklen_t) } == 0);
+	assert!(unsafe { libc::listen(fd, 8) } == 0);
+	let_assert!(Ok(mut listener) = UnixSeqpacketListener::try_from(unsafe { OwnedFd::from_raw_fd(fd) }));
+
+	let_assert!(Ok(address) = listener.local_socket_addr());
+	assert!(address.as_abstract_name() == Some(name.as_bytes()));
+	let_assert!(Ok(_client) = UnixSeqpacket::connect_addr(&address).await);
+	let_assert!(Ok(_server) = listener.accept().await);
+}
+
+/// Test that accepted connections are closed when they do not receive anything within the idle timeout.
+#[tokio::test]
+async fn idle_timeout() {
+	use std::time::Duration;
+
+	let dir = tempdir().unwrap();
+	let path = dir.path().join("listener.sock");
+
+	let_assert!(Ok(mut listener) = UnixSeqpacketListener::bind(&path));
+	listener.set_idle_timeout(Some(Duration::from_millis(50)));
+	assert!(listener.idle_timeout() == Some(Duration::from_millis(50)));
+
+	// An active client keeps the connection open.
+	let_assert!(Ok(client) = UnixSeqpacket::connect(&path).await);
+	let_assert!(Ok(server) = listener.accept().await);
+	let mut buffer = [0u8; 16];
+	for _ in 0..4 {
+		tokio::time::sleep(Duration::from_millis(30)).await;
+		assert!(let Ok(5) = client.send(b"Hello").await);
+		assert!(let Ok(5) = server.recv(&mut buffer).await);
+	}
+
+	// A silent client is disconnected.
+	let_assert!(
+		Err(e) = tokio::time::timeout(Duration::from_secs(1), server.recv(&mut buffer))
+			.await
+			.unwrap()
+	);
+	assert!(e.kind() == std::io::ErrorKind::TimedOut);
+	assert!(let Ok(0) = client.recv(&mut buffer).await);
+	let_assert!(Err(e) = server.recv(&mut buffer).await);
+	assert!(e.kind() == std::io::ErrorKind::TimedOut);
+}
+
+/// Test that the idle timeout does not close a suspended connection, and starts again when it is resumed.
+#[tokio::test]
+async fn idle_timeout_suspended() {
+	use std::time::Duration;
+
+	let dir = tempdir().unwrap();
+	let path = dir.path().join("listener.sock");
+
+	let_assert!(Ok(mut listener) = UnixSeqpacketListener::bind(&path));
+	listener.set_idle_timeout(Some(Duration::from_millis(50)));
+	let_assert!(Ok(client) = UnixSeqpacket::connect(&path).await);
+	let_assert!(Ok(server) = listener.accept().await);
+
+	let suspended = server.suspend();
+	tokio::time::sleep(Duration::from_millis(100)).await;
+	assert!(let Ok(5) = client.send(b"Hello").await);
+	let mut buffer = [0u8; 16];
+	assert!(let Ok(5) = suspended.recv(&mut buffer));
+
+	let_assert!(Ok(server) = suspended.resume());
+	let_assert!(
+		Err(e) = tokio::time::timeout(Duration::from_secs(1), server.recv(&mut buffer))
+			.await
+			.unwrap()
+	);
+	assert!(e.kind() == std::io::ErrorKind::TimedOut);
+}
+
+/// Test binding and connecting to abstract addresses.
+#[tokio::test]
+#[cfg(any(target_os = "android", target_os = "linux"))]
+async fn bind_connect_abstract() {
+	let name = format!("tokio-seqpacket-bind-abstract-{}", std::process::id());
+	let_assert!(Ok(mut listener) = UnixSeqpacketListener::bind_abstract(name.as_bytes()));
+	let_assert!(Ok(address) = listener.local_socket_addr());
+	assert!(address.as_abstract_name() == Some(name.as_bytes()));
+
+	let_assert!(Ok(client) = UnixSeqpacket::connect_abstract(name.as_bytes()).await);
+	let_assert!(Ok(server) = listener.accept().await);
+	assert!(let Ok(5) = client.send(b"hello").await);
+	let mut buf = [0u8; 16];
+	assert!(let Ok(5) = server.recv(&mut buf).await);
+
+	// An empty name makes the kernel pick a unique address.
+	let_assert!(Ok(mut listener) = UnixSeqpacketListener::bind_abstract(b""));
+	let_assert!(Ok(address) = listener.local_socket_addr());
+	let_assert!(Some(name) = address.as_abstract_name());
+	assert!(!name.is_empty());
+	let_assert!(Ok(_client) = UnixSeqpacket::connect_abstract(name).await);
+	let_assert!(Ok(_server) = listener.accept().await);
+
+	// Names that are too long are rejected.
+	let too_long = [b'a'; 108];
+	let_assert!(Err(e) = UnixSeqpacketListener::bind_abstract(&too_long));
+	assert!(e.kind() == std::io::ErrorKind::InvalidInput);
+	let_assert!(Err(e) = UnixSeqpacket::connect_abstract(&too_long).await);
+	assert!(e.kind() == std::io::ErrorKind::InvalidInput);
+	let_assert!(Ok(_) = tokio_seqpacket::SocketAddr::from_abstract_name(&too_long[..107]));
+}
+
+/// Test the local and peer addresses of connected sockets.
+#[tokio::test]
+async fn local_peer_addr() {
+	let dir = tempdir().unwrap();
+	let path = dir.path().join("listener.sock");
+	let_assert!(Ok(mut listener) = UnixSeqpacketListener::bind(&path));
+
+	let_assert!(Ok(client) = UnixSeqpacket::connect(&path).await);
+	let_assert!(Ok(server) = listener.accept().await);
+	let_assert!(Ok(address) = client.peer_addr());
+	assert!(address.as_pathname() == Some(path.as_path()));
+	let_assert!(Ok(address) = server.local_addr());
+	assert!(address.as_pathname() == Some(path.as_path()));
+	let_assert!(Ok(address) = client.local_addr());
+	assert!(address.is_unnamed());
+	let_assert!(Ok(address) = server.peer_addr());
+	assert!(address.is_unnamed());
+
+	// Sockets from a pair are unnamed.
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	assert!(let Ok(true) = a.local_addr().map(|address| address.is_unnamed()));
+	assert!(let Ok(true) = b.peer_addr().map(|address| address.is_unnamed()));
+}
+
+/// Test the peer address of a socket connected to an automatically bound abstract address.
+#[tokio::test]
+#[cfg(any(target_os = "android", target_os = "linux"))]
+async fn peer_addr_abstract() {
+	let_assert!(Ok(mut listener) = UnixSeqpacketListener::bind_abstract(b""));
+	let_assert!(Ok(address) = listener.local_socket_addr());
+	let_assert!(Some(name) = address.as_abstract_name());
+
+	let_assert!(Ok(client) = UnixSeqpacket::connect_addr(&address).await);
+	let_assert!(Ok(_server) = listener.accept().await);
+	let_assert!(Ok(peer) = client.peer_addr());
+	assert!(peer.as_abstract_name() == Some(name));
+}
+
+/// Test changing the backlog of a listener.
+#[tokio::test]
+async fn set_backlog() {
+	let dir = tempdir().unwrap();
+	let path = dir.path().join("listener.sock");
+
+	// A negative backlog is replaced by the default.
+	let_assert!(Ok(mut listener) = UnixSeqpacketListener::bind_with_backlog(&path, -1));
+	let_assert!(Ok(_client) = UnixSeqpacket::connect(&path).await);
+
+	// Queued connections survive a change of the backlog.
+	assert!(let Ok(()) = listener.set_backlog(1024));
+	let_assert!(Ok(_client) = UnixSeqpacket::connect(&path).await);
+	assert!(let Ok(_) = listener.accept().await);
+	assert!(let Ok(_) = listener.accept().await);
+
+	assert!(let Ok(()) = listener.set_backlog(-5));
+	let_assert!(Ok(_client) = UnixSeqpacket::connect(&path).await);
+	assert!(let Ok(_) = listener.accept().await);
+}
@@ -0,0 +1,51 @@
+#![cfg(feature = "bytemuck")]
+
+use assert2::{assert, let_assert};
+use tokio_seqpacket::UnixSeqpacket;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+struct Header {
+	kind: u32,
+	flags: u16,
+	version: u16,
+	length: u64,
+}
+
+// SAFETY: The struct is `repr(C)`, has no padding and all fields are `Pod`.
+unsafe impl bytemuck::Zeroable for Header {}
+unsafe impl bytemuck::Pod for Header {}
+
+/// Test sending and receiving a plain-old-data struct.
+#[tokio::test]
+async fn send_recv_pod() {
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	let header = Header {
+		kind: 7,
+		flags: 0x8001,
+		version: 2,
+		length: 1 << 40,
+	};
+
+	assert!(let Ok(16) = a.send_pod(&header).await);
+	let_assert!(Ok(received) = b.recv_pod::<Header>().await);
+	assert!(received == header);
+}
+
+/// Test that receiving a plain-old-data struct rejects datagrams of the wrong size.
+#[tokio::test]
+async fn recv_pod_wrong_size() {
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+
+	assert!(let Ok(4) = a.send(&[1, 2, 3, 4]).await);
+	let_assert!(Err(e) = b.recv_pod::<Header>().await);
+	assert!(e.kind() == std::io::ErrorKind::InvalidData);
+
+	assert!(let Ok(17) = a.send(&[0; 17]).await);
+	let_assert!(Err(e) = b.recv_pod::<Header>().await);
+	assert!(e.kind() == std::io::ErrorKind::InvalidData);
+
+	// The bad datagrams were discarded.
+	assert!(let Ok(16) = a.send(&[0; 16]).await);
+	assert!(let Ok(_) = b.recv_pod::<Header>().await);
+}
@@ -0,0 +1,31 @@
+use assert2::{assert, let_assert};
+use tokio_seqpacket::{BufferPool, UnixSeqpacket};
+
+/// Test that received messages reuse the buffers of the pool.
+#[tokio::test]
+async fn reuse_buffers() {
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	let pool = BufferPool::new(2, 64);
+
+	// Warm up the pool by holding two messages at the same time.
+	assert!(let Ok(5) = a.send(b"first").await);
+	assert!(let Ok(6) = a.send(b"second").await);
+	let_assert!(Ok(first) = pool.recv_from(&b).await);
+	let_assert!(Ok(second) = pool.recv_from(&b).await);
+	assert!(&*first == b"first");
+	assert!(&*second == b"second");
+	assert!(pool.allocated() == 2);
+	assert!(pool.available() == 0);
+	drop(first);
+	drop(second);
+	assert!(pool.available() == 2);
+
+	for i in 0..10 {
+		let message = format!("message #{i}");
+		assert!(let Ok(_) = a.send(message.as_bytes()).await);
+		let_assert!(Ok(received) = pool.recv_from(&b).await);
+		assert!(&*received == message.as_bytes());
+	}
+	assert!(pool.allocated() == 2);
+	assert!(pool.available() == 2);
+}
@@ -0,0 +1,173 @@
+use assert2::{assert, let_assert};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio_seqpacket::UnixSeqpacket;
+
+/// Test that the watermark callback fires when the receive queue is above the watermark.
+#[tokio::test]
+async fn recv_watermark() {
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+
+	let fired = Arc::new(AtomicUsize::new(0));
+	b.set_recv_watermark(150, {
+		let fired = fired.clone();
+		move |queued| {
+			fired.store(queued, Ordering::Relaxed);
+		}
+	});
+
+	for _ in 0..3 {
+		assert!(let Ok(100) = a.send(&[1; 100]).await);
+	}
+	let_assert!(Ok(queued) = b.recv_queue_len());
+	assert!(queued == 300);
+
+	// Two datagrams are left in the queue, which is above the watermark.
+	let mut buffer = [0u8; 128];
+	assert!(let Ok(100) = b.recv(&mut buffer).await);
+	assert!(fired.swap(0, Ordering::Relaxed) == 200);
+
+	// One datagram is left in the queue, which is below the watermark.
+	assert!(let Ok(100) = b.recv(&mut buffer).await);
+	assert!(fired.load(Ordering::Relaxed) == 0);
+
+	// The watermark is preserved when the socket is suspended and resumed.
+	let_assert!(Ok(b) = b.suspend().resume());
+	for _ in 0..2 {
+		assert!(let Ok(100) = a.send(&[1; 100]).await);
+	}
+	assert!(let Ok(100) = b.recv(&mut buffer).await);
+	assert!(fired.swap(0, Ordering::Relaxed) == 200);
+
+	// A cleared watermark does not fire.
+	b.clear_recv_watermark();
+	assert!(let Ok(100) = a.send(&[1; 100]).await);
+	assert!(let Ok(100) = b.recv(&mut buffer).await);
+	assert!(fired.load(Ordering::Relaxed) == 0);
+}
+
+/// Test waiting until a number of bytes is queued.
+#[tokio::test]
+#[cfg(any(target_os = "android", target_os = "linux"))]
+async fn await_queued_bytes() {
+	use std::time::Duration;
+
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+
+	let sender = tokio::spawn(async move {
+		assert!(let Ok(100) = a.send(&[1; 100]).await);
+		tokio::time::sleep(Duration::from_millis(20)).await;
+		assert!(let Ok(50) = a.send(&[2; 50]).await);
+		a
+	});
+
+	assert!(let Ok(Ok(())) = tokio::time::timeout(Duration::from_secs(1), b.await_queued_bytes(150)).await);
+	let_assert!(Ok(queued) = b.recv_queue_len());
+	assert!(queued == 150);
+
+	// The records are still received separately.
+	let mut buffer = [0u8; 256];
+	assert!(let Ok(100) = b.recv(&mut buffer).await);
+	assert!(let Ok(50) = b.recv(&mut buffer).await);
+
+	// If the peer goes away first, an error is returned.
+	let_assert!(Ok(a) = sender.await);
+	assert!(let Ok(10) = a.send(&[3; 10]).await);
+	drop(a);
+	let_assert!(Err(e) = b.await_queued_bytes(100).await);
+	assert!(e.kind() == std::io::ErrorKind::UnexpectedEof);
+}
+
+/// Test that the peak receive queue length is tracked.
+#[tokio::test]
+#[cfg(any(target_os = "android", target_os = "linux"))]
+async fn peak_recv_queue() {
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	let mut buffer = [0u8; 128];
+
+	// Tracking is off by default.
+	assert!(let Ok(100) = a.send(&[1; 100]).await);
+	assert!(let Ok(100) = b.recv(&mut buffer).await);
+	assert!(b.peak_recv_queue() == 0);
+
+	b.set_track_peak_recv_queue(true);
+	for _ in 0..5 {
+		assert!(let Ok(100) = a.send(&[1; 100]).await);
+	}
+
+	for _ in 0..5 {
+		assert!(let Ok(100) = b.recv(&mut buffer).await);
+	}
+	assert!(b.peak_recv_queue() == 500);
+
+	// A smaller burst does not lower the peak.
+	assert!(let Ok(100) = a.send(&[1; 100]).await);
+	assert!(let Ok(100) = b.recv(&mut buffer).await);
+	assert!(b.peak_recv_queue() == 500);
+}
+
+/// Test that close_gracefully() waits for the peer to receive all datagrams.
+#[tokio::test]
+#[cfg(any(target_os = "android", target_os = "linux"))]
+async fn close_gracefully() {
+	use std::time::{Duration, Instant};
+
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	for i in 0..10u8 {
+		assert!(let Ok(1) = a.send(&[i]).await);
+	}
+
+	let receiver = tokio::spawn(async move {
+		tokio::time::sleep(Duration::from_millis(20)).await;
+		let mut received = Vec::new();
+		let mut buffer = [0u8; 16];
+		loop {
+			let len = b.recv(&mut buffer).await.unwrap();
+			if len == 0 {
+				return received;
+			}
+			received.push(buffer[0]);
+		}
+	});
+
+	assert!(let Ok(()) = a.close_gracefully(Instant::now() + Duration::from_secs(5)).await);
+	let_assert!(Ok(received) = receiver.await);
+	assert!(received == (0..10).collect::<Vec<u8>>());
+}
+
+/// Test that close_gracefully() gives up at the deadline if the peer does not receive.
+#[tokio::test]
+#[cfg(any(target_os = "android", target_os = "linux"))]
+async fn close_gracefully_timeout() {
+	use std::time::{Duration, Instant};
+
+	let_assert!(Ok((a, _b)) = UnixSeqpacket::pair());
+	assert!(let Ok(5) = a.send(b"Hello").await);
+	let_assert!(Err(e) = a.close_gracefully(Instant::now() + Duration::from_millis(20)).await);
+	assert!(e.kind() == std::io::ErrorKind::TimedOut);
+}
+
+/// Test getting the sizes of all queued datagrams.
+#[tokio::test]
+#[cfg(any(target_os = "android", target_os = "linux"))]
+async fn queued_datagram_sizes() {
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	let_assert!(Ok(sizes) = b.queued_datagram_sizes(10));
+	assert!(sizes.is_empty());
+
+	assert!(let Ok(3) = a.send(b"abc").await);
+	assert!(let Ok(11) = a.send(b"hello world").await);
+	assert!(let Ok(2) = a.send(b"xy").await);
+
+	let_assert!(Ok(sizes) = b.queued_datagram_sizes(10));
+	assert!(sizes == [3, 11, 2]);
+	let_assert!(Ok(sizes) = b.queued_datagram_sizes(2));
+	assert!(sizes == [3, 11]);
+
+	// The datagrams are still queued, and the peek offset is restored.
+	assert!(let Ok(None) = b.peek_off());
+	let mut buffer = [0u8; 16];
+	assert!(let Ok(3) = b.recv(&mut buffer).await);
+	assert!(let Ok(11) = b.recv(&mut buffer).await);
+	assert!(let Ok(2) = b.recv(&mut buffer).await);
+}
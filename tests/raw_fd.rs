@@ -17,3 +17,46 @@ async fn send_recv() {
 	assert!(let Ok(12) = b.recv(&mut buffer).await);
 	assert!(&buffer[..12] == b"Hello world!");
 }
+
+/// Test that adopting a socket of the wrong type is rejected.
+#[tokio::test]
+async fn from_raw_fd_rejects_stream_socket() {
+	use std::os::unix::io::IntoRawFd;
+	let_assert!(Ok((a, _b)) = std::os::unix::net::UnixStream::pair());
+	let_assert!(Err(e) = unsafe { UnixSeqpacket::from_raw_fd(a.into_raw_fd()) });
+	assert!(e.kind() == std::io::ErrorKind::InvalidInput);
+}
+
+/// Test the standard `FromRawFd` and `IntoRawFd` traits.
+#[tokio::test]
+async fn raw_fd_traits() {
+	use std::os::unix::io::{FromRawFd, IntoRawFd};
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+
+	let a: UnixSeqpacket = unsafe { FromRawFd::from_raw_fd(IntoRawFd::into_raw_fd(a)) };
+	assert!(let Ok(5) = a.send(b"Hello").await);
+
+	let mut buffer = [0u8; 16];
+	assert!(let Ok(5) = b.recv(&mut buffer).await);
+	assert!(&buffer[..5] == b"Hello");
+}
+
+/// Test that the close-on-exec flag of sockets is reported correctly.
+#[tokio::test]
+async fn is_cloexec() {
+	// Sockets created by the crate have the flag set.
+	let_assert!(Ok((a, _b)) = UnixSeqpacket::pair());
+	assert!(let Ok(true) = a.is_cloexec());
+
+	// An adopted socket keeps the flag it was created with, and `dup()` does not set it.
+	let fd = unsafe { libc::dup(a.as_raw_fd()) };
+	assert!(fd >= 0);
+	let_assert!(Ok(adopted) = unsafe { UnixSeqpacket::from_raw_fd(fd) });
+	assert!(let Ok(false) = adopted.is_cloexec());
+
+	// Changing the flag through the configuration is reflected by the getter.
+	let_assert!(Ok(mut config) = adopted.config());
+	config.close_on_exec = true;
+	assert!(let Ok(()) = adopted.apply_config(&config));
+	assert!(let Ok(true) = adopted.is_cloexec());
+}
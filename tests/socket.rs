@@ -176,3 +176,713 @@ fn multiple_waiters() {
 		assert!(received.load(Ordering::Relaxed) == 2);
 	});
 }
+
+/// Test that ready_any() reports the right readiness.
+#[tokio::test]
+async fn ready_any() {
+	use std::time::Duration;
+	use tokio::time::timeout;
+
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+
+	// A fresh socket is only writable.
+	let_assert!(Ok(ready) = b.ready_any().await);
+	assert!(ready.is_writable());
+	assert!(!ready.is_readable());
+
+	// After receiving data, the socket becomes readable.
+	// The socket is also still writable, so yield to the runtime until it processed the readable event.
+	assert!(let Ok(12) = a.send(b"Hello world!").await);
+	let_assert!(
+		Ok(Ok(ready)) = timeout(Duration::from_secs(1), async {
+			loop {
+				let ready = b.ready_any().await?;
+				if ready.is_readable() {
+					return std::io::Result::Ok(ready);
+				}
+				tokio::task::yield_now().await;
+			}
+		})
+		.await
+	);
+	assert!(ready.is_readable());
+	assert!(ready.is_writable());
+	let mut buffer = [0u8; 1024];
+	assert!(let Ok(12) = b.recv(&mut buffer).await);
+
+	// Fill the send buffer until it blocks.
+	let mut sent = 0;
+	while let Ok(result) = timeout(Duration::from_millis(10), a.send(&[0; 1024])).await {
+		assert!(let Ok(1024) = result);
+		sent += 1;
+	}
+
+	// Now the socket is not readable or writable.
+	assert!(let Err(_) = timeout(Duration::from_millis(10), a.ready_any()).await);
+
+	// After the peer drains the queue, the socket is writable again.
+	for _ in 0..sent {
+		assert!(let Ok(1024) = b.recv(&mut buffer).await);
+	}
+	let_assert!(Ok(Ok(ready)) = timeout(Duration::from_secs(1), a.ready_any()).await);
+	assert!(ready.is_writable());
+	assert!(!ready.is_readable());
+}
+
+/// Test receiving a fixed size header and a variable sized body.
+#[tokio::test]
+async fn recv_header_body() {
+	use std::io::IoSlice;
+
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	let body: Vec<u8> = (0..100).collect();
+	assert!(let Ok(104) = a.send_vectored(&[IoSlice::new(&100u32.to_le_bytes()), IoSlice::new(&body)]).await);
+
+	let mut header = [0u8; 4];
+	let_assert!(Ok((4, received)) = b.recv_header_body(&mut header).await);
+	assert!(u32::from_le_bytes(header) == 100);
+	assert!(received == body);
+
+	// A datagram shorter than the header results in an empty body.
+	assert!(let Ok(2) = a.send(b"Hi").await);
+	let_assert!(Ok((2, received)) = b.recv_header_body(&mut header).await);
+	assert!(&header[..2] == b"Hi");
+	assert!(received.is_empty());
+}
+
+/// Test that passing too many I/O slices gives a clear error.
+#[tokio::test]
+async fn too_many_slices() {
+	use std::io::{IoSlice, IoSliceMut};
+
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	let max = tokio_seqpacket::iov_max();
+	assert!(max >= 16);
+
+	let slices = vec![IoSlice::new(b"a"); max + 1];
+	let_assert!(Err(e) = a.send_vectored(&slices).await);
+	assert!(e.kind() == std::io::ErrorKind::InvalidInput);
+
+	// Exactly the maximum is fine.
+	let_assert!(Ok(sent) = a.send_vectored(&slices[..max]).await);
+	assert!(sent == max);
+
+	let mut buffers = vec![[0u8; 1]; max + 1];
+	let mut slices: Vec<_> = buffers.iter_mut().map(|b| IoSliceMut::new(b)).collect();
+	let_assert!(Err(e) = b.recv_vectored(&mut slices).await);
+	assert!(e.kind() == std::io::ErrorKind::InvalidInput);
+	let_assert!(Ok(received) = b.recv_vectored(&mut slices[..max]).await);
+	assert!(received == max);
+}
+
+/// Test that peeking starts at the peek offset.
+#[cfg(any(target_os = "android", target_os = "linux"))]
+#[tokio::test]
+async fn peek_off() {
+	fn peek(socket: &UnixSeqpacket, buffer: &mut [u8]) -> isize {
+		unsafe {
+			libc::recv(
+				socket.as_raw_fd(),
+				buffer.as_mut_ptr().cast(),
+				buffer.len(),
+				libc::MSG_PEEK,
+			)
+		}
+	}
+
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	assert!(let Ok(None) = b.peek_off());
+	assert!(let Ok(()) = b.set_peek_off(Some(6)));
+	assert!(let Ok(Some(6)) = b.peek_off());
+
+	assert!(let Ok(12) = a.send(b"Hello world!").await);
+	let mut buffer = [0u8; 3];
+	assert!(peek(&b, &mut buffer) == 3);
+	assert!(&buffer == b"wor");
+	assert!(let Ok(Some(9)) = b.peek_off());
+	assert!(peek(&b, &mut buffer) == 3);
+	assert!(&buffer == b"ld!");
+
+	// A normal receive still starts at the beginning, and moves the offset back.
+	let mut buffer = [0u8; 128];
+	assert!(let Ok(12) = b.recv(&mut buffer).await);
+	assert!(&buffer[..12] == b"Hello world!");
+	assert!(let Ok(Some(0)) = b.peek_off());
+
+	assert!(let Ok(()) = b.set_peek_off(None));
+	assert!(let Ok(None) = b.peek_off());
+}
+
+/// Test that the sender address of a connected socket is unnamed.
+#[tokio::test]
+async fn recv_from_unnamed() {
+	use std::io::IoSliceMut;
+	use tokio_seqpacket::ancillary::SocketAncillary;
+
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	assert!(let Ok(12) = a.send(b"Hello world!").await);
+
+	let mut buffer = [0u8; 128];
+	let mut ancillary = SocketAncillary::new(&mut []);
+	let_assert!(
+		Ok((12, address)) = b
+			.recv_vectored_with_ancillary_from(&mut [IoSliceMut::new(&mut buffer)], &mut ancillary)
+			.await
+	);
+	assert!(&buffer[..12] == b"Hello world!");
+	assert!(address.is_unnamed());
+	assert!(address.as_pathname() == None);
+	assert!(format!("{:?}", address) == "(unnamed)");
+}
+
+/// Test measuring the round-trip time to an echo server.
+#[tokio::test]
+async fn ping() {
+	use std::time::Duration;
+
+	let_assert!(Ok((client, server)) = UnixSeqpacket::pair());
+	let echo = tokio::spawn(async move {
+		let mut buffer = [0u8; 128];
+		for _ in 0..3 {
+			let_assert!(Ok(len) = server.recv(&mut buffer).await);
+			// Send some noise first, which must be ignored.
+			assert!(let Ok(5) = server.send(b"noise").await);
+			assert!(let Ok(_) = server.send(&buffer[..len]).await);
+		}
+		server
+	});
+
+	for _ in 0..3 {
+		let_assert!(Ok(rtt) = client.ping(b"are you there?", Duration::from_secs(1)).await);
+		assert!(rtt < Duration::from_millis(100));
+	}
+
+	// After the echo task stops, the ping times out.
+	let_assert!(Ok(_server) = echo.await);
+	let_assert!(Err(e) = client.ping(b"hello?", Duration::from_millis(10)).await);
+	assert!(e.kind() == std::io::ErrorKind::TimedOut);
+}
+
+/// Test that small sends are coalesced and sent together after the window.
+#[tokio::test(start_paused = true)]
+async fn coalesce_sends() {
+	use std::time::Duration;
+	use tokio_seqpacket::CoalescingSocket;
+
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	let a = CoalescingSocket::new(a, Duration::from_millis(50));
+
+	for i in 0..5u8 {
+		assert!(let Ok(1) = a.send(&[i]).await);
+	}
+	assert!(a.queued() == 5);
+
+	// Nothing has been sent before the window has passed.
+	tokio::time::advance(Duration::from_millis(49)).await;
+	assert!(a.queued() == 5);
+	assert!(let Ok(0) = b.recv_queue_len());
+
+	// After the window, all datagrams arrive together, in order and still separate.
+	tokio::time::advance(Duration::from_millis(1)).await;
+	let mut buffer = [0u8; 16];
+	for i in 0..5u8 {
+		assert!(let Ok(1) = b.recv(&mut buffer).await);
+		assert!(buffer[0] == i);
+	}
+	assert!(a.queued() == 0);
+
+	// An explicit flush sends queued datagrams immediately.
+	assert!(let Ok(3) = a.send(b"foo").await);
+	assert!(let Ok(()) = a.flush().await);
+	assert!(let Ok(3) = b.recv_queue_len());
+	assert!(let Ok(3) = b.recv(&mut buffer).await);
+
+	// Unwrapping the socket sends the remaining datagrams.
+	assert!(let Ok(3) = a.send(b"bar").await);
+	let_assert!(Ok(a) = a.into_inner().await);
+	assert!(let Ok(3) = b.recv(&mut buffer).await);
+	assert!(&buffer[..3] == b"bar");
+	assert!(let Ok(3) = a.send(b"baz").await);
+	assert!(let Ok(3) = b.recv(&mut buffer).await);
+}
+
+/// Test that datagrams stay queued if flushing fails.
+#[tokio::test(start_paused = true)]
+async fn coalesce_flush_error() {
+	use std::time::Duration;
+	use tokio_seqpacket::CoalescingSocket;
+
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	let a = CoalescingSocket::new(a, Duration::from_millis(50));
+	drop(b);
+
+	assert!(let Ok(3) = a.send(b"foo").await);
+	let_assert!(Err(e) = a.flush().await);
+	assert!(e.kind() == std::io::ErrorKind::BrokenPipe);
+	assert!(a.queued() == 1);
+
+	// A failed background flush is reported by the next send, which does not queue the datagram.
+	tokio::time::advance(Duration::from_millis(50)).await;
+	tokio::task::yield_now().await;
+	let_assert!(Err(e) = a.send(b"bar").await);
+	assert!(e.kind() == std::io::ErrorKind::BrokenPipe);
+	assert!(a.queued() == 1);
+}
+
+/// Test attaching user data to a socket.
+#[tokio::test]
+async fn user_data() {
+	#[derive(Debug, PartialEq)]
+	struct Connection {
+		id: u32,
+		user: &'static str,
+	}
+
+	let_assert!(Ok((a, _b)) = UnixSeqpacket::pair());
+	assert!(a.user_data::<Connection>().is_none());
+
+	a.set_user_data(Connection { id: 7, user: "root" });
+	let_assert!(Some(connection) = a.user_data::<Connection>());
+	assert!(*connection == Connection { id: 7, user: "root" });
+	assert!(a.user_data::<u32>().is_none());
+
+	// The user data is preserved when the socket is suspended.
+	let_assert!(Ok(a) = a.suspend().resume());
+	let_assert!(Some(connection) = a.user_data::<Connection>());
+	assert!(connection.id == 7);
+
+	a.clear_user_data();
+	assert!(a.user_data::<Connection>().is_none());
+}
+
+/// Test getting the inode number of a socket.
+#[tokio::test]
+async fn inode() {
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	let_assert!(Ok(inode_a) = a.inode());
+	let_assert!(Ok(inode_b) = b.inode());
+	assert!(inode_a != 0);
+	assert!(inode_b != inode_a);
+	assert!(a.inode().ok() == Some(inode_a));
+
+	// On Linux, the inode number is visible in the link of the file descriptor.
+	#[cfg(any(target_os = "android", target_os = "linux"))]
+	{
+		let_assert!(Ok(link) = std::fs::read_link(format!("/proc/self/fd/{}", a.as_raw_fd())));
+		assert!(link.to_str() == Some(format!("socket:[{}]", inode_a).as_str()));
+	}
+}
+
+/// Test that keepalives are skipped by recv_skip_keepalive().
+#[tokio::test]
+#[cfg(any(target_os = "android", target_os = "linux"))]
+async fn skip_keepalive() {
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+
+	assert!(let Ok(()) = a.send_control_only().await);
+	assert!(let Ok(5) = a.send(b"Hello").await);
+	assert!(let Ok(()) = a.send_control_only().await);
+	assert!(let Ok(()) = a.send_control_only().await);
+	assert!(let Ok(5) = a.send(b"world").await);
+
+	let mut buffer = [0u8; 16];
+	assert!(let Ok(5) = b.recv_skip_keepalive(&mut buffer).await);
+	assert!(&buffer[..5] == b"Hello");
+	assert!(let Ok(5) = b.recv_skip_keepalive(&mut buffer).await);
+	assert!(&buffer[..5] == b"world");
+
+	// A keepalive does not look like the end of the connection.
+	assert!(let Ok(()) = a.send_control_only().await);
+	let recv = b.recv_skip_keepalive(&mut buffer);
+	assert!(let Err(_) = tokio::time::timeout(std::time::Duration::from_millis(20), recv).await);
+
+	// But closing the connection does.
+	drop(a);
+	assert!(let Ok(0) = b.recv_skip_keepalive(&mut buffer).await);
+}
+
+/// Test that data queued behind a keepalive is not lost when the peer hangs up.
+#[tokio::test]
+#[cfg(any(target_os = "android", target_os = "linux"))]
+async fn skip_keepalive_after_hangup() {
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+
+	assert!(let Ok(()) = a.send_control_only().await);
+	assert!(let Ok(5) = a.send(b"Hello").await);
+	assert!(let Ok(()) = a.send_control_only().await);
+	drop(a);
+
+	let mut buffer = [0u8; 16];
+	assert!(let Ok(5) = b.recv_skip_keepalive(&mut buffer).await);
+	assert!(&buffer[..5] == b"Hello");
+	assert!(let Ok(0) = b.recv_skip_keepalive(&mut buffer).await);
+}
+
+/// Test waiting for room in the send buffer.
+#[tokio::test]
+#[cfg(any(target_os = "android", target_os = "linux"))]
+async fn await_send_capacity() {
+	use std::time::Duration;
+
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	assert!(let Ok(()) = a.await_send_capacity(4096).await);
+
+	// Fill the send buffer.
+	let mut sent = 0;
+	while let Ok(result) = tokio::time::timeout(Duration::from_millis(10), a.send(&[0; 4096])).await {
+		assert!(let Ok(4096) = result);
+		sent += 1;
+	}
+	assert!(sent > 0);
+
+	let wait = a.await_send_capacity(4096);
+	tokio::pin!(wait);
+	assert!(let Err(_) = tokio::time::timeout(Duration::from_millis(20), &mut wait).await);
+
+	// Drain the buffer from the other side.
+	let mut buffer = [0; 4096];
+	for _ in 0..sent {
+		assert!(let Ok(4096) = b.recv(&mut buffer).await);
+	}
+	assert!(let Ok(Ok(())) = tokio::time::timeout(Duration::from_secs(1), wait).await);
+
+	// A datagram larger than the send buffer can never fit.
+	let_assert!(Err(e) = a.await_send_capacity(usize::MAX).await);
+	assert!(e.kind() == std::io::ErrorKind::InvalidInput);
+}
+
+/// Test receiving a bounded batch of datagrams.
+#[tokio::test]
+async fn recv_up_to() {
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	for i in 0..10u8 {
+		assert!(let Ok(1) = a.send(&[i]).await);
+	}
+
+	let mut buffer = [0u8; 16];
+	let mut received = Vec::new();
+	assert!(let Ok(4) = b.recv_up_to(&mut buffer, 4, |data| received.extend_from_slice(data)).await);
+	assert!(received == [0, 1, 2, 3]);
+	assert!(let Ok(4) = b.recv_up_to(&mut buffer, 4, |data| received.extend_from_slice(data)).await);
+	assert!(received == [0, 1, 2, 3, 4, 5, 6, 7]);
+
+	// Only two datagrams are left, so the batch ends early.
+	assert!(let Ok(2) = b.recv_up_to(&mut buffer, 4, |data| received.extend_from_slice(data)).await);
+	assert!(received == [0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+}
+
+/// Test that oversized datagrams are rejected and discarded.
+#[tokio::test]
+#[cfg(any(target_os = "android", target_os = "linux"))]
+async fn max_payload() {
+	use tokio_seqpacket::PayloadTooLarge;
+
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	b.set_max_payload(8);
+	assert!(b.max_payload() == 8);
+
+	assert!(let Ok(16) = a.send(&[1; 16]).await);
+	assert!(let Ok(8) = a.send(&[2; 8]).await);
+
+	let mut buffer = [0u8; 64];
+	let_assert!(Err(e) = b.recv(&mut buffer).await);
+	assert!(e.kind() == std::io::ErrorKind::InvalidData);
+	let_assert!(Some(too_large) = e.get_ref().and_then(|e| e.downcast_ref::<PayloadTooLarge>()));
+	assert!(too_large.size() == 16);
+	assert!(too_large.max() == 8);
+
+	// The oversized datagram was consumed, so the next one is received normally.
+	assert!(let Ok(8) = b.recv(&mut buffer).await);
+	assert!(buffer[..8] == [2; 8]);
+
+	// The limit also applies to receiving into a growing buffer.
+	assert!(let Ok(32) = a.send(&[3; 32]).await);
+	let_assert!(Err(e) = b.recv_header_body(&mut [0; 4]).await);
+	assert!(e.kind() == std::io::ErrorKind::InvalidData);
+	assert!(let Ok(0) = b.recv_queue_len());
+}
+
+/// Test that using an unconnected socket reports `NotConnected`.
+#[tokio::test]
+async fn not_connected() {
+	let fd = unsafe {
+		libc::socket(
+			libc::AF_UNIX,
+			libc::SOCK_SEQPACKET | libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC,
+			0,
+		)
+	};
+	assert!(fd >= 0);
+	let_assert!(Ok(socket) = unsafe { UnixSeqpacket::from_raw_fd(fd) });
+
+	let_assert!(Err(e) = socket.send(b"Hello?").await);
+	assert!(e.kind() == std::io::ErrorKind::NotConnected);
+
+	let mut buffer = [0u8; 16];
+	let_assert!(Err(e) = socket.recv(&mut buffer).await);
+	assert!(e.kind() == std::io::ErrorKind::NotConnected);
+}
+
+/// Test that both ends of a socket pair are set up correctly and can be used from different tasks.
+#[tokio::test]
+async fn pair() {
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	for socket in [&a, &b] {
+		let fd = socket.as_raw_fd();
+		let mut socket_type: libc::c_int = 0;
+		let mut len = std::mem::size_of::<libc::c_int>() as libc::socklen_t;
+		let ret = unsafe {
+			libc::getsockopt(
+				fd,
+				libc::SOL_SOCKET,
+				libc::SO_TYPE,
+				&mut socket_type as *mut libc::c_int as *mut libc::c_void,
+				&mut len,
+			)
+		};
+		assert!(ret == 0);
+		assert!(socket_type == libc::SOCK_SEQPACKET);
+		assert!(unsafe { libc::fcntl(fd, libc::F_GETFD) } & libc::FD_CLOEXEC != 0);
+		assert!(unsafe { libc::fcntl(fd, libc::F_GETFL) } & libc::O_NONBLOCK != 0);
+	}
+
+	let echo = tokio::spawn(async move {
+		let mut buffer = [0u8; 16];
+		let_assert!(Ok(len) = b.recv(&mut buffer).await);
+		assert!(let Ok(_) = b.send(&buffer[..len]).await);
+	});
+
+	assert!(let Ok(5) = a.send(b"Hello").await);
+	let mut buffer = [0u8; 16];
+	assert!(let Ok(5) = a.recv(&mut buffer).await);
+	assert!(&buffer[..5] == b"Hello");
+	assert!(let Ok(()) = echo.await);
+}
+
+/// Test that recv_sized() reports the full size of truncated datagrams.
+#[tokio::test]
+#[cfg(any(target_os = "android", target_os = "linux"))]
+async fn recv_sized() {
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+
+	assert!(let Ok(200) = a.send(&[7; 200]).await);
+	let mut buffer = [0u8; 50];
+	let_assert!(Ok(received) = b.recv_sized(&mut buffer).await);
+	assert!(received.copied == 50);
+	assert!(received.full_len == 200);
+	assert!(received.is_truncated());
+	assert!(buffer == [7; 50]);
+
+	assert!(let Ok(20) = a.send(&[8; 20]).await);
+	let_assert!(Ok(received) = b.recv_sized(&mut buffer).await);
+	assert!(received.copied == 20);
+	assert!(received.full_len == 20);
+	assert!(!received.is_truncated());
+}
+
+/// Test that recv_if_fits() leaves datagrams that are too large in the queue.
+#[tokio::test]
+#[cfg(any(target_os = "android", target_os = "linux"))]
+async fn recv_if_fits() {
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+
+	assert!(let Ok(200) = a.send(&[7; 200]).await);
+	assert!(let Ok(20) = a.send(&[8; 20]).await);
+
+	let mut small = [0u8; 50];
+	assert!(let Ok(None) = b.recv_if_fits(&mut small).await);
+	assert!(let Ok(None) = b.recv_if_fits(&mut small).await);
+
+	let mut large = [0u8; 200];
+	assert!(let Ok(Some(200)) = b.recv_if_fits(&mut large).await);
+	assert!(large == [7; 200]);
+
+	assert!(let Ok(Some(20)) = b.recv_if_fits(&mut small).await);
+	assert!(small[..20] == [8; 20]);
+}
+
+/// Test that peeking does not remove the datagram from the queue.
+#[tokio::test]
+async fn peek() {
+	use std::time::Duration;
+
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	assert!(let Ok(12) = a.send(b"Hello world!").await);
+
+	let mut header = [0u8; 5];
+	assert!(let Ok(5) = b.peek(&mut header).await);
+	assert!(&header == b"Hello");
+
+	// The socket stays readable, so peeking again does not block.
+	let mut buffer = [0u8; 128];
+	assert!(let Ok(Ok(12)) = tokio::time::timeout(Duration::from_secs(1), b.peek(&mut buffer)).await);
+	assert!(&buffer[..12] == b"Hello world!");
+
+	assert!(let Ok(12) = b.recv(&mut buffer).await);
+	assert!(&buffer[..12] == b"Hello world!");
+	assert!(let Err(_) = tokio::time::timeout(Duration::from_millis(10), b.peek(&mut buffer)).await);
+}
+
+/// Test that errors of a labeled socket mention the label.
+#[tokio::test]
+#[cfg(any(target_os = "android", target_os = "linux"))]
+async fn labeled_errors() {
+	use tokio_seqpacket::LabeledError;
+
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	b.set_label("worker-3");
+	assert!(b.label().as_deref() == Some("worker-3"));
+	b.set_max_payload(4);
+
+	assert!(let Ok(12) = a.send(b"Hello world!").await);
+	let mut buffer = [0u8; 128];
+	let_assert!(Err(e) = b.recv(&mut buffer).await);
+	assert!(e.kind() == std::io::ErrorKind::InvalidData);
+	assert!(e.to_string().starts_with("recv failed on connection 'worker-3': "));
+	let_assert!(Some(labeled) = e.get_ref().and_then(|e| e.downcast_ref::<LabeledError>()));
+	assert!(labeled.operation() == "recv");
+	assert!(labeled.label() == "worker-3");
+
+	// Without a label, the original error is returned as-is.
+	b.clear_label();
+	assert!(let Ok(12) = a.send(b"Hello world!").await);
+	let_assert!(Err(e) = b.recv(&mut buffer).await);
+	assert!(!e.to_string().contains("worker-3"));
+}
+
+/// Test that recv_or_eof() tells data, empty datagrams and the end of the connection apart.
+#[tokio::test]
+#[cfg(any(target_os = "android", target_os = "linux"))]
+async fn recv_or_eof() {
+	use tokio_seqpacket::RecvState;
+
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	let mut buffer = [0u8; 16];
+
+	assert!(let Ok(5) = a.send(b"Hello").await);
+	assert!(let Ok(RecvState::Data(5)) = b.recv_or_eof(&mut buffer).await);
+	assert!(&buffer[..5] == b"Hello");
+
+	assert!(let Ok(0) = a.send(b"").await);
+	assert!(let Ok(RecvState::EmptyDatagram) = b.recv_or_eof(&mut buffer).await);
+
+	// Datagrams queued before the peer hung up are still received.
+	assert!(let Ok(0) = a.send(b"").await);
+	assert!(let Ok(5) = a.send(b"world").await);
+	drop(a);
+	assert!(let Ok(RecvState::EmptyDatagram) = b.recv_or_eof(&mut buffer).await);
+	assert!(let Ok(RecvState::Data(5)) = b.recv_or_eof(&mut buffer).await);
+	assert!(&buffer[..5] == b"world");
+
+	assert!(let Ok(RecvState::Eof) = b.recv_or_eof(&mut buffer).await);
+	assert!(let Ok(RecvState::Eof) = b.recv_or_eof(&mut buffer).await);
+}
+
+/// Test half-closing a connection with shutdown().
+#[tokio::test]
+async fn shutdown_write() {
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	assert!(let Ok(()) = a.shutdown(std::net::Shutdown::Write));
+
+	let_assert!(Err(e) = a.send(b"Hello").await);
+	assert!(e.kind() == std::io::ErrorKind::BrokenPipe);
+
+	let mut buffer = [0u8; 16];
+	assert!(let Ok(0) = b.recv(&mut buffer).await);
+
+	// The other direction still works.
+	assert!(let Ok(5) = b.send(b"reply").await);
+	assert!(let Ok(5) = a.recv(&mut buffer).await);
+	assert!(&buffer[..5] == b"reply");
+}
+
+/// Test the readiness-based polling functions with non-blocking sends and receives.
+#[tokio::test]
+async fn poll_ready_try_io() {
+	use std::future::poll_fn;
+	use std::io::{IoSlice, IoSliceMut};
+	use tokio_seqpacket::ancillary::SocketAncillary;
+
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	let mut buffer = [0u8; 16];
+
+	// Nothing to receive yet.
+	let_assert!(Err(e) = b.try_recv(&mut buffer));
+	assert!(e.kind() == std::io::ErrorKind::WouldBlock);
+
+	assert!(let Ok(()) = poll_fn(|cx| a.poll_send_ready(cx)).await);
+	assert!(let Ok(5) = a.try_send(b"Hello"));
+	assert!(let Ok(5) = a.try_send_vectored_with_ancillary(&[IoSlice::new(b"wor"), IoSlice::new(b"ld")], &mut SocketAncillary::new(&mut [])));
+
+	// Message boundaries are preserved.
+	assert!(let Ok(()) = poll_fn(|cx| b.poll_recv_ready(cx)).await);
+	assert!(let Ok(5) = b.try_recv(&mut buffer));
+	assert!(&buffer[..5] == b"Hello");
+	let mut control = [0; 64];
+	let mut ancillary = SocketAncillary::new(&mut control);
+	assert!(let Ok(5) = b.try_recv_vectored_with_ancillary(&mut [IoSliceMut::new(&mut buffer)], &mut ancillary));
+	assert!(&buffer[..5] == b"world");
+
+	let_assert!(Err(e) = b.try_recv(&mut buffer));
+	assert!(e.kind() == std::io::ErrorKind::WouldBlock);
+
+	// After a WouldBlock, polling for readiness waits for new data.
+	let ready = poll_fn(|cx| b.poll_recv_ready(cx));
+	assert!(let Err(_) = tokio::time::timeout(std::time::Duration::from_millis(20), ready).await);
+	assert!(let Ok(1) = a.send(b"!").await);
+	assert!(let Ok(Ok(())) = tokio::time::timeout(std::time::Duration::from_secs(1), poll_fn(|cx| b.poll_recv_ready(cx))).await);
+	assert!(let Ok(1) = b.try_recv(&mut buffer));
+}
+
+/// Test that the non-blocking functions do not steal the wake-up of a task that is polling the socket.
+#[tokio::test]
+async fn try_recv_keeps_pending_waker() {
+	use std::future::poll_fn;
+	use std::sync::Arc;
+	use std::time::Duration;
+
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	let b = Arc::new(b);
+	let receiver = tokio::spawn({
+		let b = b.clone();
+		async move {
+			let mut buffer = [0u8; 16];
+			poll_fn(|cx| b.poll_recv(cx, &mut buffer)).await
+		}
+	});
+	tokio::time::sleep(Duration::from_millis(10)).await;
+
+	let mut buffer = [0u8; 16];
+	let_assert!(Err(e) = b.try_recv(&mut buffer));
+	assert!(e.kind() == std::io::ErrorKind::WouldBlock);
+
+	assert!(let Ok(5) = a.send(b"Hello").await);
+	assert!(let Ok(Ok(Ok(5))) = tokio::time::timeout(Duration::from_secs(1), receiver).await);
+}
+
+/// Test request-reply calls.
+#[tokio::test]
+async fn call() {
+	use std::time::Duration;
+
+	let_assert!(Ok((client, server)) = UnixSeqpacket::pair());
+	let echo = tokio::spawn(async move {
+		let mut buffer = [0u8; 128];
+		for _ in 0..2 {
+			let_assert!(Ok(len) = server.recv(&mut buffer).await);
+			assert!(let Ok(_) = server.send(&buffer[..len]).await);
+		}
+		server
+	});
+
+	let mut response = [0u8; 128];
+	assert!(let Ok(5) = client.call(b"first", &mut response, Duration::from_secs(1)).await);
+	assert!(&response[..5] == b"first");
+	assert!(let Ok(6) = client.call(b"second", &mut response, Duration::from_secs(1)).await);
+	assert!(&response[..6] == b"second");
+
+	// The server no longer replies, so the call times out.
+	let_assert!(Ok(_server) = echo.await);
+	let_assert!(Err(e) = client.call(b"third", &mut response, Duration::from_millis(10)).await);
+	assert!(e.kind() == std::io::ErrorKind::TimedOut);
+}
@@ -21,3 +21,69 @@ async fn send_recv() {
 	let_assert!(Ok(len) = read_a.recv(&mut buffer).await);
 	assert!(&buffer[..len] == b"Hello A!");
 }
+
+/// Test sending and receiving through restricted handles.
+#[tokio::test]
+async fn sender_receiver() {
+	use std::sync::Arc;
+
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	let a = Arc::new(a);
+	let b = Arc::new(b);
+
+	let sender = a.sender();
+	let receiver = b.receiver();
+
+	let task = tokio::spawn({
+		let sender = sender.clone();
+		async move {
+			assert!(let Ok(_) = sender.send(b"Hello from a task!").await);
+		}
+	});
+	assert!(let Ok(()) = task.await);
+	assert!(let Ok(_) = sender.send(b"Hello B!").await);
+
+	let mut buffer = [0u8; 128];
+	let_assert!(Ok(len) = receiver.recv(&mut buffer).await);
+	assert!(&buffer[..len] == b"Hello from a task!");
+	let_assert!(Ok(len) = receiver.clone().recv(&mut buffer).await);
+	assert!(&buffer[..len] == b"Hello B!");
+}
+
+/// Test splitting a socket into owned halves and reuniting them.
+#[tokio::test]
+async fn into_split_reunite() {
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	let (sender, receiver) = a.into_split();
+
+	let task = tokio::spawn(async move {
+		assert!(let Ok(_) = sender.send(b"Hello B!").await);
+		sender
+	});
+	let_assert!(Ok(sender) = task.await);
+
+	let mut buffer = [0u8; 128];
+	let_assert!(Ok(len) = b.recv(&mut buffer).await);
+	assert!(&buffer[..len] == b"Hello B!");
+
+	// Halves of different sockets can not be reunited.
+	let (other_sender, other_receiver) = b.into_split();
+	let_assert!(Err(e) = sender.reunite(other_receiver));
+	let (sender, other_receiver) = e.into_parts();
+
+	// Halves can not be reunited while other handles exist.
+	let extra = receiver.clone();
+	let_assert!(Err(e) = receiver.reunite(sender));
+	let (sender, receiver) = e.into_parts();
+	drop(extra);
+
+	let_assert!(Ok(a) = sender.reunite(receiver));
+	let_assert!(Ok(b) = other_receiver.reunite(other_sender));
+	assert!(let Ok(_) = b.send(b"Hello A!").await);
+	let_assert!(Ok(len) = a.recv(&mut buffer).await);
+	assert!(&buffer[..len] == b"Hello A!");
+
+	// Dropping the socket closes it.
+	drop(a);
+	assert!(let Ok(0) = b.recv(&mut buffer).await);
+}
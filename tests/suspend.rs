@@ -0,0 +1,25 @@
+use assert2::{assert, let_assert};
+use tokio_seqpacket::UnixSeqpacket;
+
+/// Test suspending a socket, using it in blocking mode and resuming it.
+#[tokio::test]
+async fn suspend_resume() {
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	assert!(let Ok(12) = a.send(b"Hello world!").await);
+
+	let b = b.suspend();
+	assert!(let Ok(()) = b.set_nonblocking(false));
+	let mut buffer = [0u8; 128];
+	assert!(let Ok(12) = b.recv(&mut buffer));
+	assert!(&buffer[..12] == b"Hello world!");
+
+	let_assert!(Ok(b) = b.resume());
+	assert!(let Ok(8) = b.send(b"Goodbye!").await);
+	assert!(let Ok(8) = a.recv(&mut buffer).await);
+	assert!(&buffer[..8] == b"Goodbye!");
+
+	// The resumed socket is non-blocking again.
+	let b = b.suspend();
+	let_assert!(Err(e) = b.recv(&mut buffer));
+	assert!(e.kind() == std::io::ErrorKind::WouldBlock);
+}
@@ -0,0 +1,84 @@
+#![cfg(feature = "tracing")]
+
+use assert2::{assert, let_assert};
+use std::sync::{Arc, Mutex};
+use tokio_seqpacket::UnixSeqpacket;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Metadata, Subscriber};
+
+/// The recorded fields of an event.
+type Fields = Vec<(String, String)>;
+
+/// A subscriber that records all warning events.
+#[derive(Default, Clone)]
+struct WarningCollector {
+	events: Arc<Mutex<Vec<Fields>>>,
+}
+
+impl Subscriber for WarningCollector {
+	fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+		*metadata.level() <= Level::WARN
+	}
+
+	fn new_span(&self, _span: &Attributes<'_>) -> Id {
+		Id::from_u64(1)
+	}
+
+	fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+	fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+	fn event(&self, event: &Event<'_>) {
+		let mut fields = FieldCollector::default();
+		event.record(&mut fields);
+		self.events.lock().unwrap().push(fields.0);
+	}
+
+	fn enter(&self, _span: &Id) {}
+
+	fn exit(&self, _span: &Id) {}
+}
+
+#[derive(Default)]
+struct FieldCollector(Fields);
+
+impl Visit for FieldCollector {
+	fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+		self.0.push((field.name().to_string(), format!("{:?}", value)));
+	}
+}
+
+/// Test that a warning is emitted when a received message is truncated.
+#[tokio::test]
+async fn warn_on_truncation() {
+	let collector = WarningCollector::default();
+	let _guard = tracing::subscriber::set_default(collector.clone());
+
+	let_assert!(Ok((a, b)) = UnixSeqpacket::pair());
+	assert!(let Ok(12) = a.send(b"Hello world!").await);
+	assert!(let Ok(12) = a.send(b"Hello world!").await);
+
+	// Receiving the full message should not emit a warning.
+	let mut buffer = [0u8; 12];
+	assert!(let Ok(12) = b.recv(&mut buffer).await);
+	assert!(collector.events.lock().unwrap().len() == 0);
+
+	// Receiving a partial message should.
+	let mut buffer = [0u8; 5];
+	assert!(let Ok(5) = b.recv(&mut buffer).await);
+
+	let events = collector.events.lock().unwrap();
+	assert!(events.len() == 1);
+	let field = |name: &str| {
+		events[0]
+			.iter()
+			.find(|(key, _)| key == name)
+			.map(|(_, value)| value.as_str())
+	};
+	assert!(field("message") == Some("received message was truncated"));
+	assert!(field("data_truncated") == Some("true"));
+	assert!(field("ancillary_truncated") == Some("false"));
+	assert!(field("received_bytes") == Some("5"));
+	assert!(field("received_fds") == Some("0"));
+}